@@ -0,0 +1,561 @@
+use std::mem;
+
+use crate::trie::{NodeLayout, Trie, TriePathBuilder};
+use crate::trie_node::TrieNode;
+
+const CANDIDATE_ARITIES: [usize; 5] = [2, 4, 16, 64, 256];
+
+/// Estimated cost of storing the same keys with another arity and node layout.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LayoutEstimate {
+    pub arity: usize,
+    pub layout: NodeLayout,
+    pub nodes: usize,
+    pub est_bytes: usize,
+    pub avg_lookup_steps: f64,
+}
+
+/// Structural statistics of a trie, as returned by `Trie::analyze`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrieAnalysis {
+    pub nodes: usize,
+    pub values: usize,
+    pub leaves: usize,
+    pub max_depth: usize,
+    pub avg_value_depth: f64,
+    // key_lengths[d] is the number of keys whose path has length d.
+    pub key_lengths: Vec<usize>,
+    // branching[k] is the number of nodes with exactly k children.
+    pub branching: Vec<usize>,
+    // Runs of valueless single-child nodes, which path compression would collapse.
+    pub chains: usize,
+    pub chain_nodes: usize,
+    pub longest_chain: usize,
+    // chain_runs[k] is the number of chains made of exactly k nodes.
+    pub chain_runs: Vec<usize>,
+    // Empty unless N is a power of two, since keys are re-sliced bitwise.
+    pub estimates: Vec<LayoutEstimate>,
+}
+
+/// One bit per fixed-width path prefix, numbered by reading the prefix as a base-`N` number.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OccupancyBitmap {
+    words: Vec<u64>,
+    bits: usize,
+}
+
+impl OccupancyBitmap {
+    // Number of prefixes covered, occupied or not.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.bits
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.bits == 0
+    }
+
+    #[must_use]
+    pub fn get(&self, slot: usize) -> bool {
+        slot < self.bits && self.words[slot / 64] & (1 << (slot % 64)) != 0
+    }
+
+    #[must_use]
+    pub fn count_ones(&self) -> usize {
+        self.words.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    // Little-endian bit order within each word, for shipping the bitmap elsewhere.
+    #[must_use]
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    pub fn iter_ones(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.bits).filter(|&slot| self.get(slot))
+    }
+}
+
+impl TrieAnalysis {
+    #[must_use]
+    pub fn smallest(&self) -> Option<&LayoutEstimate> {
+        self.estimates.iter().min_by_key(|estimate| estimate.est_bytes)
+    }
+}
+
+impl<K: ?Sized, T, const N: usize> Trie<K, T, N> {
+    #[must_use]
+    pub fn analyze(&self) -> TrieAnalysis {
+        let bits = N.is_power_of_two().then(|| N.trailing_zeros() as usize).filter(|&bits| bits > 0);
+        let mut analysis = TrieAnalysis {
+            nodes: 0,
+            values: 0,
+            leaves: 0,
+            max_depth: 0,
+            avg_value_depth: 0.0,
+            key_lengths: Vec::new(),
+            branching: vec![0; N + 1],
+            chains: 0,
+            chain_nodes: 0,
+            longest_chain: 0,
+            chain_runs: Vec::new(),
+            estimates: Vec::new(),
+        };
+        // Nodes an arity of 2^b would need, indexed by b; each bit prefix whose length is a
+        // multiple of b is one node.
+        let mut arity_nodes = [1usize; 9];
+        let mut value_depth_total = 0;
+
+        let mut stack = vec![(&self.root, 0, 0)];
+        while let Some((node, depth, chain)) = stack.pop() {
+            let children = node.count_children();
+            analysis.nodes += 1;
+            analysis.max_depth = analysis.max_depth.max(depth);
+            analysis.branching[children] += 1;
+            if node.value().is_some() {
+                analysis.values += 1;
+                value_depth_total += depth;
+                bump(&mut analysis.key_lengths, depth);
+            }
+            if children == 0 {
+                analysis.leaves += 1;
+            }
+            let in_chain = children == 1 && node.value().is_none() && depth > 0;
+            if !in_chain && chain > 0 {
+                bump(&mut analysis.chain_runs, chain);
+            }
+            let chain = if in_chain { chain + 1 } else { 0 };
+            if in_chain {
+                analysis.chain_nodes += 1;
+                if chain == 1 {
+                    analysis.chains += 1;
+                }
+                analysis.longest_chain = analysis.longest_chain.max(chain);
+            }
+
+            if let Some(bits) = bits {
+                let offset = depth * bits;
+                for (target, count) in arity_nodes.iter_mut().enumerate().skip(1) {
+                    for j in 1..=bits {
+                        if (offset + j) % target == 0 {
+                            let mut prefixes: Vec<usize> = node.child_indices().map(|index| index >> (bits - j)).collect();
+                            prefixes.dedup();
+                            *count += prefixes.len();
+                        }
+                    }
+                }
+            }
+
+            for index in node.child_indices() {
+                stack.push((node.child(index).unwrap(), depth + 1, chain));
+            }
+        }
+
+        while analysis.branching.len() > 1 && analysis.branching.last() == Some(&0) {
+            analysis.branching.pop();
+        }
+        if analysis.values > 0 {
+            analysis.avg_value_depth = value_depth_total as f64 / analysis.values as f64;
+        }
+        if let Some(bits) = bits {
+            analysis.estimates = estimates::<T, N>(&analysis, &arity_nodes, bits);
+        }
+        analysis
+    }
+
+    // The `key_lengths` part of `analyze`, measured in path indices rather than key units.
+    #[must_use]
+    pub fn key_length_histogram(&self) -> Vec<usize> {
+        self.analyze().key_lengths
+    }
+
+    // Which of the `N.pow(prefix_len)` fixed-width path prefixes have any entries below them,
+    // e.g. the first two bytes of byte keys with `prefix_len = 4`. Keys shorter than the prefix
+    // are not counted. Panics if the bitmap would not be addressable.
+    #[must_use]
+    pub fn occupancy_bitmap(&self, prefix_len: usize) -> OccupancyBitmap {
+        let bits = u32::try_from(prefix_len)
+            .ok()
+            .and_then(|exp| N.checked_pow(exp))
+            .expect("occupancy bitmap too large to address");
+        let mut bitmap = OccupancyBitmap {
+            words: vec![0; bits.div_ceil(64)],
+            bits,
+        };
+        for (path, _) in nodes_at_depth(&self.root, prefix_len, self.hollow) {
+            let slot = path.iter().fold(0, |slot, &index| slot * N + index);
+            bitmap.words[slot / 64] |= 1 << (slot % 64);
+        }
+        bitmap
+    }
+
+    // One `(prefix path, values, estimated bytes)` row per node exactly `depth` indices below the
+    // root, in path order. Bytes cover the nodes and child tables of the subtree, not what the
+    // values own. Keys shorter than `depth` fall in no row.
+    pub fn prefix_sizes(&self, depth: usize) -> impl Iterator<Item = (Vec<usize>, usize, usize)> + '_ {
+        let level = nodes_at_depth(&self.root, depth, self.hollow);
+        level.into_iter().map(|(path, node)| {
+            let (count, _, bytes) = node.footprint();
+            (path, count, bytes)
+        })
+    }
+
+    // Folds the entries of each subtree `prefix_depth` indices below the root on its own, starting
+    // from a copy of `init`, and returns each subtree's prefix path with its result, in path order.
+    // `f` sees full paths. As with `prefix_sizes`, keys shorter than `prefix_depth` are skipped.
+    pub fn fold_subtrees<A: Clone>(
+        &self,
+        prefix_depth: usize,
+        init: A,
+        mut f: impl FnMut(A, &[usize], &T) -> A,
+    ) -> Vec<(Vec<usize>, A)> {
+        nodes_at_depth(&self.root, prefix_depth, self.hollow)
+            .into_iter()
+            .map(|(prefix, node)| {
+                let mut acc = Some(init.clone());
+                let mut path = prefix.clone();
+                node.visit_paths(|rel, val| {
+                    path.truncate(prefix_depth);
+                    path.extend_from_slice(rel);
+                    acc = Some(f(acc.take().unwrap(), &path, val));
+                });
+                (prefix, acc.unwrap())
+            })
+            .collect()
+    }
+}
+
+// Every node exactly `depth` indices below `root`, with its path, in path order. In a `hollow`
+// trie, subtrees left without values by `take` are skipped.
+fn nodes_at_depth<T, const N: usize>(root: &TrieNode<T, N>, depth: usize, hollow: bool) -> Vec<(Vec<usize>, &TrieNode<T, N>)> {
+    let mut level = vec![(Vec::new(), root)];
+    for _ in 0..depth {
+        level = level
+            .into_iter()
+            .flat_map(|(path, node)| {
+                node.child_indices().map(move |index| {
+                    let mut child_path = path.clone();
+                    child_path.push(index);
+                    (child_path, node.child(index).unwrap())
+                })
+            })
+            .collect();
+    }
+    if hollow {
+        level.retain(|(_, node)| node.count_values() > 0);
+    }
+    level
+}
+
+fn bump(histogram: &mut Vec<usize>, index: usize) {
+    if histogram.len() <= index {
+        histogram.resize(index + 1, 0);
+    }
+    histogram[index] += 1;
+}
+
+/// The structural role of a node, as reported by `Trie::nodes`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum NodeRole {
+    Root,
+    Leaf,
+    Branch,
+    // One child and no value, so path compression could merge it into the child.
+    Chain,
+    // One child and a value.
+    Inner,
+}
+
+/// A read-only view of one node, yielded by `Trie::nodes`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NodeInfo {
+    pub path: Vec<usize>,
+    pub has_value: bool,
+    pub child_count: usize,
+    pub role: NodeRole,
+}
+
+impl<K: ?Sized, T, const N: usize> Trie<K, T, N> {
+    // Visits every node, root included, in path order.
+    #[must_use]
+    pub fn nodes(&self) -> Nodes<'_, T, N> {
+        Nodes {
+            stack: vec![(Vec::new(), &self.root)],
+        }
+    }
+}
+
+pub struct Nodes<'a, T, const N: usize> {
+    stack: Vec<(Vec<usize>, &'a TrieNode<T, N>)>,
+}
+
+impl<T, const N: usize> Iterator for Nodes<'_, T, N> {
+    type Item = NodeInfo;
+
+    fn next(&mut self) -> Option<NodeInfo> {
+        let (path, node) = self.stack.pop()?;
+        let child_count = node.count_children();
+        let has_value = node.value().is_some();
+        let role = match child_count {
+            _ if path.is_empty() => NodeRole::Root,
+            0 => NodeRole::Leaf,
+            1 if has_value => NodeRole::Inner,
+            1 => NodeRole::Chain,
+            _ => NodeRole::Branch,
+        };
+        let mut children: Vec<usize> = node.child_indices().collect();
+        children.reverse();
+        for index in children {
+            let mut child_path = path.clone();
+            child_path.push(index);
+            self.stack.push((child_path, node.child(index).unwrap()));
+        }
+        Some(NodeInfo {
+            path,
+            has_value,
+            child_count,
+            role,
+        })
+    }
+}
+
+/// Where a traced lookup stopped without finding a value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QueryMiss {
+    // The Bloom filter ruled the key out before any node was visited.
+    Filter,
+    InvalidIndex { position: usize, index: usize },
+    MissingChild { depth: usize, index: usize },
+    // The path exists but ends on a node without a value, e.g. a prefix of stored keys.
+    NoValue,
+}
+
+/// What a single lookup did, as returned by `Trie::trace`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QueryTrace {
+    pub path_len: usize,
+    pub nodes_visited: usize,
+    pub depth: usize,
+    // Valueless single-child nodes passed on the way down.
+    pub chain_nodes: usize,
+    pub miss: Option<QueryMiss>,
+}
+
+impl QueryTrace {
+    #[must_use]
+    pub fn found(&self) -> bool {
+        self.miss.is_none()
+    }
+}
+
+impl<K: ?Sized, T, const N: usize> Trie<K, T, N>
+where
+    Trie<K, T, N>: TriePathBuilder<K>,
+{
+    #[must_use]
+    pub fn trace(&self, key: &K) -> QueryTrace {
+        self.trace_path(&<Trie<K, T, N> as TriePathBuilder<K>>::build_path(key))
+    }
+}
+
+impl<K: ?Sized, T, const N: usize> Trie<K, T, N> {
+    // Mirrors `get_path` step for step, recording the work instead of returning the value.
+    #[must_use]
+    pub fn trace_path(&self, path: &[usize]) -> QueryTrace {
+        let mut trace = QueryTrace {
+            path_len: path.len(),
+            nodes_visited: 0,
+            depth: 0,
+            chain_nodes: 0,
+            miss: None,
+        };
+        if let Some((position, &index)) = path.iter().enumerate().find(|(_, &index)| index >= N) {
+            trace.miss = Some(QueryMiss::InvalidIndex { position, index });
+            return trace;
+        }
+        if self.filter_excludes(path) {
+            trace.miss = Some(QueryMiss::Filter);
+            return trace;
+        }
+        let mut current_node = &self.root;
+        trace.nodes_visited = 1;
+        for &child_index in path {
+            if current_node.value().is_none() && current_node.count_children() == 1 && trace.depth > 0 {
+                trace.chain_nodes += 1;
+            }
+            match current_node.child(child_index) {
+                Some(node) => {
+                    current_node = node;
+                    trace.nodes_visited += 1;
+                    trace.depth += 1;
+                }
+                None => {
+                    trace.miss = Some(QueryMiss::MissingChild {
+                        depth: trace.depth,
+                        index: child_index,
+                    });
+                    return trace;
+                }
+            }
+        }
+        if current_node.value().is_none() {
+            trace.miss = Some(QueryMiss::NoValue);
+        }
+        trace
+    }
+}
+
+fn estimates<T, const N: usize>(analysis: &TrieAnalysis, arity_nodes: &[usize; 9], bits: usize) -> Vec<LayoutEstimate> {
+    let node_size = mem::size_of::<TrieNode<T, N>>();
+    let avg_bits = analysis.avg_value_depth * bits as f64;
+    let mut estimates = Vec::new();
+    for arity in CANDIDATE_ARITIES {
+        let target = arity.trailing_zeros() as usize;
+        let nodes = arity_nodes[target];
+        let edges = nodes - 1;
+        // Keys that are not a prefix of another key end in a leaf at every arity.
+        let internal = nodes.saturating_sub(analysis.leaves);
+        for layout in [NodeLayout::Dense, NodeLayout::Packed, NodeLayout::Sorted] {
+            let children_bytes = match layout {
+                NodeLayout::Dense => internal * arity * mem::size_of::<usize>(),
+                NodeLayout::Packed if arity <= 64 => edges * mem::size_of::<usize>(),
+                NodeLayout::Packed => continue,
+                NodeLayout::Sorted => edges * 2 * mem::size_of::<usize>(),
+                // Depends on each node's fan-out, which the analysis doesn't keep.
+                NodeLayout::Adaptive => unreachable!(),
+            };
+            estimates.push(LayoutEstimate {
+                arity,
+                layout,
+                nodes,
+                est_bytes: nodes * node_size + children_bytes,
+                avg_lookup_steps: avg_bits / target as f64,
+            });
+        }
+    }
+    estimates
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::{NodeRole, QueryMiss};
+    use crate::trie::{NodeLayout, Trie};
+
+    #[test]
+    fn nodes() {
+        let mut trie: Trie<[u8], usize, 16> = Trie::new();
+        trie.insert(&[0x12, 0x34], 0);
+        trie.insert(&[0x12, 0x35], 1);
+        trie.insert(&[0x12], 2);
+        let nodes: Vec<(Vec<usize>, NodeRole)> = trie.nodes().map(|node| (node.path, node.role)).collect();
+        assert_eq!(
+            nodes,
+            vec![
+                (vec![], NodeRole::Root),
+                (vec![1], NodeRole::Chain),
+                (vec![1, 2], NodeRole::Inner),
+                (vec![1, 2, 3], NodeRole::Branch),
+                (vec![1, 2, 3, 4], NodeRole::Leaf),
+                (vec![1, 2, 3, 5], NodeRole::Leaf),
+            ]
+        );
+        assert_eq!(trie.nodes().filter(|node| node.has_value).count(), trie.len());
+    }
+
+    #[test]
+    fn trace() {
+        let mut trie: Trie<[u8], usize, 16> = Trie::new();
+        trie.insert(&[0x12, 0x34], 0);
+        trie.insert(&[0x12, 0x35], 1);
+
+        let hit = trie.trace(&[0x12, 0x34]);
+        assert!(hit.found());
+        assert_eq!((hit.nodes_visited, hit.depth, hit.chain_nodes), (5, 4, 2));
+        assert_eq!(trie.trace(&[0x12]).miss, Some(QueryMiss::NoValue));
+        assert_eq!(trie.trace(&[0x13]).miss, Some(QueryMiss::MissingChild { depth: 1, index: 3 }));
+        assert_eq!(trie.trace_path(&[1, 20]).miss, Some(QueryMiss::InvalidIndex { position: 1, index: 20 }));
+        trie.enable_filter(10, 10);
+        assert_eq!(trie.trace(&[0x99, 0x99]).miss, Some(QueryMiss::Filter));
+    }
+
+    #[test]
+    fn prefix_sizes() {
+        let mut trie: Trie<str, usize, 16> = Trie::new();
+        for (i, key) in ["acme/a", "acme/b", "blue/a", "c"].iter().enumerate() {
+            trie.insert(key, i);
+        }
+        let rows: Vec<(Vec<usize>, usize, usize)> = trie.prefix_sizes(2).collect();
+        let counts: Vec<(Vec<usize>, usize)> = rows.iter().map(|(path, count, _)| (path.clone(), *count)).collect();
+        assert_eq!(counts, vec![(vec![6, 1], 2), (vec![6, 2], 1), (vec![6, 3], 1)]);
+        assert!(rows[0].2 > rows[1].2);
+        assert_eq!(trie.prefix_sizes(0).next().map(|(_, count, _)| count), Some(4));
+    }
+
+    #[test]
+    fn fold_subtrees() {
+        let mut trie: Trie<str, usize, 16> = Trie::new();
+        for (i, key) in ["", "ab", "ac", "b", "bcd"].iter().enumerate() {
+            trie.insert(key, i);
+        }
+        let sums = trie.fold_subtrees(2, 0, |sum, _, val| sum + val);
+        assert_eq!(sums, vec![(vec![6, 1], 1 + 2), (vec![6, 2], 3 + 4)]);
+        let longest = trie.fold_subtrees(1, 0, |longest: usize, path, _| longest.max(path.len()));
+        assert_eq!(longest, vec![(vec![6], 6)]);
+        assert_eq!(trie.fold_subtrees(0, 0, |count, _, _| count + 1), vec![(vec![], 5)]);
+    }
+
+    #[test]
+    fn occupancy() {
+        let mut trie: Trie<[u8], usize, 16> = Trie::new();
+        trie.insert(&[0x01, 0x02], 0);
+        trie.insert(&[0x01, 0x7f, 0x00], 1);
+        trie.insert(&[0xff], 2);
+        trie.insert(&[0x05], 3);
+        let bitmap = trie.occupancy_bitmap(4);
+        assert_eq!((bitmap.len(), bitmap.count_ones()), (65536, 2));
+        assert_eq!(bitmap.iter_ones().collect::<Vec<_>>(), vec![0x0102, 0x017f]);
+        assert_eq!(trie.occupancy_bitmap(1).words(), &[1 | 1 << 15]);
+    }
+
+    #[test]
+    fn hollow_subtrees_skipped() {
+        let mut trie: Trie<str, usize, 16> = Trie::new();
+        trie.insert("a", 0);
+        trie.insert("zebra", 1);
+        assert_eq!(trie.take("zebra"), Some(1));
+        assert_eq!(trie.occupancy_bitmap(1).words(), &[1 << 6]);
+        assert_eq!(trie.prefix_sizes(1).map(|(path, count, _)| (path, count)).collect::<Vec<_>>(), vec![(vec![6], 1)]);
+        assert_eq!(trie.fold_subtrees(1, 0, |count, _, _| count + 1), vec![(vec![6], 1)]);
+    }
+
+    #[test]
+    fn analyze() {
+        let mut trie: Trie<[u8], usize, 16> = Trie::new();
+        trie.insert(&[0x12, 0x34], 0);
+        trie.insert(&[0x12, 0x35], 1);
+        trie.insert(&[0x12], 2);
+        let analysis = trie.analyze();
+
+        assert_eq!(analysis.nodes, 6);
+        assert_eq!(analysis.values, 3);
+        assert_eq!(analysis.leaves, 2);
+        assert_eq!(analysis.max_depth, 4);
+        assert_eq!(analysis.branching, vec![2, 3, 1]);
+        assert_eq!(analysis.chains, 1);
+        assert_eq!(analysis.chain_nodes, 1);
+        assert_eq!(analysis.longest_chain, 1);
+        assert_eq!(analysis.chain_runs, vec![0, 1]);
+        assert_eq!(analysis.key_lengths, vec![0, 0, 1, 0, 2]);
+        assert_eq!(trie.key_length_histogram(), analysis.key_lengths);
+
+        let byte_wise = analysis.estimates.iter().find(|estimate| estimate.arity == 256).unwrap();
+        assert_eq!(byte_wise.nodes, 4);
+        assert_eq!(byte_wise.layout, NodeLayout::Dense);
+        let same = analysis.estimates.iter().find(|estimate| estimate.arity == 16).unwrap();
+        assert_eq!(same.nodes, analysis.nodes);
+        let bit_wise = analysis.estimates.iter().find(|estimate| estimate.arity == 2).unwrap();
+        assert_eq!(bit_wise.avg_lookup_steps, 4.0 * (4.0 + 4.0 + 2.0) / 3.0);
+        assert!(analysis.smallest().is_some());
+    }
+}
@@ -0,0 +1,68 @@
+use crate::trie::{DecodeError, NodeLayout, Trie};
+
+impl<K: ?Sized, T, const N: usize> Trie<K, T, N> {
+    // Moves every entry into a trie of arity `M`, re-slicing each path's bits: with `N = 16` and
+    // `M = 256`, each pair of nibbles becomes one byte-wide index and back. Both arities must be
+    // powers of two. Fails without consuming anything if some path's bits do not divide evenly
+    // into `M`-ary indices. The new trie uses the default layout for `M`, without a filter or
+    // prefix counts.
+    pub fn convert_arity<const M: usize>(self) -> Result<Trie<K, T, M>, (Trie<K, T, N>, DecodeError)> {
+        assert!(N.is_power_of_two() && N > 1, "arity {N} is not a power of two");
+        assert!(M.is_power_of_two() && M > 1, "arity {M} is not a power of two");
+        let (from_bits, to_bits) = (N.trailing_zeros() as usize, M.trailing_zeros() as usize);
+        let mut uneven = None;
+        self.root.visit_paths(|path, _| {
+            if uneven.is_none() && path.len() * from_bits % to_bits != 0 {
+                uneven = Some(path.len());
+            }
+        });
+        if let Some(len) = uneven {
+            return Err((self, DecodeError::Truncated { len }));
+        }
+
+        let mut converted = Trie::empty(NodeLayout::default_for(M));
+        let mut resliced = Vec::new();
+        self.root.drain_paths(|path, val| {
+            reslice(path, from_bits, to_bits, &mut resliced);
+            converted.insert_path(&resliced, val);
+        });
+        Ok(converted)
+    }
+}
+
+fn reslice(path: &[usize], from_bits: usize, to_bits: usize, out: &mut Vec<usize>) {
+    out.clear();
+    let (mut acc, mut held) = (0u128, 0);
+    for &index in path {
+        acc = acc << from_bits | index as u128;
+        held += from_bits;
+        while held >= to_bits {
+            held -= to_bits;
+            out.push((acc >> held) as usize & ((1 << to_bits) - 1));
+        }
+        acc &= (1 << held) - 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trie::{DecodeError, Trie};
+
+    #[test]
+    fn round_trip() {
+        let mut trie: Trie<str, usize, 16> = Trie::new();
+        for (i, key) in ["", "a", "ab", "zebra"].iter().enumerate() {
+            trie.insert(key, i);
+        }
+        let Ok(wide) = trie.convert_arity::<256>() else { panic!("byte keys re-slice evenly") };
+        assert_eq!(wide.len, 4);
+        assert_eq!(wide.get_path(&[usize::from(b'a'), usize::from(b'b')]), Some(&2));
+        let Ok(narrow) = wide.convert_arity::<16>() else { panic!("byte keys re-slice evenly") };
+        assert_eq!(narrow.get("zebra"), Some(&3));
+
+        let mut odd: Trie<str, usize, 16> = Trie::new();
+        odd.insert_path(&[6], 0);
+        let Err((odd, err)) = odd.convert_arity::<256>() else { panic!("a lone nibble is not a byte") };
+        assert_eq!((odd.len(), err), (1, DecodeError::Truncated { len: 1 }));
+    }
+}
@@ -0,0 +1,186 @@
+use std::iter::Peekable;
+use std::marker::PhantomData;
+use std::vec;
+
+use crate::trie::{NodeLayout, Trie, TriePathBuilder};
+use crate::trie_node::TrieNode;
+
+/// Inserts and deletes collected for `Trie::apply`, which sorts them and applies them in one
+/// walk, visiting each shared prefix once.
+///
+/// When a batch touches the same key more than once, the last operation wins.
+pub struct WriteBatch<K: ?Sized, T, const N: usize> {
+    // `None` deletes the key.
+    ops: Vec<(Vec<usize>, Option<T>)>,
+    _trie: PhantomData<Trie<K, T, N>>,
+}
+
+/// What `Trie::apply` changed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BatchStats {
+    pub inserted: usize,
+    pub replaced: usize,
+    pub deleted: usize,
+}
+
+impl<K: ?Sized, T, const N: usize> WriteBatch<K, T, N>
+where
+    Trie<K, T, N>: TriePathBuilder<K>,
+{
+    #[must_use]
+    pub fn new() -> WriteBatch<K, T, N> {
+        WriteBatch {
+            ops: Vec::new(),
+            _trie: PhantomData,
+        }
+    }
+
+    pub fn insert(&mut self, key: &K, val: T) -> &mut WriteBatch<K, T, N> {
+        self.ops.push((<Trie<K, T, N> as TriePathBuilder<K>>::build_path(key), Some(val)));
+        self
+    }
+
+    #[deprecated(note = "renamed to `remove`")]
+    pub fn delete(&mut self, key: &K) -> &mut WriteBatch<K, T, N> {
+        self.remove(key)
+    }
+
+    pub fn remove(&mut self, key: &K) -> &mut WriteBatch<K, T, N> {
+        self.ops.push((<Trie<K, T, N> as TriePathBuilder<K>>::build_path(key), None));
+        self
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+}
+
+impl<K: ?Sized, T, const N: usize> Default for WriteBatch<K, T, N>
+where
+    Trie<K, T, N>: TriePathBuilder<K>,
+{
+    fn default() -> WriteBatch<K, T, N> {
+        WriteBatch::new()
+    }
+}
+
+type Ops<T> = Peekable<vec::IntoIter<(Vec<usize>, Option<T>)>>;
+
+impl<K: ?Sized, T, const N: usize> Trie<K, T, N>
+where
+    Trie<K, T, N>: TriePathBuilder<K>,
+{
+    // Nodes emptied by deletes are pruned as the walk leaves them, rather than by a second pass
+    // per deleted key.
+    pub fn apply(&mut self, batch: WriteBatch<K, T, N>) -> BatchStats {
+        let mut ops = batch.ops;
+        // Stable, so the last operation on a key ends its run of equal paths.
+        ops.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let mut deduped: Vec<(Vec<usize>, Option<T>)> = Vec::with_capacity(ops.len());
+        for op in ops {
+            match deduped.last_mut() {
+                Some(last) if last.0 == op.0 => *last = op,
+                _ => deduped.push(op),
+            }
+        }
+        if let Some(filter) = self.filter.as_mut() {
+            for (path, _) in deduped.iter().filter(|(_, val)| val.is_some()) {
+                filter.insert_path(path);
+            }
+        }
+
+        let mut stats = BatchStats::default();
+        let mut ops = deduped.into_iter().peekable();
+        let mut prefix = Vec::new();
+        apply_at(&mut self.root, &mut prefix, &mut ops, self.layout, self.count_depth, &mut stats);
+        self.len = (self.len + stats.inserted).checked_sub(stats.deleted).expect("trie len underflow");
+        stats
+    }
+}
+
+// Applies the leading ops under `prefix`, the path of `node`, and returns the net change in the
+// number of values below it.
+fn apply_at<T, const N: usize>(
+    node: &mut TrieNode<T, N>,
+    prefix: &mut Vec<usize>,
+    ops: &mut Ops<T>,
+    layout: NodeLayout,
+    count_depth: usize,
+    stats: &mut BatchStats,
+) -> isize {
+    let depth = prefix.len();
+    let mut delta = 0;
+    while let Some((path, _)) = ops.peek() {
+        if !path.starts_with(prefix) {
+            break;
+        }
+        if path.len() == depth {
+            let (_, val) = ops.next().unwrap();
+            match (val, node.value().is_some()) {
+                (Some(val), had) => {
+                    node.value_replace(val);
+                    if had {
+                        stats.replaced += 1;
+                    } else {
+                        stats.inserted += 1;
+                        delta += 1;
+                    }
+                }
+                (None, true) => {
+                    node.value_take();
+                    stats.deleted += 1;
+                    delta -= 1;
+                }
+                (None, false) => {}
+            }
+            continue;
+        }
+        let index = path[depth];
+        if node.child(index).is_none() {
+            node.child_set(index, TrieNode::with_layout(layout));
+        }
+        let child = node.child_mut(index).unwrap();
+        prefix.push(index);
+        delta += apply_at(child, prefix, ops, layout, count_depth, stats);
+        prefix.pop();
+        if child.value().is_none() && !child.has_child() {
+            node.child_take(index);
+        }
+    }
+    if depth < count_depth {
+        let count = node.cached_count().checked_add_signed(delta).expect("prefix count underflow");
+        node.set_cached_count(count);
+    }
+    delta
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::batch::{BatchStats, WriteBatch};
+    use crate::trie::Trie;
+
+    #[test]
+    fn applies_in_one_pass() {
+        let mut trie: Trie<str, usize, 16> = Trie::new();
+        trie.enable_prefix_counts(4);
+        trie.insert("user/1", 1);
+        trie.insert("user/2", 2);
+
+        let mut batch = WriteBatch::new();
+        batch.insert("user/3", 3).remove("user/1").insert("user/2", 20).remove("user/9");
+        batch.insert("user/4", 4).remove("user/4");
+        let stats = trie.apply(batch);
+        assert_eq!(stats, BatchStats { inserted: 1, replaced: 1, deleted: 1 });
+        assert_eq!(trie.len(), 2);
+        assert_eq!(trie.get("user/2"), Some(&20));
+        assert_eq!(trie.get("user/1"), None);
+        assert_eq!(trie.count_prefix("u"), 2);
+        assert_eq!(trie.analyze().nodes, trie.clone_prefix("user/").analyze().nodes);
+    }
+}
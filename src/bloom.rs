@@ -0,0 +1,115 @@
+/// A Bloom filter over trie paths, answering "definitely absent" or "possibly present".
+#[derive(Clone)]
+pub struct BloomFilter {
+    bits: Vec<u64>,
+    hashes: u32,
+}
+
+impl BloomFilter {
+    #[must_use]
+    pub fn new(expected_keys: usize, bits_per_key: usize) -> BloomFilter {
+        let num_bits = (expected_keys.max(1) * bits_per_key.max(1)).max(64);
+        // k = ln(2) * m / n minimises the false positive rate.
+        let hashes = ((bits_per_key as f64) * std::f64::consts::LN_2).round().clamp(1.0, 30.0);
+        BloomFilter {
+            bits: vec![0; num_bits.div_ceil(64)],
+            hashes: hashes as u32,
+        }
+    }
+
+    #[must_use]
+    pub fn num_bits(&self) -> usize {
+        self.bits.len() * 64
+    }
+
+    #[must_use]
+    pub fn num_hashes(&self) -> u32 {
+        self.hashes
+    }
+
+    pub fn clear(&mut self) {
+        self.bits.fill(0);
+    }
+
+    pub(crate) fn insert_path(&mut self, path: &[usize]) {
+        let num_bits = self.num_bits() as u64;
+        let (h1, h2) = Self::hash_path(path);
+        for i in 0..u64::from(self.hashes) {
+            let bit = h1.wrapping_add(i.wrapping_mul(h2)) % num_bits;
+            self.bits[(bit / 64) as usize] |= 1 << (bit % 64);
+        }
+    }
+
+    // Build the path with the `TriePathBuilder` of the trie the filter came from.
+    #[must_use]
+    pub fn contains_path(&self, path: &[usize]) -> bool {
+        let num_bits = self.num_bits() as u64;
+        let (h1, h2) = Self::hash_path(path);
+        (0..u64::from(self.hashes)).all(|i| {
+            let bit = h1.wrapping_add(i.wrapping_mul(h2)) % num_bits;
+            self.bits[(bit / 64) as usize] & (1 << (bit % 64)) != 0
+        })
+    }
+
+    // The hash count as four little-endian bytes, then the bit words as eight each.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(4 + 8 * self.bits.len());
+        bytes.extend_from_slice(&self.hashes.to_le_bytes());
+        for word in &self.bits {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+        bytes
+    }
+
+    // `None` for bytes that `to_bytes` could not have produced.
+    #[must_use]
+    pub fn from_bytes(bytes: &[u8]) -> Option<BloomFilter> {
+        let (hashes, words) = bytes.split_first_chunk::<4>()?;
+        let hashes = u32::from_le_bytes(*hashes);
+        if !(1..=30).contains(&hashes) || words.is_empty() || words.len() % 8 != 0 {
+            return None;
+        }
+        let bits = words.chunks_exact(8).map(|word| u64::from_le_bytes(word.try_into().unwrap())).collect();
+        Some(BloomFilter { bits, hashes })
+    }
+
+    // FNV-1a over the path followed by two splitmix64 finalisers for double hashing.
+    pub(crate) fn hash_path(path: &[usize]) -> (u64, u64) {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for &index in path {
+            hash ^= index as u64;
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+        (Self::mix(hash), Self::mix(hash ^ 0x9e37_79b9_7f4a_7c15) | 1)
+    }
+
+    fn mix(mut x: u64) -> u64 {
+        x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        x ^ (x >> 31)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bloom::BloomFilter;
+
+    #[test]
+    fn no_false_negatives() {
+        let mut filter = BloomFilter::new(1000, 10);
+        for i in 0..1000 {
+            filter.insert_path(&[i % 16, i / 16]);
+        }
+        for i in 0..1000 {
+            assert!(filter.contains_path(&[i % 16, i / 16]));
+        }
+        let false_positives = (1000..11000).filter(|&i| filter.contains_path(&[i % 16, i / 16])).count();
+        assert!(false_positives < 500);
+
+        let copy = BloomFilter::from_bytes(&filter.to_bytes()).unwrap();
+        assert_eq!((copy.num_bits(), copy.num_hashes()), (filter.num_bits(), filter.num_hashes()));
+        assert!((0..1000).all(|i| copy.contains_path(&[i % 16, i / 16])));
+        assert!(BloomFilter::from_bytes(&[7, 0, 0, 0, 1]).is_none());
+    }
+}
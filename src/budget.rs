@@ -0,0 +1,164 @@
+use std::mem;
+
+use crate::trie::{Trie, TriePathBuilder};
+use crate::trie_node::TrieNode;
+
+type Weigher<T> = Box<dyn Fn(&T) -> usize>;
+type EvictionPolicy<K, T, const N: usize> = Box<dyn FnMut(&Trie<K, T, N>, usize) -> Vec<Vec<usize>>>;
+
+/// A trie with a memory budget and an application-supplied eviction policy.
+///
+/// Each entry is charged one node per path index plus what the weigher says its value owns,
+/// which overestimates keys that share prefixes. Once an insert takes the charge over budget,
+/// the policy gets the trie and the number of bytes to free and returns path prefixes to evict;
+/// everything under them is removed with the usual cleanup. Eviction is cooperative: if the
+/// victims do not free enough, the trie stays over budget until the next insert asks again.
+pub struct BudgetTrie<K: ?Sized, T, const N: usize> {
+    trie: Trie<K, T, N>,
+    budget: usize,
+    used: usize,
+    evicted: usize,
+    weigh: Weigher<T>,
+    policy: EvictionPolicy<K, T, N>,
+}
+
+impl<K: ?Sized, T, const N: usize> BudgetTrie<K, T, N>
+where
+    Trie<K, T, N>: TriePathBuilder<K>,
+{
+    #[must_use]
+    pub fn new(
+        budget: usize,
+        weigh: impl Fn(&T) -> usize + 'static,
+        policy: impl FnMut(&Trie<K, T, N>, usize) -> Vec<Vec<usize>> + 'static,
+    ) -> BudgetTrie<K, T, N> {
+        BudgetTrie {
+            trie: Trie::new(),
+            budget,
+            used: 0,
+            evicted: 0,
+            weigh: Box::new(weigh),
+            policy: Box::new(policy),
+        }
+    }
+
+    #[must_use]
+    pub fn get(&self, key: &K) -> Option<&T> {
+        self.trie.get(key)
+    }
+
+    pub fn insert(&mut self, key: &K, val: T) -> Option<T> {
+        let path = <Trie<K, T, N> as TriePathBuilder<K>>::build_path(key);
+        self.used += self.charge(&path, &val);
+        let old = self.trie.insert_path(&path, val);
+        if let Some(old) = &old {
+            self.used -= self.charge(&path, old);
+        }
+        if self.used > self.budget {
+            self.enforce();
+        }
+        old
+    }
+
+    #[deprecated(note = "renamed to `remove`")]
+    pub fn delete(&mut self, key: &K) -> Option<T> {
+        self.remove(key)
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<T> {
+        let path = <Trie<K, T, N> as TriePathBuilder<K>>::build_path(key);
+        let old = self.trie.delete_path(&path)?;
+        self.used -= self.charge(&path, &old);
+        Some(old)
+    }
+
+    // Lowering the budget below the current charge runs the policy straight away.
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+        if self.used > self.budget {
+            self.enforce();
+        }
+    }
+
+    #[must_use]
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    #[must_use]
+    pub fn used_bytes(&self) -> usize {
+        self.used
+    }
+
+    // Entries removed by the policy so far.
+    #[must_use]
+    pub fn evicted(&self) -> usize {
+        self.evicted
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.trie.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.trie.is_empty()
+    }
+
+    #[must_use]
+    pub fn trie(&self) -> &Trie<K, T, N> {
+        &self.trie
+    }
+
+    fn enforce(&mut self) {
+        let victims = (self.policy)(&self.trie, self.used - self.budget);
+        for prefix in victims {
+            let mut freed = 0;
+            let mut removed = 0;
+            let weigh = &self.weigh;
+            self.trie.remove_prefix_path(&prefix, |path, val| {
+                freed += path.len() * mem::size_of::<TrieNode<T, N>>() + weigh(&val);
+                removed += 1;
+            });
+            self.used -= freed;
+            self.evicted += removed;
+        }
+    }
+
+    fn charge(&self, path: &[usize], val: &T) -> usize {
+        path.len() * mem::size_of::<TrieNode<T, N>>() + (self.weigh)(val)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::budget::BudgetTrie;
+    use crate::trie::{Trie, TriePathBuilder};
+
+    #[test]
+    fn policy_picks_victims() {
+        // Scores as values; evict the lowest-scored entry under "cold/" first, then anything.
+        let mut trie: BudgetTrie<str, u32, 16> = BudgetTrie::new(
+            4000,
+            |_| 100,
+            |trie: &Trie<str, u32, 16>, _| {
+                let cold = <Trie<str, u32, 16> as TriePathBuilder<str>>::build_path("cold/");
+                let mut entries: Vec<(Vec<usize>, u32)> = trie.iter_paths().map(|(path, score)| (path, *score)).collect();
+                entries.sort_by_key(|(path, score)| (!path.starts_with(&cold), *score));
+                entries.into_iter().take(1).map(|(path, _)| path).collect()
+            },
+        );
+        trie.insert("hot/a", 9);
+        trie.insert("cold/a", 5);
+        trie.insert("cold/b", 1);
+        let used = trie.used_bytes();
+        assert!(used <= 4000);
+
+        trie.set_budget(used - 1);
+        assert_eq!((trie.len(), trie.evicted()), (2, 1));
+        assert_eq!(trie.get("cold/b"), None);
+        assert!(trie.used_bytes() < used);
+        assert_eq!(trie.remove("hot/a"), Some(9));
+    }
+}
@@ -0,0 +1,138 @@
+use std::io;
+
+/// Converts values to and from bytes for the on-disk representations.
+pub trait ValueCodec<T> {
+    fn encode(&self, val: &T, out: &mut Vec<u8>);
+    fn decode(&self, bytes: &[u8]) -> io::Result<T>;
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BytesCodec;
+
+impl ValueCodec<Vec<u8>> for BytesCodec {
+    fn encode(&self, val: &Vec<u8>, out: &mut Vec<u8>) {
+        out.extend_from_slice(val);
+    }
+
+    fn decode(&self, bytes: &[u8]) -> io::Result<Vec<u8>> {
+        Ok(bytes.to_vec())
+    }
+}
+
+impl ValueCodec<String> for BytesCodec {
+    fn encode(&self, val: &String, out: &mut Vec<u8>) {
+        out.extend_from_slice(val.as_bytes());
+    }
+
+    fn decode(&self, bytes: &[u8]) -> io::Result<String> {
+        String::from_utf8(bytes.to_vec()).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
+
+/// Encodes primitive numbers as little-endian bytes.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LeBytesCodec;
+
+macro_rules! le_bytes_codec {
+    ($($ty:ty),*) => {
+        $(
+            impl ValueCodec<$ty> for LeBytesCodec {
+                fn encode(&self, val: &$ty, out: &mut Vec<u8>) {
+                    out.extend_from_slice(&val.to_le_bytes());
+                }
+
+                fn decode(&self, bytes: &[u8]) -> io::Result<$ty> {
+                    let bytes = bytes.try_into().map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+                    Ok(<$ty>::from_le_bytes(bytes))
+                }
+            }
+        )*
+    };
+}
+
+le_bytes_codec!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
+
+/// A codec made from a pair of closures, for one-off binary formats that do not warrant a type.
+#[derive(Clone, Copy)]
+pub struct FnCodec<E, D> {
+    encode: E,
+    decode: D,
+}
+
+impl<E, D> FnCodec<E, D> {
+    #[must_use]
+    pub fn new<T>(encode: E, decode: D) -> FnCodec<E, D>
+    where
+        E: Fn(&T, &mut Vec<u8>),
+        D: Fn(&[u8]) -> io::Result<T>,
+    {
+        FnCodec { encode, decode }
+    }
+}
+
+impl<T, E: Fn(&T, &mut Vec<u8>), D: Fn(&[u8]) -> io::Result<T>> ValueCodec<T> for FnCodec<E, D> {
+    fn encode(&self, val: &T, out: &mut Vec<u8>) {
+        (self.encode)(val, out);
+    }
+
+    fn decode(&self, bytes: &[u8]) -> io::Result<T> {
+        (self.decode)(bytes)
+    }
+}
+
+// LEB128: seven bits per byte, low bits first, high bit set on all but the last byte.
+pub(crate) fn write_varint(out: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        out.push((value as u8 & 0x7F) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+// `None` if the bytes run out or the value does not fit in a `usize`.
+pub(crate) fn read_varint(bytes: &mut &[u8]) -> Option<usize> {
+    let mut value = 0usize;
+    let mut shift = 0;
+    loop {
+        let (&byte, rest) = bytes.split_first()?;
+        *bytes = rest;
+        let bits = usize::from(byte & 0x7F);
+        if shift >= usize::BITS || (bits << shift) >> shift != bits {
+            return None;
+        }
+        value |= bits << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+        shift += 7;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use crate::codec::FnCodec;
+    use crate::trie::Trie;
+
+    #[test]
+    fn fn_codec_snapshot() {
+        // A bitmap index stored as its set bit positions, one byte each.
+        let codec = FnCodec::new(
+            |bits: &u64, out: &mut Vec<u8>| out.extend((0..64u8).filter(|&i| bits & (1 << i) != 0)),
+            |bytes: &[u8]| {
+                bytes.iter().try_fold(0u64, |bits, &i| {
+                    let bit = 1u64.checked_shl(u32::from(i));
+                    bit.map(|bit| bits | bit).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "bit out of range"))
+                })
+            },
+        );
+        let mut trie: Trie<str, u64, 16> = Trie::new();
+        trie.insert("even", 0x5555);
+        trie.insert("odd", 0xAAAA);
+        let mut bytes = Vec::new();
+        trie.save(&mut bytes, &codec).unwrap();
+        let loaded: Trie<str, u64, 16> = Trie::load(bytes.as_slice(), &codec).unwrap();
+        assert_eq!((loaded.get("even"), loaded.get("odd")), (Some(&0x5555), Some(&0xAAAA)));
+    }
+}
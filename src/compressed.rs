@@ -0,0 +1,351 @@
+use std::marker::PhantomData;
+use std::mem;
+
+use crate::trie::{Trie, TriePathBuilder, TriePathDecoder};
+
+struct RadixNode<T> {
+    // The path indices from the parent to this node; empty only at the root. Every chain of
+    // valueless single-child nodes a `Trie` would hold is folded into one run.
+    run: Vec<u8>,
+    value: Option<T>,
+    // Sorted by the first index of their runs, which are distinct.
+    children: Vec<RadixNode<T>>,
+}
+
+impl<T> RadixNode<T> {
+    fn leaf(run: impl Iterator<Item = usize>, value: T) -> RadixNode<T> {
+        RadixNode {
+            run: run.map(|index| index as u8).collect(),
+            value: Some(value),
+            children: Vec::new(),
+        }
+    }
+
+    fn child_pos(&self, index: usize) -> Result<usize, usize> {
+        self.children.binary_search_by_key(&index, |child| usize::from(child.run[0]))
+    }
+
+    // Whether `rest` continues with the run of `self` past its first index, consuming it.
+    fn run_matches(&self, rest: &mut impl Iterator<Item = usize>) -> bool {
+        self.run[1..].iter().all(|&index| rest.next() == Some(usize::from(index)))
+    }
+
+    fn insert(&mut self, mut rest: impl Iterator<Item = usize>, val: T) -> Option<T> {
+        let mut node = self;
+        let mut next = rest.next();
+        while let Some(first) = next {
+            let pos = match node.child_pos(first) {
+                Ok(pos) => pos,
+                Err(pos) => {
+                    node.children.insert(pos, RadixNode::leaf(std::iter::once(first).chain(rest), val));
+                    return None;
+                }
+            };
+            let child = &mut node.children[pos];
+            let mut common = 1;
+            next = rest.next();
+            while common < child.run.len() && next == Some(usize::from(child.run[common])) {
+                common += 1;
+                next = rest.next();
+            }
+            if common < child.run.len() {
+                // Split the run where the new key leaves it.
+                let tail = child.run.split_off(common);
+                let lower = RadixNode {
+                    run: tail,
+                    value: child.value.take(),
+                    children: mem::take(&mut child.children),
+                };
+                child.children.push(lower);
+            }
+            node = child;
+        }
+        node.value.replace(val)
+    }
+
+    fn get(&self, mut rest: impl Iterator<Item = usize>) -> Option<&RadixNode<T>> {
+        let mut node = self;
+        while let Some(first) = rest.next() {
+            let child = &node.children[node.child_pos(first).ok()?];
+            if !child.run_matches(&mut rest) {
+                return None;
+            }
+            node = child;
+        }
+        Some(node)
+    }
+
+    fn get_mut(&mut self, mut rest: impl Iterator<Item = usize>) -> Option<&mut RadixNode<T>> {
+        let mut node = self;
+        while let Some(first) = rest.next() {
+            let pos = node.child_pos(first).ok()?;
+            let child = &mut node.children[pos];
+            if !child.run_matches(&mut rest) {
+                return None;
+            }
+            node = child;
+        }
+        Some(node)
+    }
+
+    // The node whose path starts with `rest` and is shortest, e.g. a node whose run goes past the
+    // end of a prefix.
+    fn covering(&self, mut rest: impl Iterator<Item = usize>) -> Option<&RadixNode<T>> {
+        let mut node = self;
+        while let Some(first) = rest.next() {
+            let child = &node.children[node.child_pos(first).ok()?];
+            for &index in &child.run[1..] {
+                match rest.next() {
+                    None => return Some(child),
+                    Some(other) if other != usize::from(index) => return None,
+                    Some(_) => {}
+                }
+            }
+            node = child;
+        }
+        Some(node)
+    }
+
+    fn delete(&mut self, rest: &mut impl Iterator<Item = usize>) -> Option<T> {
+        let Some(first) = rest.next() else {
+            return self.value.take();
+        };
+        let pos = self.child_pos(first).ok()?;
+        let child = &mut self.children[pos];
+        if !child.run_matches(rest) {
+            return None;
+        }
+        let retval = child.delete(rest)?;
+        // Re-merge: drop a child left empty, or fold a valueless child into its only child.
+        if child.value.is_none() {
+            match child.children.len() {
+                0 => {
+                    self.children.remove(pos);
+                }
+                1 => {
+                    let only = child.children.pop().unwrap();
+                    child.run.extend_from_slice(&only.run);
+                    child.value = only.value;
+                    child.children = only.children;
+                }
+                _ => {}
+            }
+        }
+        Some(retval)
+    }
+
+    fn count_values(&self) -> usize {
+        usize::from(self.value.is_some()) + self.children.iter().map(RadixNode::count_values).sum::<usize>()
+    }
+
+    fn count_nodes(&self) -> usize {
+        1 + self.children.iter().map(RadixNode::count_nodes).sum::<usize>()
+    }
+}
+
+/// A trie with path compression: each chain of valueless single-child nodes is stored as one
+/// node holding the run of path indices, split on insert where a new key branches off and
+/// merged back on delete. Keys are encoded as for `Trie<K, T, N>`.
+///
+/// Compression is only available through this type: `Trie` keeps one node per path index, which
+/// its prefix counts, node layouts and positions rely on.
+pub struct CompressedTrie<K: ?Sized, T, const N: usize> {
+    len: usize,
+    root: RadixNode<T>,
+    _key_type: PhantomData<K>,
+}
+
+impl<K: ?Sized, T, const N: usize> CompressedTrie<K, T, N>
+where
+    Trie<K, T, N>: TriePathBuilder<K>,
+{
+    #[must_use]
+    pub fn new() -> CompressedTrie<K, T, N> {
+        // Runs store path indices as bytes.
+        const { assert!(N <= 256, "compressed tries support arities up to 256") };
+        CompressedTrie {
+            len: 0,
+            root: RadixNode {
+                run: Vec::new(),
+                value: None,
+                children: Vec::new(),
+            },
+            _key_type: PhantomData,
+        }
+    }
+
+    pub fn insert(&mut self, key: &K, val: T) -> Option<T> {
+        let retval = self.root.insert(Self::path(key), val);
+        if retval.is_none() {
+            self.len = self.len.checked_add(1).expect("trie len overflow");
+        }
+        retval
+    }
+
+    #[must_use]
+    pub fn get(&self, key: &K) -> Option<&T> {
+        self.root.get(Self::path(key))?.value.as_ref()
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut T> {
+        self.root.get_mut(Self::path(key))?.value.as_mut()
+    }
+
+    #[deprecated(note = "renamed to `remove`")]
+    pub fn delete(&mut self, key: &K) -> Option<T> {
+        self.remove(key)
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<T> {
+        let retval = self.root.delete(&mut Self::path(key));
+        if retval.is_some() {
+            self.len = self.len.checked_sub(1).expect("trie len underflow");
+        }
+        retval
+    }
+
+    #[must_use]
+    pub fn count_prefix(&self, prefix: &K) -> usize {
+        self.root.covering(Self::path(prefix)).map_or(0, RadixNode::count_values)
+    }
+
+    fn path(key: &K) -> impl Iterator<Item = usize> + '_ {
+        <Trie<K, T, N> as TriePathBuilder<K>>::path_iter(key)
+    }
+}
+
+impl<K: ?Sized, T, const N: usize> Default for CompressedTrie<K, T, N>
+where
+    Trie<K, T, N>: TriePathBuilder<K>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: ?Sized, T, const N: usize> CompressedTrie<K, T, N> {
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Including the root; a `Trie` holding the same keys has one node per path index.
+    #[must_use]
+    pub fn node_count(&self) -> usize {
+        self.root.count_nodes()
+    }
+
+    // The paths and values in path order, as `Trie::iter_paths` yields them.
+    #[must_use]
+    pub fn iter_paths(&self) -> CompressedIter<'_, T> {
+        CompressedIter {
+            stack: vec![(&self.root, 0)],
+            path: Vec::new(),
+            pending: self.root.value.as_ref(),
+        }
+    }
+}
+
+impl<K: ?Sized + ToOwned, T, const N: usize> CompressedTrie<K, T, N>
+where
+    Trie<K, T, N>: TriePathDecoder<K>,
+{
+    // Entries in key order with decoded keys.
+    pub fn iter(&self) -> impl Iterator<Item = (K::Owned, &T)> + '_ {
+        self.iter_paths().map(|(path, val)| {
+            let key = <Trie<K, T, N> as TriePathDecoder<K>>::decode_path(&path).expect("trie holds a path that is not a valid key");
+            (key, val)
+        })
+    }
+}
+
+impl<K: ?Sized, T, const N: usize> From<Trie<K, T, N>> for CompressedTrie<K, T, N>
+where
+    Trie<K, T, N>: TriePathBuilder<K>,
+{
+    fn from(trie: Trie<K, T, N>) -> Self {
+        let mut compressed = CompressedTrie::new();
+        compressed.len = trie.len;
+        trie.root.drain_paths(|path, val| {
+            compressed.root.insert(path.iter().copied(), val);
+        });
+        compressed
+    }
+}
+
+pub struct CompressedIter<'a, T> {
+    // Each node on the way down with the position of the next child to enter.
+    stack: Vec<(&'a RadixNode<T>, usize)>,
+    path: Vec<usize>,
+    pending: Option<&'a T>,
+}
+
+impl<'a, T> Iterator for CompressedIter<'a, T> {
+    type Item = (Vec<usize>, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(val) = self.pending.take() {
+                return Some((self.path.clone(), val));
+            }
+            let (node, next) = self.stack.last_mut()?;
+            let node: &'a RadixNode<T> = node;
+            match node.children.get(*next) {
+                Some(child) => {
+                    *next += 1;
+                    self.path.extend(child.run.iter().map(|&index| usize::from(index)));
+                    self.stack.push((child, 0));
+                    self.pending = child.value.as_ref();
+                }
+                None => {
+                    self.path.truncate(self.path.len() - node.run.len());
+                    self.stack.pop();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compressed::CompressedTrie;
+    use crate::trie::Trie;
+
+    #[test]
+    fn split_and_merge() {
+        let mut trie: CompressedTrie<str, usize, 16> = CompressedTrie::new();
+        assert_eq!(trie.insert("romane", 0), None);
+        assert_eq!(trie.node_count(), 2);
+        trie.insert("romanus", 1);
+        trie.insert("rom", 2);
+        trie.insert("", 3);
+        assert_eq!(trie.insert("rom", 4), Some(2));
+        // root, "rom", "an" up to the branch at the high nibble of 'e' (6) and 'u' (7), two leaves.
+        assert_eq!(trie.node_count(), 5);
+        assert_eq!((trie.len(), trie.get("rom"), trie.get("roma"), trie.get("romanus")), (4, Some(&4), None, Some(&1)));
+        assert_eq!((trie.count_prefix("roma"), trie.count_prefix("r"), trie.count_prefix("x")), (2, 3, 0));
+        let keys: Vec<String> = trie.iter().map(|(key, _)| key).collect();
+        assert_eq!(keys, vec!["", "rom", "romane", "romanus"]);
+
+        assert_eq!(trie.remove("romanus"), Some(1));
+        assert_eq!(trie.remove("roma"), None);
+        assert_eq!(trie.node_count(), 3);
+        assert_eq!(trie.remove("rom"), Some(4));
+        assert_eq!(trie.node_count(), 2);
+        assert_eq!(trie.get("romane"), Some(&0));
+
+        let mut plain: Trie<str, usize, 16> = Trie::new();
+        for (i, key) in ["romane", "romanus", "rom"].iter().enumerate() {
+            plain.insert(key, i);
+        }
+        let paths: Vec<Vec<usize>> = plain.iter_paths().map(|(path, _)| path).collect();
+        let compressed = CompressedTrie::from(plain);
+        assert_eq!(compressed.iter_paths().map(|(path, _)| path).collect::<Vec<_>>(), paths);
+        assert_eq!(compressed.len(), 3);
+    }
+}
@@ -0,0 +1,467 @@
+use std::collections::HashMap;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::marker::PhantomData;
+
+use crate::codec::ValueCodec;
+use crate::trie::{Trie, TriePathBuilder};
+
+const MAGIC: &[u8; 8] = b"HTRIELOG";
+const FREED: u64 = u64::MAX;
+const KIND_NODE: u8 = 0;
+const KIND_FREE: u8 = 1;
+const DEFAULT_CACHE_CAPACITY: usize = 4096;
+
+#[derive(Clone, Default)]
+struct DiskNode {
+    value: Option<Vec<u8>>,
+    children: Vec<(usize, u64)>,
+}
+
+impl DiskNode {
+    fn encode(&self, out: &mut Vec<u8>) {
+        if let Some(val) = &self.value {
+            out.push(1);
+            out.extend_from_slice(&u32::try_from(val.len()).unwrap().to_le_bytes());
+            out.extend_from_slice(val);
+        } else {
+            out.push(0);
+        }
+        out.extend_from_slice(&u32::try_from(self.children.len()).unwrap().to_le_bytes());
+        for &(index, id) in &self.children {
+            out.extend_from_slice(&u32::try_from(index).unwrap().to_le_bytes());
+            out.extend_from_slice(&id.to_le_bytes());
+        }
+    }
+
+    fn decode(mut bytes: &[u8]) -> io::Result<DiskNode> {
+        let value = if take(&mut bytes, 1)?[0] == 1 {
+            let len = u32::from_le_bytes(take(&mut bytes, 4)?.try_into().unwrap()) as usize;
+            Some(take(&mut bytes, len)?.to_vec())
+        } else {
+            None
+        };
+        let count = u32::from_le_bytes(take(&mut bytes, 4)?.try_into().unwrap()) as usize;
+        let mut children = Vec::with_capacity(count);
+        for _ in 0..count {
+            let index = u32::from_le_bytes(take(&mut bytes, 4)?.try_into().unwrap()) as usize;
+            let id = u64::from_le_bytes(take(&mut bytes, 8)?.try_into().unwrap());
+            children.push((index, id));
+        }
+        Ok(DiskNode { value, children })
+    }
+}
+
+fn take<'a>(bytes: &mut &'a [u8], len: usize) -> io::Result<&'a [u8]> {
+    if bytes.len() < len {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "truncated trie node record"));
+    }
+    let (head, tail) = bytes.split_at(len);
+    *bytes = tail;
+    Ok(head)
+}
+
+// Record layout: payload length (u32), node id (u64), kind (u8), payload.
+fn append<S: Write + Seek>(storage: &mut S, end: &mut u64, id: u64, kind: u8, payload: &[u8]) -> io::Result<u64> {
+    let offset = *end;
+    let mut record = Vec::with_capacity(13 + payload.len());
+    record.extend_from_slice(&u32::try_from(payload.len()).unwrap().to_le_bytes());
+    record.extend_from_slice(&id.to_le_bytes());
+    record.push(kind);
+    record.extend_from_slice(payload);
+    storage.seek(SeekFrom::Start(offset))?;
+    storage.write_all(&record)?;
+    *end += record.len() as u64;
+    Ok(offset)
+}
+
+struct CacheEntry {
+    node: DiskNode,
+    last_used: u64,
+}
+
+/// A trie whose nodes live in an append-only log, with only a bounded set of them cached in memory.
+///
+/// Every change appends new versions of the touched nodes, and an in-memory table maps each stable
+/// node id to its latest record. Nodes are read back from the log when they are not cached, so the
+/// trie can be much larger than available memory. `open` rebuilds the table by replaying the log,
+/// ignoring a torn final record and reclaiming nodes an interrupted change left unreachable, and
+/// `compact_into` copies only the live records to new storage.
+pub struct DiskTrie<K: ?Sized, T, const N: usize, S, C> {
+    storage: S,
+    codec: C,
+    offsets: Vec<u64>,
+    free_ids: Vec<u64>,
+    end: u64,
+    len: usize,
+    cache: HashMap<u64, CacheEntry>,
+    cache_capacity: usize,
+    clock: u64,
+    _key_type: PhantomData<K>,
+    _value_type: PhantomData<T>,
+}
+
+impl<K: ?Sized, T, const N: usize, S: Read + Write + Seek, C: ValueCodec<T>> DiskTrie<K, T, N, S, C>
+where
+    Trie<K, T, N>: TriePathBuilder<K>,
+{
+    pub fn create(mut storage: S, codec: C) -> io::Result<DiskTrie<K, T, N, S, C>> {
+        storage.seek(SeekFrom::Start(0))?;
+        storage.write_all(MAGIC)?;
+        let mut trie = Self::with_storage(storage, codec, MAGIC.len() as u64);
+        trie.offsets.push(FREED);
+        trie.write_node(0, DiskNode::default())?;
+        Ok(trie)
+    }
+
+    pub fn open(mut storage: S, codec: C) -> io::Result<DiskTrie<K, T, N, S, C>> {
+        storage.seek(SeekFrom::Start(0))?;
+        let mut magic = [0; 8];
+        storage.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a trie log"));
+        }
+        let mut offsets = Vec::new();
+        let mut end = MAGIC.len() as u64;
+        let mut header = [0; 13];
+        loop {
+            if Self::read_full(&mut storage, &mut header)? < header.len() {
+                break;
+            }
+            let len = u32::from_le_bytes(header[0..4].try_into().unwrap()) as usize;
+            let id = u64::from_le_bytes(header[4..12].try_into().unwrap());
+            let mut payload = vec![0; len];
+            if Self::read_full(&mut storage, &mut payload)? < len {
+                break;
+            }
+            let index = usize::try_from(id).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+            if index >= offsets.len() {
+                offsets.resize(index + 1, FREED);
+            }
+            offsets[index] = if header[12] == KIND_FREE { FREED } else { end };
+            end += (header.len() + len) as u64;
+        }
+        if offsets.first().map_or(true, |&offset| offset == FREED) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "trie log has no root node"));
+        }
+        let mut trie = Self::with_storage(storage, codec, end);
+        trie.offsets = offsets;
+        // An insert appends several records deepest first, so a crash partway through can leave
+        // nodes that no parent refers to yet. Only nodes reachable from the root count; the
+        // others are reclaimed along with the freed ids.
+        let mut reachable = vec![false; trie.offsets.len()];
+        let mut stack = vec![0];
+        while let Some(id) = stack.pop() {
+            reachable[id as usize] = true;
+            let node = trie.node(id)?;
+            trie.len += usize::from(node.value.is_some());
+            for &(_, child) in &node.children {
+                let index = usize::try_from(child).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+                if trie.offsets.get(index).map_or(true, |&offset| offset == FREED) || reachable[index] {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, "trie log refers to a missing or shared node"));
+                }
+                stack.push(child);
+            }
+        }
+        for (id, reachable) in reachable.into_iter().enumerate() {
+            if !reachable {
+                trie.offsets[id] = FREED;
+                trie.free_ids.push(id as u64);
+            }
+        }
+        Ok(trie)
+    }
+
+    pub fn get(&mut self, key: &K) -> io::Result<Option<T>> {
+        let path = <Trie<K, T, N> as TriePathBuilder<K>>::build_path(key);
+        let mut node = self.node(0)?;
+        for child_index in path {
+            match node.children.binary_search_by_key(&child_index, |child| child.0) {
+                Ok(pos) => node = self.node(node.children[pos].1)?,
+                Err(_) => return Ok(None),
+            }
+        }
+        node.value.map(|bytes| self.codec.decode(&bytes)).transpose()
+    }
+
+    pub fn insert(&mut self, key: &K, val: T) -> io::Result<Option<T>> {
+        let path = <Trie<K, T, N> as TriePathBuilder<K>>::build_path(key);
+        let mut bytes = Vec::new();
+        self.codec.encode(&val, &mut bytes);
+
+        // Modified nodes are written deepest first so a parent never refers to an unwritten child.
+        let mut pending = Vec::new();
+        let mut id = 0;
+        let mut node = self.node(0)?;
+        for child_index in path {
+            match node.children.binary_search_by_key(&child_index, |child| child.0) {
+                Ok(pos) => {
+                    id = node.children[pos].1;
+                    node = self.node(id)?;
+                }
+                Err(pos) => {
+                    let child_id = self.alloc_id();
+                    node.children.insert(pos, (child_index, child_id));
+                    pending.push((id, node));
+                    id = child_id;
+                    node = DiskNode::default();
+                }
+            }
+        }
+        let retval = node.value.replace(bytes);
+        pending.push((id, node));
+        for (id, node) in pending.into_iter().rev() {
+            self.write_node(id, node)?;
+        }
+        if retval.is_none() {
+            self.len = self.len.checked_add(1).expect("trie len overflow");
+        }
+        retval.map(|bytes| self.codec.decode(&bytes)).transpose()
+    }
+
+    #[deprecated(note = "renamed to `remove`")]
+    pub fn delete(&mut self, key: &K) -> io::Result<Option<T>> {
+        self.remove(key)
+    }
+
+    pub fn remove(&mut self, key: &K) -> io::Result<Option<T>> {
+        let path = <Trie<K, T, N> as TriePathBuilder<K>>::build_path(key);
+        let mut trail = vec![(0, self.node(0)?)];
+        for child_index in path {
+            let node = &trail.last().unwrap().1;
+            match node.children.binary_search_by_key(&child_index, |child| child.0) {
+                Ok(pos) => {
+                    let id = node.children[pos].1;
+                    trail.push((id, self.node(id)?));
+                }
+                Err(_) => return Ok(None),
+            }
+        }
+        let (mut id, mut node) = trail.pop().unwrap();
+        let Some(retval) = node.value.take() else {
+            return Ok(None);
+        };
+        let mut freed = Vec::new();
+        while node.value.is_none() && node.children.is_empty() {
+            let Some((parent_id, mut parent)) = trail.pop() else {
+                break;
+            };
+            parent.children.retain(|child| child.1 != id);
+            freed.push(id);
+            id = parent_id;
+            node = parent;
+        }
+        self.write_node(id, node)?;
+        for id in freed {
+            self.free_node(id)?;
+        }
+        self.len = self.len.checked_sub(1).expect("trie len underflow");
+        self.codec.decode(&retval).map(Some)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.storage.flush()
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[must_use]
+    pub fn live_nodes(&self) -> usize {
+        self.offsets.len() - self.free_ids.len()
+    }
+
+    #[must_use]
+    pub fn log_len(&self) -> u64 {
+        self.end
+    }
+
+    pub fn set_cache_capacity(&mut self, capacity: usize) {
+        self.cache_capacity = capacity.max(1);
+        self.evict();
+    }
+
+    #[must_use]
+    pub fn into_storage(self) -> S {
+        self.storage
+    }
+
+    pub fn compact_into<S2: Read + Write + Seek>(mut self, mut storage: S2) -> io::Result<DiskTrie<K, T, N, S2, C>> {
+        storage.seek(SeekFrom::Start(0))?;
+        storage.write_all(MAGIC)?;
+        let mut end = MAGIC.len() as u64;
+        let mut offsets = vec![FREED; self.offsets.len()];
+        let mut payload = Vec::new();
+        for id in 0..self.offsets.len() as u64 {
+            if self.offsets[id as usize] != FREED {
+                payload.clear();
+                self.node(id)?.encode(&mut payload);
+                offsets[id as usize] = append(&mut storage, &mut end, id, KIND_NODE, &payload)?;
+            }
+        }
+        let mut trie = DiskTrie::with_storage(storage, self.codec, end);
+        trie.offsets = offsets;
+        trie.free_ids = self.free_ids;
+        trie.len = self.len;
+        trie.cache_capacity = self.cache_capacity;
+        Ok(trie)
+    }
+
+    fn with_storage(storage: S, codec: C, end: u64) -> DiskTrie<K, T, N, S, C> {
+        DiskTrie {
+            storage,
+            codec,
+            offsets: Vec::new(),
+            free_ids: Vec::new(),
+            end,
+            len: 0,
+            cache: HashMap::new(),
+            cache_capacity: DEFAULT_CACHE_CAPACITY,
+            clock: 0,
+            _key_type: PhantomData,
+            _value_type: PhantomData,
+        }
+    }
+
+    fn read_full(storage: &mut S, buf: &mut [u8]) -> io::Result<usize> {
+        let mut filled = 0;
+        while filled < buf.len() {
+            match storage.read(&mut buf[filled..]) {
+                Ok(0) => break,
+                Ok(read) => filled += read,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(filled)
+    }
+
+    fn node(&mut self, id: u64) -> io::Result<DiskNode> {
+        self.clock += 1;
+        if let Some(entry) = self.cache.get_mut(&id) {
+            entry.last_used = self.clock;
+            return Ok(entry.node.clone());
+        }
+        let offset = self.offsets[id as usize];
+        self.storage.seek(SeekFrom::Start(offset))?;
+        let mut header = [0; 13];
+        self.storage.read_exact(&mut header)?;
+        let mut payload = vec![0; u32::from_le_bytes(header[0..4].try_into().unwrap()) as usize];
+        self.storage.read_exact(&mut payload)?;
+        let node = DiskNode::decode(&payload)?;
+        self.cache_insert(id, node.clone());
+        Ok(node)
+    }
+
+    fn write_node(&mut self, id: u64, node: DiskNode) -> io::Result<()> {
+        let mut payload = Vec::new();
+        node.encode(&mut payload);
+        self.offsets[id as usize] = append(&mut self.storage, &mut self.end, id, KIND_NODE, &payload)?;
+        self.clock += 1;
+        self.cache_insert(id, node);
+        Ok(())
+    }
+
+    fn free_node(&mut self, id: u64) -> io::Result<()> {
+        append(&mut self.storage, &mut self.end, id, KIND_FREE, &[])?;
+        self.offsets[id as usize] = FREED;
+        self.free_ids.push(id);
+        self.cache.remove(&id);
+        Ok(())
+    }
+
+    fn alloc_id(&mut self) -> u64 {
+        self.free_ids.pop().unwrap_or_else(|| {
+            self.offsets.push(FREED);
+            self.offsets.len() as u64 - 1
+        })
+    }
+
+    fn cache_insert(&mut self, id: u64, node: DiskNode) {
+        self.cache.insert(
+            id,
+            CacheEntry {
+                node,
+                last_used: self.clock,
+            },
+        );
+        self.evict();
+    }
+
+    // Drops the least recently used half of the cache once it outgrows its capacity.
+    fn evict(&mut self) {
+        if self.cache.len() <= self.cache_capacity {
+            return;
+        }
+        let mut ages: Vec<u64> = self.cache.values().map(|entry| entry.last_used).collect();
+        let cutoff_index = (ages.len() - self.cache_capacity / 2).min(ages.len() - 1);
+        let cutoff = *ages.select_nth_unstable(cutoff_index).1;
+        self.cache.retain(|_, entry| entry.last_used >= cutoff);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::codec::LeBytesCodec;
+    use crate::disk::DiskTrie;
+
+    #[test]
+    fn persists_and_pages() {
+        let mut trie: DiskTrie<str, u32, 16, _, _> = DiskTrie::create(Cursor::new(Vec::new()), LeBytesCodec).unwrap();
+        trie.set_cache_capacity(8);
+        let words: Vec<String> = (0..200).map(|i| format!("key{i}")).collect();
+        for (i, word) in words.iter().enumerate() {
+            assert_eq!(trie.insert(word, i as u32).unwrap(), None);
+        }
+        assert_eq!(trie.insert("key7", 700).unwrap(), Some(7));
+        for (i, word) in words.iter().enumerate().skip(8) {
+            assert_eq!(trie.get(word).unwrap(), Some(i as u32));
+        }
+        assert_eq!(trie.get("key").unwrap(), None);
+        for word in words.iter().skip(100) {
+            assert!(trie.remove(word).unwrap().is_some());
+        }
+        assert_eq!(trie.remove("key150").unwrap(), None);
+        assert_eq!(trie.len(), 100);
+
+        let log_len = trie.log_len();
+        let mut trie: DiskTrie<str, u32, 16, _, _> = DiskTrie::open(trie.into_storage(), LeBytesCodec).unwrap();
+        assert_eq!(trie.len(), 100);
+        assert_eq!(trie.get("key7").unwrap(), Some(700));
+        assert_eq!(trie.get("key150").unwrap(), None);
+
+        let mut trie = trie.compact_into(Cursor::new(Vec::new())).unwrap();
+        assert!(trie.log_len() < log_len);
+        assert_eq!(trie.get("key99").unwrap(), Some(99));
+        assert_eq!(trie.insert("key150", 150).unwrap(), None);
+        let mut trie: DiskTrie<str, u32, 16, _, _> = DiskTrie::open(trie.into_storage(), LeBytesCodec).unwrap();
+        assert_eq!(trie.len(), 101);
+        assert_eq!(trie.get("key150").unwrap(), Some(150));
+    }
+
+    #[test]
+    fn torn_insert_reclaimed() {
+        let mut trie: DiskTrie<str, u32, 16, _, _> = DiskTrie::create(Cursor::new(Vec::new()), LeBytesCodec).unwrap();
+        trie.insert("a", 1).unwrap();
+        let (live, log_len) = (trie.live_nodes(), trie.log_len());
+        trie.insert("abc", 2).unwrap();
+        // Keep only the new leaf's record (13 header bytes, 13 payload bytes), as if the process
+        // died before writing the nodes above it.
+        let mut bytes = trie.into_storage().into_inner();
+        bytes.truncate(log_len as usize + 26);
+
+        let mut trie: DiskTrie<str, u32, 16, _, _> = DiskTrie::open(Cursor::new(bytes), LeBytesCodec).unwrap();
+        assert_eq!((trie.len(), trie.live_nodes()), (1, live));
+        assert_eq!(trie.get("abc").unwrap(), None);
+        trie.insert("b", 3).unwrap();
+        assert_eq!((trie.len(), trie.get("a").unwrap(), trie.get("b").unwrap()), (2, Some(1), Some(3)));
+    }
+}
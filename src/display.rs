@@ -0,0 +1,74 @@
+use std::fmt;
+
+/// Renders a key for logs and terminals, escaping control characters and invalid UTF-8.
+#[derive(Clone, Copy)]
+pub struct KeyDisplay<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> KeyDisplay<'a> {
+    #[must_use]
+    pub fn new(bytes: &'a [u8]) -> KeyDisplay<'a> {
+        KeyDisplay { bytes }
+    }
+}
+
+impl<'a> From<&'a [u8]> for KeyDisplay<'a> {
+    fn from(bytes: &'a [u8]) -> KeyDisplay<'a> {
+        KeyDisplay::new(bytes)
+    }
+}
+
+impl<'a> From<&'a str> for KeyDisplay<'a> {
+    fn from(key: &'a str) -> KeyDisplay<'a> {
+        KeyDisplay::new(key.as_bytes())
+    }
+}
+
+impl KeyDisplay<'_> {
+    fn write_escaped(&self, f: &mut fmt::Formatter<'_>, quoted: bool) -> fmt::Result {
+        for chunk in self.bytes.utf8_chunks() {
+            for c in chunk.valid().chars() {
+                match c {
+                    '\\' => f.write_str("\\\\")?,
+                    '"' if quoted => f.write_str("\\\"")?,
+                    '\n' => f.write_str("\\n")?,
+                    '\r' => f.write_str("\\r")?,
+                    '\t' => f.write_str("\\t")?,
+                    c if c.is_control() => write!(f, "\\u{{{:x}}}", u32::from(c))?,
+                    c => write!(f, "{c}")?,
+                }
+            }
+            for byte in chunk.invalid() {
+                write!(f, "\\x{byte:02x}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for KeyDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_escaped(f, false)
+    }
+}
+
+impl fmt::Debug for KeyDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("\"")?;
+        self.write_escaped(f, true)?;
+        f.write_str("\"")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::display::KeyDisplay;
+
+    #[test]
+    fn escapes() {
+        let key = KeyDisplay::new(b"caf\xc3\xa9\n\x00\xff\\");
+        assert_eq!(key.to_string(), "café\\n\\u{0}\\xff\\\\");
+        assert_eq!(format!("{:?}", KeyDisplay::from("a\"b")), "\"a\\\"b\"");
+    }
+}
@@ -0,0 +1,124 @@
+use crate::trie::{Trie, TriePathBuilder};
+
+/// A `Trie` wrapper that tags entries with the epoch they were written in, so old generations
+/// can be hidden at once and swept out later.
+pub struct EpochTrie<K: ?Sized, T, const N: usize> {
+    trie: Trie<K, (T, u64), N>,
+    epoch: u64,
+    // Entries written before this epoch are invisible but may still be stored.
+    floor: u64,
+}
+
+impl<K: ?Sized, T, const N: usize> EpochTrie<K, T, N>
+where
+    Trie<K, (T, u64), N>: TriePathBuilder<K>,
+{
+    #[must_use]
+    pub fn new() -> EpochTrie<K, T, N> {
+        EpochTrie {
+            trie: Trie::new(),
+            epoch: 0,
+            floor: 0,
+        }
+    }
+
+    #[must_use]
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    pub fn advance_epoch(&mut self) -> u64 {
+        self.epoch += 1;
+        self.epoch
+    }
+
+    #[must_use]
+    pub fn get(&self, key: &K) -> Option<&T> {
+        self.trie.get(key).filter(|(_, epoch)| *epoch >= self.floor).map(|(val, _)| val)
+    }
+
+    #[must_use]
+    pub fn entry_epoch(&self, key: &K) -> Option<u64> {
+        self.trie.get(key).map(|&(_, epoch)| epoch).filter(|&epoch| epoch >= self.floor)
+    }
+
+    // Tags the entry with the current epoch; returns the previous value only if it was visible.
+    pub fn insert(&mut self, key: &K, val: T) -> Option<T> {
+        self.trie
+            .insert(key, (val, self.epoch))
+            .filter(|(_, epoch)| *epoch >= self.floor)
+            .map(|(val, _)| val)
+    }
+
+    #[deprecated(note = "renamed to `remove`")]
+    pub fn delete(&mut self, key: &K) -> Option<T> {
+        self.remove(key)
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<T> {
+        self.trie.remove(key).filter(|(_, epoch)| *epoch >= self.floor).map(|(val, _)| val)
+    }
+
+    // Hides every entry written before `epoch` without touching the nodes.
+    pub fn expire_before(&mut self, epoch: u64) {
+        self.floor = self.floor.max(epoch);
+    }
+
+    // Physically removes the entries written before `epoch` and returns how many were dropped.
+    pub fn purge_older_than(&mut self, epoch: u64) -> usize {
+        let stale: Vec<Vec<usize>> = self
+            .trie
+            .iter_paths()
+            .filter(|(_, (_, written))| *written < epoch)
+            .map(|(path, _)| path)
+            .collect();
+        for path in &stale {
+            let _ = self.trie.delete_path(path);
+        }
+        stale.len()
+    }
+
+    // Counts stored entries, including expired ones that have not been purged yet.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.trie.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.trie.is_empty()
+    }
+}
+
+impl<K: ?Sized, T, const N: usize> Default for EpochTrie<K, T, N>
+where
+    Trie<K, (T, u64), N>: TriePathBuilder<K>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::epoch::EpochTrie;
+
+    #[test]
+    fn expire_and_purge() {
+        let mut trie: EpochTrie<str, usize, 16> = EpochTrie::new();
+        trie.insert("old", 1);
+        trie.insert("kept", 2);
+        let current = trie.advance_epoch();
+        trie.insert("kept", 3);
+        trie.insert("new", 4);
+        assert_eq!(trie.entry_epoch("old"), Some(0));
+
+        trie.expire_before(current);
+        assert_eq!(trie.get("old"), None);
+        assert_eq!(trie.get("kept"), Some(&3));
+        assert_eq!(trie.len(), 3);
+        assert_eq!(trie.purge_older_than(current), 1);
+        assert_eq!(trie.len(), 2);
+        assert_eq!(trie.insert("old", 5), None);
+    }
+}
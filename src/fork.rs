@@ -0,0 +1,177 @@
+use std::cmp::Ordering;
+use std::iter::Peekable;
+
+use crate::trie::{Trie, TrieIter, TriePathBuilder};
+
+/// A writable view of a borrowed trie that keeps its own changes in a delta, so the base is
+/// shared as-is and only the overridden keys are stored again.
+///
+/// Deletions of base entries are recorded as tombstones. Many forks can share one base.
+pub struct Fork<'a, K: ?Sized, T, const N: usize> {
+    base: &'a Trie<K, T, N>,
+    delta: Trie<K, Option<T>, N>,
+    len: usize,
+}
+
+impl<K: ?Sized, T, const N: usize> Trie<K, T, N>
+where
+    Trie<K, T, N>: TriePathBuilder<K>,
+    Trie<K, Option<T>, N>: TriePathBuilder<K>,
+{
+    #[must_use]
+    pub fn fork(&self) -> Fork<'_, K, T, N> {
+        Fork {
+            base: self,
+            delta: Trie::new(),
+            len: self.len(),
+        }
+    }
+}
+
+impl<'a, K: ?Sized, T: Clone, const N: usize> Fork<'a, K, T, N>
+where
+    Trie<K, T, N>: TriePathBuilder<K>,
+    Trie<K, Option<T>, N>: TriePathBuilder<K>,
+{
+    #[must_use]
+    pub fn get(&self, key: &K) -> Option<&T> {
+        let path = <Trie<K, T, N> as TriePathBuilder<K>>::build_path(key);
+        match self.delta.get_path(&path) {
+            Some(val) => val.as_ref(),
+            None => self.base.get_path(&path),
+        }
+    }
+
+    pub fn insert(&mut self, key: &K, val: T) -> Option<T> {
+        let path = <Trie<K, T, N> as TriePathBuilder<K>>::build_path(key);
+        let retval = match self.delta.insert_path(&path, Some(val)) {
+            Some(old) => old,
+            None => self.base.get_path(&path).cloned(),
+        };
+        if retval.is_none() {
+            self.len += 1;
+        }
+        retval
+    }
+
+    #[deprecated(note = "renamed to `remove`")]
+    pub fn delete(&mut self, key: &K) -> Option<T> {
+        self.remove(key)
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<T> {
+        let path = <Trie<K, T, N> as TriePathBuilder<K>>::build_path(key);
+        let in_base = self.base.get_path(&path);
+        let retval = if let Some(slot) = self.delta.get_path_mut(&path) {
+            let retval = slot.take();
+            if retval.is_some() && in_base.is_none() {
+                self.delta.delete_path(&path);
+            }
+            retval
+        } else {
+            let retval = in_base.cloned();
+            if retval.is_some() {
+                self.delta.insert_path(&path, None);
+            }
+            retval
+        };
+        if retval.is_some() {
+            self.len -= 1;
+        }
+        retval
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Keys this fork overrides or deletes.
+    #[must_use]
+    pub fn delta_len(&self) -> usize {
+        self.delta.len()
+    }
+
+    #[must_use]
+    pub fn base(&self) -> &'a Trie<K, T, N> {
+        self.base
+    }
+
+    #[must_use]
+    pub fn iter(&self) -> ForkIter<'_, T, N> {
+        ForkIter {
+            base: self.base.iter_paths().peekable(),
+            delta: self.delta.iter_paths().peekable(),
+        }
+    }
+
+    // Copies the merged view into a standalone trie.
+    #[must_use]
+    pub fn to_trie(&self) -> Trie<K, T, N> {
+        let mut trie = Trie::with_layout(self.base.layout());
+        for (path, val) in self.iter() {
+            trie.insert_path(&path, val.clone());
+        }
+        trie
+    }
+}
+
+pub struct ForkIter<'b, T, const N: usize> {
+    base: Peekable<TrieIter<'b, T, N>>,
+    delta: Peekable<TrieIter<'b, Option<T>, N>>,
+}
+
+impl<'b, T, const N: usize> Iterator for ForkIter<'b, T, N> {
+    type Item = (Vec<usize>, &'b T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let order = match (self.base.peek(), self.delta.peek()) {
+                (None, None) => return None,
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (Some((base_path, _)), Some((delta_path, _))) => base_path.cmp(delta_path),
+            };
+            if order == Ordering::Less {
+                return self.base.next();
+            }
+            if order == Ordering::Equal {
+                self.base.next();
+            }
+            if let Some((path, Some(val))) = self.delta.next() {
+                return Some((path, val));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trie::Trie;
+
+    #[test]
+    fn overrides() {
+        let mut base: Trie<str, usize, 16> = Trie::new();
+        base.insert("a", 1);
+        base.insert("b", 2);
+        let mut first = base.fork();
+        let mut second = base.fork();
+
+        assert_eq!(first.insert("a", 10), Some(1));
+        assert_eq!(first.remove("b"), Some(2));
+        assert_eq!(first.insert("c", 3), None);
+        assert_eq!(second.remove("a"), Some(1));
+
+        assert_eq!((first.get("a"), first.get("b"), first.len()), (Some(&10), None, 2));
+        assert_eq!((second.get("a"), second.get("b"), second.len()), (None, Some(&2), 1));
+        assert_eq!(base.get("a"), Some(&1));
+        let values: Vec<usize> = first.iter().map(|(_, val)| *val).collect();
+        assert_eq!(values, vec![10, 3]);
+        assert_eq!(first.to_trie().len(), 2);
+    }
+}
@@ -0,0 +1,243 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::Deref;
+
+use crate::trie::{Trie, TriePathBuilder};
+
+pub(crate) const NO_VALUE: u32 = u32::MAX;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FrozenNode {
+    value: u32,
+    first_edge: u32,
+    edge_count: u32,
+}
+
+impl FrozenNode {
+    #[must_use]
+    pub const fn new(value: u32, first_edge: u32, edge_count: u32) -> FrozenNode {
+        FrozenNode {
+            value,
+            first_edge,
+            edge_count,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FrozenEdge {
+    index: u32,
+    node: u32,
+}
+
+impl FrozenEdge {
+    #[must_use]
+    pub const fn new(index: u32, node: u32) -> FrozenEdge {
+        FrozenEdge { index, node }
+    }
+}
+
+enum Storage<'a, X> {
+    Borrowed(&'a [X]),
+    Owned(Vec<X>),
+}
+
+impl<X> Deref for Storage<'_, X> {
+    type Target = [X];
+
+    fn deref(&self) -> &[X] {
+        match self {
+            Storage::Borrowed(items) => items,
+            Storage::Owned(items) => items,
+        }
+    }
+}
+
+/// An immutable trie stored as flat node, edge and value tables.
+///
+/// Nodes are laid out breadth first with the edges of each node contiguous and sorted by child
+/// index. The tables can be borrowed from `static` data, which is what `write_rust` generates for
+/// build scripts.
+pub struct FrozenTrie<'a, K: ?Sized, T, const N: usize> {
+    nodes: Storage<'a, FrozenNode>,
+    edges: Storage<'a, FrozenEdge>,
+    values: Storage<'a, T>,
+    _key_type: PhantomData<K>,
+}
+
+impl<'a, K: ?Sized, T, const N: usize> FrozenTrie<'a, K, T, N> {
+    #[must_use]
+    pub const fn from_static(nodes: &'a [FrozenNode], edges: &'a [FrozenEdge], values: &'a [T]) -> FrozenTrie<'a, K, T, N> {
+        FrozenTrie {
+            nodes: Storage::Borrowed(nodes),
+            edges: Storage::Borrowed(edges),
+            values: Storage::Borrowed(values),
+            _key_type: PhantomData,
+        }
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    #[must_use]
+    pub fn iter(&self) -> FrozenIter<'_, T> {
+        FrozenIter {
+            nodes: &self.nodes,
+            edges: &self.edges,
+            values: &self.values,
+            stack: if self.nodes.is_empty() { Vec::new() } else { vec![(0, 0, None)] },
+            path: Vec::new(),
+        }
+    }
+
+    pub(crate) fn get_path(&self, path: &[usize]) -> Option<&T> {
+        let mut node = self.nodes.first()?;
+        for &child_index in path {
+            let first = node.first_edge as usize;
+            let edges = &self.edges[first..first + node.edge_count as usize];
+            let pos = edges.binary_search_by_key(&child_index, |edge| edge.index as usize).ok()?;
+            node = &self.nodes[edges[pos].node as usize];
+        }
+        self.values.get(node.value as usize)
+    }
+
+    pub fn write_rust<W: fmt::Write>(&self, out: &mut W, name: &str, key_type: &str, value_type: &str) -> fmt::Result
+    where
+        T: fmt::Debug,
+    {
+        let path = "::hardly_trie::frozen";
+        writeln!(out, "pub static {name}: {path}::FrozenTrie<'static, {key_type}, {value_type}, {N}> = {path}::FrozenTrie::from_static(")?;
+        writeln!(out, "    &[")?;
+        for node in self.nodes.iter() {
+            writeln!(out, "        {path}::FrozenNode::new({}, {}, {}),", node.value, node.first_edge, node.edge_count)?;
+        }
+        writeln!(out, "    ],")?;
+        writeln!(out, "    &[")?;
+        for edge in self.edges.iter() {
+            writeln!(out, "        {path}::FrozenEdge::new({}, {}),", edge.index, edge.node)?;
+        }
+        writeln!(out, "    ],")?;
+        writeln!(out, "    &[")?;
+        for val in self.values.iter() {
+            writeln!(out, "        {val:?},")?;
+        }
+        writeln!(out, "    ],")?;
+        writeln!(out, ");")
+    }
+}
+
+pub struct FrozenIter<'a, T> {
+    nodes: &'a [FrozenNode],
+    edges: &'a [FrozenEdge],
+    values: &'a [T],
+    // (node, length of the parent's path, index of the edge leading here)
+    stack: Vec<(usize, usize, Option<usize>)>,
+    path: Vec<usize>,
+}
+
+impl<'a, T> Iterator for FrozenIter<'a, T> {
+    type Item = (Vec<usize>, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((node_id, depth, index)) = self.stack.pop() {
+            self.path.truncate(depth);
+            self.path.extend(index);
+            let node = &self.nodes[node_id];
+            let first = node.first_edge as usize;
+            for edge in self.edges[first..first + node.edge_count as usize].iter().rev() {
+                self.stack.push((edge.node as usize, self.path.len(), Some(edge.index as usize)));
+            }
+            if let Some(val) = self.values.get(node.value as usize) {
+                return Some((self.path.clone(), val));
+            }
+        }
+        None
+    }
+}
+
+impl<K: ?Sized, T, const N: usize> FrozenTrie<'_, K, T, N>
+where
+    Trie<K, T, N>: TriePathBuilder<K>,
+{
+    #[must_use]
+    pub fn get(&self, key: &K) -> Option<&T> {
+        self.get_path(&<Trie<K, T, N> as TriePathBuilder<K>>::build_path(key))
+    }
+}
+
+impl<K: ?Sized, T, const N: usize> Trie<K, T, N> {
+    #[must_use]
+    pub fn freeze<'a>(self) -> FrozenTrie<'a, K, T, N> {
+        let mut nodes = Vec::new();
+        let mut edges = Vec::new();
+        let mut values = Vec::with_capacity(self.len);
+        let mut queue = VecDeque::from([self.root]);
+        // Children are queued in order, so a node's id is its position in the queue overall.
+        let mut next_id = 1;
+        while let Some(node) = queue.pop_front() {
+            let (value, children) = node.into_parts();
+            let value_index = if let Some(val) = value {
+                values.push(val);
+                u32::try_from(values.len() - 1).unwrap()
+            } else {
+                NO_VALUE
+            };
+            nodes.push(FrozenNode::new(
+                value_index,
+                u32::try_from(edges.len()).unwrap(),
+                u32::try_from(children.len()).unwrap(),
+            ));
+            for (index, child) in children {
+                edges.push(FrozenEdge::new(u32::try_from(index).unwrap(), next_id));
+                next_id += 1;
+                queue.push_back(child);
+            }
+        }
+        FrozenTrie {
+            nodes: Storage::Owned(nodes),
+            edges: Storage::Owned(edges),
+            values: Storage::Owned(values),
+            _key_type: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::frozen::FrozenTrie;
+    use crate::trie::Trie;
+
+    #[test]
+    fn freeze() {
+        let mut trie: Trie<str, usize, 16> = Trie::new();
+        for (i, word) in ["app", "apple", "", "banana", "band"].into_iter().enumerate() {
+            trie.insert(word, i);
+        }
+        let frozen = trie.freeze();
+        assert_eq!(frozen.len(), 5);
+        assert_eq!(frozen.get("app"), Some(&0));
+        assert_eq!(frozen.get("apple"), Some(&1));
+        assert_eq!(frozen.get(""), Some(&2));
+        assert_eq!(frozen.get("band"), Some(&4));
+        assert_eq!(frozen.get("ban"), None);
+        assert_eq!(frozen.get("bandana"), None);
+        let values: Vec<usize> = frozen.iter().map(|(_, val)| *val).collect();
+        assert_eq!(values, vec![2, 0, 1, 3, 4]);
+
+        let borrowed: FrozenTrie<str, usize, 16> = FrozenTrie::from_static(&frozen.nodes, &frozen.edges, &frozen.values);
+        assert_eq!(borrowed.get("banana"), Some(&3));
+
+        let mut source = String::new();
+        frozen.write_rust(&mut source, "WORDS", "str", "usize").unwrap();
+        assert!(source.starts_with("pub static WORDS: ::hardly_trie::frozen::FrozenTrie<'static, str, usize, 16> ="));
+        assert!(source.ends_with(");\n"));
+    }
+}
@@ -0,0 +1,177 @@
+use crate::trie::{decode_nibbles, ByteKey, Trie, TriePathDecoder};
+use crate::trie_node::TrieNode;
+
+impl<K: ?Sized + ByteKey + ToOwned, T> Trie<K, T, 16>
+where
+    Trie<K, T, 16>: TriePathDecoder<K>,
+{
+    // Every pair of stored keys within `max_distance` byte edits (Levenshtein) of each other, with
+    // the distance. Each key runs one edit-distance search that prunes subtrees as soon as no
+    // extension can come back within range, instead of comparing all pairs.
+    #[must_use]
+    pub fn find_near_duplicates(&self, max_distance: usize) -> Vec<(K::Owned, K::Owned, usize)> {
+        let mut pairs = Vec::new();
+        let mut paths = Vec::with_capacity(self.len);
+        self.root.visit_paths(|path, _| paths.push(path.to_vec()));
+        for path in paths {
+            let key = decode_nibbles(&path).expect("trie holds a path that is not a valid key");
+            let first_row: Vec<usize> = (0..=key.len()).collect();
+            within_distance(&self.root, &key, max_distance, &mut Vec::new(), &first_row, &mut |other, distance, _| {
+                // Keys come back in path order, which matches byte order, so this keeps each pair once.
+                if other > key.as_slice() {
+                    pairs.push((decode::<K, T>(&path), decode::<K, T>(&nibbles(other)), distance));
+                }
+            });
+        }
+        pairs
+    }
+
+    // Stored keys within `max_distance` byte edits of `key`, with their distances, in key order:
+    // the lookup behind spelling suggestions.
+    #[must_use]
+    pub fn search_within_distance(&self, key: &K, max_distance: usize) -> Vec<(K::Owned, &T, usize)> {
+        let query = key.key_bytes();
+        let first_row: Vec<usize> = (0..=query.len()).collect();
+        let mut matches = Vec::new();
+        within_distance(&self.root, query, max_distance, &mut Vec::new(), &first_row, &mut |bytes, distance, val| {
+            matches.push((decode::<K, T>(&nibbles(bytes)), val, distance));
+        });
+        matches
+    }
+
+    // Keys of exactly `template.len()` bytes whose byte at each position is one of the allowed
+    // bytes for that position, e.g. keypad digits `2 = b"abc"`, in key order. Only branches
+    // consistent with the template so far are visited.
+    #[must_use]
+    pub fn match_template(&self, template: &[&[u8]]) -> Vec<(K::Owned, &T)> {
+        let allowed: Vec<Vec<u8>> = template
+            .iter()
+            .map(|bytes| {
+                let mut bytes = bytes.to_vec();
+                bytes.sort_unstable();
+                bytes.dedup();
+                bytes
+            })
+            .collect();
+        let mut matches = Vec::new();
+        let mut path = Vec::with_capacity(2 * template.len());
+        match_positions(&self.root, &allowed, &mut path, &mut |path, val| {
+            matches.push((decode::<K, T>(path), val));
+        });
+        matches
+    }
+}
+
+fn match_positions<'a, T>(
+    node: &'a TrieNode<T, 16>,
+    allowed: &[Vec<u8>],
+    path: &mut Vec<usize>,
+    found: &mut impl FnMut(&[usize], &'a T),
+) {
+    let Some((bytes, rest)) = allowed.split_first() else {
+        if let Some(val) = node.value() {
+            found(path, val);
+        }
+        return;
+    };
+    for &byte in bytes {
+        let (high, low) = (usize::from(byte >> 4), usize::from(byte & 0x0F));
+        if let Some(child) = node.child(high).and_then(|high_node| high_node.child(low)) {
+            path.extend([high, low]);
+            match_positions(child, rest, path, found);
+            path.truncate(path.len() - 2);
+        }
+    }
+}
+
+fn decode<K: ?Sized + ToOwned, T>(path: &[usize]) -> K::Owned
+where
+    Trie<K, T, 16>: TriePathDecoder<K>,
+{
+    <Trie<K, T, 16> as TriePathDecoder<K>>::decode_path(path).expect("trie holds a path that is not a valid key")
+}
+
+fn nibbles(bytes: &[u8]) -> Vec<usize> {
+    bytes.iter().flat_map(|&byte| [usize::from(byte >> 4), usize::from(byte & 0x0F)]).collect()
+}
+
+// Walks the byte-aligned nodes below `node`, keeping the edit-distance row of `query` against the
+// bytes so far, and reports every value whose key is within `max` edits.
+pub(crate) fn within_distance<'a, T>(
+    node: &'a TrieNode<T, 16>,
+    query: &[u8],
+    max: usize,
+    prefix: &mut Vec<u8>,
+    row: &[usize],
+    found: &mut impl FnMut(&[u8], usize, &'a T),
+) {
+    if let Some(val) = node.value() {
+        if row[query.len()] <= max {
+            found(prefix, row[query.len()], val);
+        }
+    }
+    if row.iter().min().is_some_and(|&min| min > max) {
+        return;
+    }
+    for high in node.child_indices() {
+        let high_node = node.child(high).unwrap();
+        for low in high_node.child_indices() {
+            let byte = (high << 4 | low) as u8;
+            let mut next = Vec::with_capacity(row.len());
+            next.push(row[0] + 1);
+            for j in 1..row.len() {
+                let substitution = row[j - 1] + usize::from(query[j - 1] != byte);
+                next.push(substitution.min(row[j] + 1).min(next[j - 1] + 1));
+            }
+            prefix.push(byte);
+            within_distance(high_node.child(low).unwrap(), query, max, prefix, &next, found);
+            prefix.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trie::Trie;
+
+    #[test]
+    fn near_duplicates() {
+        let mut trie: Trie<str, (), 16> = Trie::new();
+        for key in ["color", "colour", "colors", "cooler", "banana"] {
+            trie.insert(key, ());
+        }
+        let pairs = trie.find_near_duplicates(1);
+        assert_eq!(
+            pairs,
+            vec![("color".to_string(), "colors".to_string(), 1), ("color".to_string(), "colour".to_string(), 1)]
+        );
+        assert_eq!(trie.find_near_duplicates(2).len(), 4);
+    }
+
+    #[test]
+    fn search_within_distance() {
+        let mut trie: Trie<str, usize, 16> = Trie::new();
+        for (i, word) in ["spell", "spelt", "smell", "spill", "spells", "shell"].iter().enumerate() {
+            trie.insert(word, i);
+        }
+        let found: Vec<(String, usize)> = trie.search_within_distance("spel", 1).into_iter().map(|(key, _, distance)| (key, distance)).collect();
+        assert_eq!(found, vec![("spell".to_string(), 1), ("spelt".to_string(), 1)]);
+        assert_eq!(trie.search_within_distance("spell", 0).len(), 1);
+        assert_eq!(trie.search_within_distance("spell", 2).len(), 6);
+    }
+
+    #[test]
+    fn template() {
+        let mut trie: Trie<str, usize, 16> = Trie::new();
+        for (i, word) in ["home", "good", "gone", "hood", "hold", "goods"].iter().enumerate() {
+            trie.insert(word, i);
+        }
+        let keys: Vec<String> = trie
+            .match_template(&[b"ghi", b"mno", b"onm", b"def"])
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        assert_eq!(keys, vec!["gone", "good", "home", "hood"]);
+        assert!(trie.match_template(&[b"x"]).is_empty());
+    }
+}
@@ -0,0 +1,104 @@
+use crate::bloom::BloomFilter;
+use crate::trie::{Trie, TriePathBuilder};
+
+/// A `Trie` wrapper that prepends a short hash of each key's path, spreading keys that share a
+/// long common prefix across the root fanout.
+///
+/// Keys stay sorted within a shard (a hash prefix) but not globally, so iteration runs shard by
+/// shard. The shared prefix itself is still stored once per shard.
+pub struct HashPrefixTrie<K: ?Sized, T, const N: usize> {
+    trie: Trie<K, T, N>,
+    hash_len: usize,
+}
+
+impl<K: ?Sized, T, const N: usize> HashPrefixTrie<K, T, N>
+where
+    Trie<K, T, N>: TriePathBuilder<K>,
+{
+    // `hash_len` path indices of hash give `N.pow(hash_len)` shards.
+    #[must_use]
+    pub fn new(hash_len: usize) -> HashPrefixTrie<K, T, N> {
+        HashPrefixTrie {
+            trie: Trie::new(),
+            hash_len,
+        }
+    }
+
+    #[must_use]
+    pub fn get(&self, key: &K) -> Option<&T> {
+        self.trie.get_path(&self.hashed_path(key))
+    }
+
+    pub fn insert(&mut self, key: &K, val: T) -> Option<T> {
+        let path = self.hashed_path(key);
+        self.trie.insert_path(&path, val)
+    }
+
+    #[deprecated(note = "renamed to `remove`")]
+    pub fn delete(&mut self, key: &K) -> Option<T> {
+        self.remove(key)
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<T> {
+        let path = self.hashed_path(key);
+        self.trie.delete_path(&path)
+    }
+
+    // The hash indices the key is stored under.
+    #[must_use]
+    pub fn shard(&self, key: &K) -> Vec<usize> {
+        let mut path = self.hashed_path(key);
+        path.truncate(self.hash_len);
+        path
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.trie.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.trie.is_empty()
+    }
+
+    // Yields the unhashed key paths, sorted within each shard.
+    pub fn iter(&self) -> impl Iterator<Item = (Vec<usize>, &T)> + '_ {
+        self.trie.iter_paths().map(|(path, val)| (path[self.hash_len..].to_vec(), val))
+    }
+
+    fn hashed_path(&self, key: &K) -> Vec<usize> {
+        let key_path = <Trie<K, T, N> as TriePathBuilder<K>>::build_path(key);
+        let (mut hash, _) = BloomFilter::hash_path(&key_path);
+        let mut path = Vec::with_capacity(self.hash_len + key_path.len());
+        for _ in 0..self.hash_len {
+            path.push((hash % N as u64) as usize);
+            hash /= N as u64;
+        }
+        path.extend(key_path);
+        path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::hashed::HashPrefixTrie;
+
+    #[test]
+    fn spreads_shared_prefix() {
+        let mut trie: HashPrefixTrie<str, usize, 16> = HashPrefixTrie::new(2);
+        for i in 0..64 {
+            trie.insert(&format!("tenant-0001/{i}"), i);
+        }
+        assert_eq!(trie.len(), 64);
+        assert_eq!(trie.get("tenant-0001/42"), Some(&42));
+        assert_eq!(trie.remove("tenant-0001/42"), Some(42));
+        assert_eq!(trie.get("tenant-0001/42"), None);
+        assert_eq!(trie.shard("tenant-0001/7").len(), 2);
+
+        let shards: std::collections::HashSet<Vec<usize>> =
+            (0..64).map(|i| trie.shard(&format!("tenant-0001/{i}"))).collect();
+        assert!(shards.len() > 16);
+        assert_eq!(trie.iter().count(), 63);
+    }
+}
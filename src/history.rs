@@ -0,0 +1,246 @@
+use std::borrow::Borrow;
+use std::collections::VecDeque;
+
+use crate::trie::{Trie, TriePathBuilder};
+#[cfg(feature = "zeroize")]
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+enum Op<O, T> {
+    Insert(O, T),
+    Delete(O),
+}
+
+/// A `Trie` wrapper that records the inverse of every mutation so it can be undone and redone.
+pub struct HistoryTrie<K: ?Sized + ToOwned, T, const N: usize> {
+    trie: Trie<K, T, N>,
+    depth: usize,
+    undo: VecDeque<Op<K::Owned, T>>,
+    redo: Vec<Op<K::Owned, T>>,
+}
+
+impl<K: ?Sized + ToOwned, T, const N: usize> HistoryTrie<K, T, N>
+where
+    Trie<K, T, N>: TriePathBuilder<K>,
+{
+    #[must_use]
+    pub fn new(depth: usize) -> HistoryTrie<K, T, N> {
+        Self::from_trie(Trie::new(), depth)
+    }
+
+    #[must_use]
+    pub fn from_trie(trie: Trie<K, T, N>, depth: usize) -> HistoryTrie<K, T, N> {
+        HistoryTrie {
+            trie,
+            depth,
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn get(&self, key: &K) -> Option<&T> {
+        self.trie.get(key)
+    }
+
+    pub fn insert(&mut self, key: &K, val: T) -> Option<T>
+    where
+        T: Clone,
+    {
+        let retval = self.trie.insert(key, val);
+        let inverse = match &retval {
+            Some(old) => Op::Insert(key.to_owned(), old.clone()),
+            None => Op::Delete(key.to_owned()),
+        };
+        self.record(inverse);
+        retval
+    }
+
+    #[deprecated(note = "renamed to `remove`")]
+    pub fn delete(&mut self, key: &K) -> Option<T>
+    where
+        T: Clone,
+    {
+        self.remove(key)
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<T>
+    where
+        T: Clone,
+    {
+        let retval = self.trie.remove(key)?;
+        self.record(Op::Insert(key.to_owned(), retval.clone()));
+        Some(retval)
+    }
+
+    pub fn undo(&mut self) -> bool {
+        if let Some(op) = self.undo.pop_back() {
+            let inverse = Self::apply(&mut self.trie, op);
+            self.redo.push(inverse);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn redo(&mut self) -> bool {
+        if let Some(op) = self.redo.pop() {
+            let inverse = Self::apply(&mut self.trie, op);
+            self.push_undo(inverse);
+            true
+        } else {
+            false
+        }
+    }
+
+    #[must_use]
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    #[must_use]
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub fn clear_history(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.trie.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.trie.is_empty()
+    }
+
+    #[must_use]
+    pub fn trie(&self) -> &Trie<K, T, N> {
+        &self.trie
+    }
+
+    #[must_use]
+    pub fn into_inner(self) -> Trie<K, T, N> {
+        self.trie
+    }
+
+    // Steps dropped here, from the cleared redo stack or evicted past `depth`, wipe their values
+    // when those are `Zeroizing`.
+    fn record(&mut self, inverse: Op<K::Owned, T>) {
+        self.redo.clear();
+        self.push_undo(inverse);
+    }
+
+    fn push_undo(&mut self, inverse: Op<K::Owned, T>) {
+        if self.depth == 0 {
+            return;
+        }
+        if self.undo.len() == self.depth {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(inverse);
+    }
+
+    fn apply(trie: &mut Trie<K, T, N>, op: Op<K::Owned, T>) -> Op<K::Owned, T> {
+        match op {
+            Op::Insert(key, val) => match trie.insert(key.borrow(), val) {
+                Some(old) => Op::Insert(key, old),
+                None => Op::Delete(key),
+            },
+            Op::Delete(key) => {
+                let val = trie.remove(key.borrow()).unwrap();
+                Op::Insert(key, val)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<K: ?Sized + ToOwned, T: Zeroize, const N: usize> ZeroizeOnDrop for HistoryTrie<K, Zeroizing<T>, N> {}
+
+// Undo and redo steps hold copies of overwritten and deleted values, so they are wiped too.
+#[cfg(feature = "zeroize")]
+impl<K: ?Sized + ToOwned, T: Zeroize, const N: usize> Zeroize for HistoryTrie<K, T, N> {
+    fn zeroize(&mut self) {
+        self.trie.zeroize();
+        for op in self.undo.iter_mut().chain(self.redo.iter_mut()) {
+            if let Op::Insert(_, val) = op {
+                val.zeroize();
+            }
+        }
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::history::HistoryTrie;
+
+    #[test]
+    fn undo_redo() {
+        let mut trie: HistoryTrie<str, usize, 16> = HistoryTrie::new(2);
+
+        assert_eq!(trie.insert("a", 1), None);
+        assert_eq!(trie.insert("a", 2), Some(1));
+        assert_eq!(trie.remove("a"), Some(2));
+        assert_eq!(trie.get("a"), None);
+
+        assert!(trie.undo());
+        assert_eq!(trie.get("a"), Some(&2));
+        assert!(trie.undo());
+        assert_eq!(trie.get("a"), Some(&1));
+        assert!(!trie.undo());
+        assert_eq!(trie.len(), 1);
+
+        assert!(trie.redo());
+        assert_eq!(trie.get("a"), Some(&2));
+        assert!(trie.redo());
+        assert_eq!(trie.get("a"), None);
+        assert!(!trie.redo());
+        assert!(trie.is_empty());
+
+        assert!(trie.undo());
+        assert_eq!(trie.insert("b", 3), None);
+        assert!(!trie.can_redo());
+        assert!(trie.undo());
+        assert_eq!(trie.get("b"), None);
+        assert_eq!(trie.get("a"), Some(&2));
+    }
+
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn dropped_steps_wipe_secrets() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        use zeroize::{Zeroize, Zeroizing};
+
+        #[derive(Clone)]
+        struct Probe(Arc<AtomicUsize>);
+
+        impl Zeroize for Probe {
+            fn zeroize(&mut self) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let wiped = Arc::new(AtomicUsize::new(0));
+        let secret = || Zeroizing::new(Probe(Arc::clone(&wiped)));
+        let mut trie: HistoryTrie<str, Zeroizing<Probe>, 16> = HistoryTrie::new(1);
+        trie.insert("a", secret());
+        drop(trie.insert("a", secret()));
+        assert_eq!(wiped.load(Ordering::SeqCst), 1);
+        // Evicts the step holding the first value of "a".
+        trie.insert("b", secret());
+        assert_eq!(wiped.load(Ordering::SeqCst), 2);
+        // The undone insert of "b" waits on the redo stack until the next change clears it.
+        assert!(trie.undo());
+        trie.insert("c", secret());
+        assert_eq!(wiped.load(Ordering::SeqCst), 3);
+        drop(trie);
+        assert_eq!(wiped.load(Ordering::SeqCst), 5);
+    }
+}
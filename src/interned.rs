@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Arc;
+
+use crate::trie::{Trie, TriePathBuilder};
+
+/// A `Trie` wrapper that stores each distinct value once and shares it between all keys mapping to it.
+pub struct InternedTrie<K: ?Sized, T, const N: usize> {
+    trie: Trie<K, Arc<T>, N>,
+    // Each pooled value with the number of keys mapping to it. Counted explicitly rather than
+    // read off the `Arc`, since `get_shared` hands out references the pool cannot see.
+    pool: HashMap<Arc<T>, usize>,
+}
+
+impl<K: ?Sized, T: Eq + Hash, const N: usize> InternedTrie<K, T, N>
+where
+    Trie<K, Arc<T>, N>: TriePathBuilder<K>,
+{
+    #[must_use]
+    pub fn new() -> InternedTrie<K, T, N> {
+        InternedTrie {
+            trie: Trie::new(),
+            pool: HashMap::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn get(&self, key: &K) -> Option<&T> {
+        self.trie.get(key).map(Arc::as_ref)
+    }
+
+    #[must_use]
+    pub fn get_shared(&self, key: &K) -> Option<Arc<T>> {
+        self.trie.get_shared(key)
+    }
+
+    pub fn insert(&mut self, key: &K, val: T) -> Option<Arc<T>> {
+        let shared = if let Some((existing, keys)) = self.pool.get_key_value(&val) {
+            let existing = Arc::clone(existing);
+            *self.pool.get_mut(existing.as_ref()).unwrap() = keys + 1;
+            existing
+        } else {
+            let shared = Arc::new(val);
+            self.pool.insert(Arc::clone(&shared), 1);
+            shared
+        };
+        let retval = self.trie.insert(key, shared)?;
+        self.release(&retval);
+        Some(retval)
+    }
+
+    #[deprecated(note = "renamed to `remove`")]
+    pub fn delete(&mut self, key: &K) -> Option<Arc<T>> {
+        self.remove(key)
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<Arc<T>> {
+        let retval = self.trie.remove(key)?;
+        self.release(&retval);
+        Some(retval)
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.trie.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.trie.is_empty()
+    }
+
+    #[must_use]
+    pub fn distinct_values(&self) -> usize {
+        self.pool.len()
+    }
+
+    // Drops one key's use of `val`, removing it from the pool once no key maps to it.
+    fn release(&mut self, val: &Arc<T>) {
+        let keys = self.pool.get_mut(val.as_ref()).expect("stored value missing from the pool");
+        *keys -= 1;
+        if *keys == 0 {
+            self.pool.remove(val.as_ref());
+        }
+    }
+}
+
+impl<K: ?Sized, T: Eq + Hash, const N: usize> Default for InternedTrie<K, T, N>
+where
+    Trie<K, Arc<T>, N>: TriePathBuilder<K>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interned::InternedTrie;
+
+    #[test]
+    fn shares_values() {
+        let mut trie: InternedTrie<str, String, 16> = InternedTrie::new();
+
+        assert!(trie.insert("a", "meta".to_string()).is_none());
+        assert!(trie.insert("b", "meta".to_string()).is_none());
+        assert!(trie.insert("c", "other".to_string()).is_none());
+        assert_eq!(trie.len(), 3);
+        assert_eq!(trie.distinct_values(), 2);
+        assert!(std::ptr::eq(trie.get("a").unwrap(), trie.get("b").unwrap()));
+
+        assert_eq!(trie.remove("a").as_deref(), Some(&"meta".to_string()));
+        assert_eq!(trie.distinct_values(), 2);
+        assert_eq!(trie.insert("b", "other".to_string()).as_deref(), Some(&"meta".to_string()));
+        assert_eq!(trie.distinct_values(), 1);
+        assert!(trie.remove("b").is_some());
+        assert!(trie.remove("c").is_some());
+        assert_eq!(trie.distinct_values(), 0);
+        assert!(trie.is_empty());
+
+        // Handing out a shared reference does not pin the value in the pool.
+        trie.insert("k", "kept".to_string());
+        let shared = trie.get_shared("k").unwrap();
+        trie.remove("k");
+        drop(shared);
+        assert_eq!(trie.distinct_values(), 0);
+    }
+}
@@ -0,0 +1,201 @@
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::keys::{decode_digits, digits_per_byte, push_digits};
+use crate::trie::{DecodeError, Trie, TriePathBuilder, TriePathDecoder};
+
+// Bits in front of an IPv4 address once it is mapped into IPv6 as `::ffff:a.b.c.d`.
+const V4_MAPPED_BITS: usize = 96;
+
+// Addresses as their octets in network order, so paths sort like the addresses do. `IpAddr`
+// stores IPv4 addresses mapped into IPv6, which keeps both families apart in one trie.
+macro_rules! addr_keys {
+    ($($addr:ty => $len:expr, $octets:expr, $from:expr;)*) => {$(
+        impl<T, const N: usize> TriePathBuilder<$addr> for Trie<$addr, T, N> {
+            fn populate_path(key: &$addr, path: &mut Vec<usize>) {
+                push_digits::<N>(&$octets(key), path);
+            }
+
+            fn init_path(_key: &$addr) -> Vec<usize> {
+                Vec::with_capacity(digits_per_byte::<N>() * $len)
+            }
+        }
+
+        impl<T, const N: usize> TriePathDecoder<$addr> for Trie<$addr, T, N> {
+            fn decode_path(path: &[usize]) -> Result<$addr, DecodeError> {
+                let expected = digits_per_byte::<N>() * $len;
+                if path.len() != expected {
+                    return Err(DecodeError::WrongLength { len: path.len(), expected });
+                }
+                Ok($from(decode_digits::<N>(path)?.try_into().unwrap()))
+            }
+        }
+    )*};
+}
+
+addr_keys! {
+    Ipv4Addr => 4, Ipv4Addr::octets, |octets: [u8; 4]| Ipv4Addr::from(octets);
+    Ipv6Addr => 16, Ipv6Addr::octets, |octets: [u8; 16]| Ipv6Addr::from(octets);
+    IpAddr => 16, mapped_octets, |octets: [u8; 16]| unmap(Ipv6Addr::from(octets));
+}
+
+fn mapped_octets(addr: &IpAddr) -> [u8; 16] {
+    match addr {
+        IpAddr::V4(addr) => addr.to_ipv6_mapped().octets(),
+        IpAddr::V6(addr) => addr.octets(),
+    }
+}
+
+fn unmap(addr: Ipv6Addr) -> IpAddr {
+    addr.to_ipv4_mapped().map_or(IpAddr::V6(addr), IpAddr::V4)
+}
+
+/// A network prefix such as `10.0.0.0/8`, stored as only its first `prefix_len` bits in a
+/// `Trie<CidrKey, T, 2>`, so the networks covering an address lie on its path and
+/// `Trie::longest_match` finds the most specific one. IPv4 networks sit under the IPv4-mapped
+/// IPv6 range, as with `IpAddr` keys.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CidrKey {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+impl CidrKey {
+    // `None` if `prefix_len` is longer than the address. Host bits past the prefix are cleared.
+    #[must_use]
+    pub fn new(addr: IpAddr, prefix_len: u8) -> Option<CidrKey> {
+        let bits = match addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        if prefix_len > bits {
+            return None;
+        }
+        let host_bits = u32::from(bits - prefix_len);
+        let addr = match addr {
+            IpAddr::V4(addr) => IpAddr::V4(Ipv4Addr::from(u32::from(addr) & u32::MAX.checked_shl(host_bits).unwrap_or(0))),
+            IpAddr::V6(addr) => IpAddr::V6(Ipv6Addr::from(u128::from(addr) & u128::MAX.checked_shl(host_bits).unwrap_or(0))),
+        };
+        Some(CidrKey { addr, prefix_len })
+    }
+
+    // The whole address, as a host route.
+    #[must_use]
+    pub fn host(addr: IpAddr) -> CidrKey {
+        let prefix_len = if addr.is_ipv4() { 32 } else { 128 };
+        CidrKey { addr, prefix_len }
+    }
+
+    #[must_use]
+    pub fn addr(&self) -> IpAddr {
+        self.addr
+    }
+
+    #[must_use]
+    pub fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+
+    // Bits of the key in a path; IPv4 networks count the mapped prefix in front.
+    fn path_len(&self) -> usize {
+        usize::from(self.prefix_len) + if self.addr.is_ipv4() { V4_MAPPED_BITS } else { 0 }
+    }
+}
+
+impl fmt::Display for CidrKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix_len)
+    }
+}
+
+impl<T> TriePathBuilder<CidrKey> for Trie<CidrKey, T, 2> {
+    fn populate_path(key: &CidrKey, path: &mut Vec<usize>) {
+        let start = path.len();
+        push_digits::<2>(&mapped_octets(&key.addr), path);
+        path.truncate(start + key.path_len());
+    }
+
+    fn init_path(_key: &CidrKey) -> Vec<usize> {
+        Vec::with_capacity(128)
+    }
+}
+
+// Paths of at least 96 bits under `::ffff:0:0/96` decode as IPv4 networks, so that IPv6 prefix
+// itself comes back as `0.0.0.0/0`.
+impl<T> TriePathDecoder<CidrKey> for Trie<CidrKey, T, 2> {
+    fn decode_path(path: &[usize]) -> Result<CidrKey, DecodeError> {
+        if path.len() > 128 {
+            return Err(DecodeError::WrongLength { len: path.len(), expected: 128 });
+        }
+        let mut bits = path.to_vec();
+        bits.resize(128, 0);
+        let addr = unmap(Ipv6Addr::from(<[u8; 16]>::try_from(decode_digits::<2>(&bits)?).unwrap()));
+        // Shorter paths leave zeros where the mapped prefix has ones, so they stay IPv6.
+        let prefix_len = match addr {
+            IpAddr::V4(_) => path.len() - V4_MAPPED_BITS,
+            IpAddr::V6(_) => path.len(),
+        };
+        Ok(CidrKey { addr, prefix_len: prefix_len as u8 })
+    }
+}
+
+impl<T> Trie<CidrKey, T, 2> {
+    // The most specific stored network containing `addr`, as a router picks a route. IPv4
+    // addresses are also covered by IPv6 networks spanning the mapped range, such as `::/0`.
+    #[must_use]
+    pub fn longest_match(&self, addr: IpAddr) -> Option<(CidrKey, &T)> {
+        let path = Self::build_path(&CidrKey::host(addr));
+        let (len, val) = self.longest_prefix_path(&path)?;
+        let network = Self::decode_path(&path[..len]).expect("prefixes of an address path decode");
+        Some((network, val))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+    use crate::ip::CidrKey;
+    use crate::trie::Trie;
+
+    fn net(addr: &str, prefix_len: u8) -> CidrKey {
+        CidrKey::new(addr.parse().unwrap(), prefix_len).unwrap()
+    }
+
+    #[test]
+    fn routing_table() {
+        let mut routes: Trie<CidrKey, &str, 2> = Trie::new();
+        routes.insert(&net("0.0.0.0", 0), "default");
+        routes.insert(&net("10.0.0.0", 8), "corp");
+        routes.insert(&net("10.1.2.3", 16), "lab");
+        routes.insert(&net("2001:db8::", 32), "docs");
+
+        let lookup = |addr: &str| routes.longest_match(addr.parse().unwrap()).map(|(net, val)| (net.to_string(), *val));
+        assert_eq!(lookup("10.1.200.7"), Some(("10.1.0.0/16".to_string(), "lab")));
+        assert_eq!(lookup("10.2.0.1"), Some(("10.0.0.0/8".to_string(), "corp")));
+        assert_eq!(lookup("192.168.0.1"), Some(("0.0.0.0/0".to_string(), "default")));
+        assert_eq!(lookup("2001:db8:1::1"), Some(("2001:db8::/32".to_string(), "docs")));
+        assert_eq!(lookup("fe80::1"), None);
+
+        let networks: Vec<String> = routes.keys().map(|net| net.to_string()).collect();
+        assert_eq!(networks, vec!["0.0.0.0/0", "10.0.0.0/8", "10.1.0.0/16", "2001:db8::/32"]);
+        assert_eq!(CidrKey::new("10.0.0.0".parse().unwrap(), 33), None);
+    }
+
+    #[test]
+    fn address_keys() {
+        let mut hosts: Trie<IpAddr, usize, 16> = Trie::new();
+        for (i, addr) in ["10.0.0.2", "::1", "9.255.255.255"].iter().enumerate() {
+            hosts.insert(&addr.parse().unwrap(), i);
+        }
+        let addrs: Vec<String> = hosts.keys().map(|addr| addr.to_string()).collect();
+        assert_eq!(addrs, vec!["::1", "9.255.255.255", "10.0.0.2"]);
+
+        let mut v4: Trie<Ipv4Addr, (), 256> = Trie::new();
+        v4.insert(&Ipv4Addr::new(192, 168, 0, 1), ());
+        assert_eq!(v4.keys().collect::<Vec<_>>(), vec![Ipv4Addr::new(192, 168, 0, 1)]);
+        let mut v6: Trie<Ipv6Addr, (), 2> = Trie::new();
+        v6.insert(&Ipv6Addr::LOCALHOST, ());
+        assert_eq!(v6.iter_paths().next().unwrap().0.len(), 128);
+    }
+}
@@ -0,0 +1,148 @@
+use crate::trie::{DecodeError, Trie, TriePathBuilder, TriePathDecoder};
+
+/// Bytes stored one bit per level, most significant bit first, in a `Trie<Bits<B>, T, 2>`.
+/// Each node then branches only where keys differ in a bit, as in a crit-bit tree, which suits
+/// fixed-width keys such as integers and addresses. Any `B: AsRef<[u8]>` works for lookups;
+/// decoded keys come back as `Bits<Vec<u8>>`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Bits<B>(pub B);
+
+impl<B: AsRef<[u8]>> Bits<B> {
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        self.0.as_ref()
+    }
+}
+
+impl<B: AsRef<[u8]>, T> TriePathBuilder<Bits<B>> for Trie<Bits<B>, T, 2> {
+    fn populate_path(key: &Bits<B>, path: &mut Vec<usize>) {
+        push_digits::<2>(key.as_bytes(), path);
+    }
+
+    fn init_path(key: &Bits<B>) -> Vec<usize> {
+        Vec::with_capacity(8 * key.as_bytes().len())
+    }
+}
+
+impl<T> TriePathDecoder<Bits<Vec<u8>>> for Trie<Bits<Vec<u8>>, T, 2> {
+    fn decode_path(path: &[usize]) -> Result<Bits<Vec<u8>>, DecodeError> {
+        decode_digits::<2>(path).map(Bits)
+    }
+}
+
+// Integers as their big-endian bytes, so paths sort in numeric order. Signed integers get their
+// sign bit flipped first, which puts negative numbers below the others.
+macro_rules! int_keys {
+    ($($int:ty => $unsigned:ty, $flip:expr;)*) => {$(
+        impl<T, const N: usize> TriePathBuilder<$int> for Trie<$int, T, N> {
+            fn populate_path(key: &$int, path: &mut Vec<usize>) {
+                push_digits::<N>(&((*key as $unsigned) ^ $flip).to_be_bytes(), path);
+            }
+
+            fn init_path(_key: &$int) -> Vec<usize> {
+                Vec::with_capacity(digits_per_byte::<N>() * std::mem::size_of::<$int>())
+            }
+        }
+
+        impl<T, const N: usize> TriePathDecoder<$int> for Trie<$int, T, N> {
+            fn decode_path(path: &[usize]) -> Result<$int, DecodeError> {
+                let expected = digits_per_byte::<N>() * std::mem::size_of::<$int>();
+                if path.len() != expected {
+                    return Err(DecodeError::WrongLength { len: path.len(), expected });
+                }
+                let bytes = decode_digits::<N>(path)?;
+                Ok((<$unsigned>::from_be_bytes(bytes.try_into().unwrap()) ^ $flip) as $int)
+            }
+        }
+    )*};
+}
+
+int_keys! {
+    u8 => u8, 0;
+    u16 => u16, 0;
+    u32 => u32, 0;
+    u64 => u64, 0;
+    u128 => u128, 0;
+    usize => usize, 0;
+    i8 => u8, 1 << 7;
+    i16 => u16, 1 << 15;
+    i32 => u32, 1 << 31;
+    i64 => u64, 1 << 63;
+    i128 => u128, 1 << 127;
+    isize => usize, 1 << (usize::BITS - 1);
+}
+
+pub(crate) const fn digits_per_byte<const N: usize>() -> usize {
+    assert!(N == 2 || N == 4 || N == 16 || N == 256, "keys split into digits need an arity of 2, 4, 16 or 256");
+    (8 / N.trailing_zeros()) as usize
+}
+
+// Appends the `N`-ary digits of `bytes`, most significant first. `N` divides a byte evenly:
+// 2, 4, 16 or 256.
+pub(crate) fn push_digits<const N: usize>(bytes: &[u8], path: &mut Vec<usize>) {
+    const { digits_per_byte::<N>() };
+    let bits = N.trailing_zeros();
+    for &byte in bytes {
+        for shift in (0..8 / bits).rev() {
+            path.push(usize::from(byte) >> (shift * bits) & (N - 1));
+        }
+    }
+}
+
+// The bytes whose `N`-ary digits make up `path`, reversing `push_digits`.
+pub(crate) fn decode_digits<const N: usize>(path: &[usize]) -> Result<Vec<u8>, DecodeError> {
+    let per_byte = const { digits_per_byte::<N>() };
+    if path.len() % per_byte != 0 {
+        return Err(DecodeError::Truncated { len: path.len() });
+    }
+    if let Some((position, &index)) = path.iter().enumerate().find(|(_, &index)| index >= N) {
+        return Err(DecodeError::IndexOutOfRange { position, index });
+    }
+    Ok(path
+        .chunks(per_byte)
+        .map(|digits| digits.iter().fold(0, |byte, &digit| byte * N + digit) as u8)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::keys::Bits;
+    use crate::trie::{DecodeError, Trie, TriePathBuilder, TriePathDecoder};
+
+    #[test]
+    fn bitwise_keys() {
+        type BitTrie = Trie<Bits<Vec<u8>>, &'static str, 2>;
+        let mut trie: BitTrie = Trie::new();
+        trie.insert(&Bits(vec![0b1010_0000]), "a0");
+        trie.insert(&Bits(vec![0b1010_0001]), "a1");
+        trie.insert(&Bits(vec![0x0f, 0xff]), "0fff");
+        assert_eq!(BitTrie::build_path(&Bits(vec![0b1010_0001])), vec![1, 0, 1, 0, 0, 0, 0, 1]);
+        assert_eq!(trie.get(&Bits(vec![0b1010_0001])), Some(&"a1"));
+        assert_eq!(trie.get(&Bits(vec![0x0f])), None);
+
+        let keys: Vec<Vec<u8>> = trie.iter().map(|(key, _)| key.0).collect();
+        assert_eq!(keys, vec![vec![0x0f, 0xff], vec![0b1010_0000], vec![0b1010_0001]]);
+        assert_eq!(BitTrie::decode_path(&[1, 0, 1]), Err(DecodeError::Truncated { len: 3 }));
+    }
+
+    #[test]
+    fn integer_keys() {
+        let mut signed: Trie<i32, (), 16> = Trie::new();
+        for key in [7, -1, i32::MIN, 0, 300, i32::MAX, -300] {
+            signed.insert(&key, ());
+        }
+        let keys: Vec<i32> = signed.keys().collect();
+        assert_eq!(keys, vec![i32::MIN, -300, -1, 0, 7, 300, i32::MAX]);
+        let mut wide: Trie<u64, (), 256> = Trie::new();
+        for key in [1 << 40, 256, u64::MAX, 255] {
+            wide.insert(&key, ());
+        }
+        assert_eq!(wide.keys().collect::<Vec<_>>(), vec![255, 256, 1 << 40, u64::MAX]);
+        assert_eq!(Trie::<i32, (), 16>::build_path(&-1), vec![7, 15, 15, 15, 15, 15, 15, 15]);
+
+        let mut bits: Trie<u8, char, 2> = Trie::new();
+        bits.insert(&0b1000_0001, 'a');
+        assert_eq!(bits.get(&129), Some(&'a'));
+        assert_eq!(Trie::<u8, char, 2>::decode_path(&[1, 0]), Err(DecodeError::WrongLength { len: 2, expected: 8 }));
+    }
+}
@@ -2,5 +2,65 @@
 #![allow(clippy::missing_panics_doc)]
 
 mod trie_node;
+pub mod bloom;
 pub mod trie;
+pub mod history;
+pub mod interned;
+pub mod frozen;
+pub mod static_trie;
+pub mod tiered;
+pub mod codec;
+pub mod disk;
+pub mod analysis;
+pub mod lru;
+pub mod epoch;
+pub mod display;
+pub mod hashed;
+pub mod loading;
+pub mod tokenize;
+pub mod fuzzy;
+pub mod fork;
+pub mod verify;
+pub mod snapshot;
+pub mod reader;
+pub mod batch;
+pub mod arity;
+pub mod nested;
+pub mod shrink;
+pub mod retune;
+pub mod budget;
+pub mod rewrite;
+pub mod compressed;
+pub mod keys;
+pub mod ip;
+pub mod subtrie;
+pub mod suggest;
+#[cfg(feature = "rayon")]
+pub mod parallel;
+#[cfg(feature = "serde")]
+mod serde_impls;
 
+// Compile-time checks that the trie and the types handed out with it are `Send + Sync` whenever
+// the values are, so a change that loses either fails the build rather than a downstream user.
+const _: () = {
+    fn assert_send_sync<S: Send + Sync>() {}
+
+    fn check<'a, T: Send + Sync + 'a>() {
+        assert_send_sync::<trie::Trie<str, T, 16>>();
+        assert_send_sync::<trie::Trie<[u8], T, 16>>();
+        assert_send_sync::<trie::TrieIter<'a, T, 16>>();
+        assert_send_sync::<trie::Iter<'a, str, T, 16>>();
+        assert_send_sync::<trie::IterMut<'a, str, T, 16>>();
+        assert_send_sync::<trie::OwnedIter<String, T>>();
+        assert_send_sync::<trie::IntoValues<T>>();
+        assert_send_sync::<trie::Position>();
+        assert_send_sync::<trie::RemovalReport>();
+        assert_send_sync::<reader::TrieReader<str, T, 16>>();
+        assert_send_sync::<fork::Fork<'a, str, T, 16>>();
+        assert_send_sync::<subtrie::SubTrie<'a, str, T, 16>>();
+        assert_send_sync::<frozen::FrozenTrie<'a, str, T, 16>>();
+        assert_send_sync::<batch::WriteBatch<str, T, 16>>();
+        assert_send_sync::<snapshot::SnapshotStats>();
+        assert_send_sync::<bloom::BloomFilter>();
+    }
+};
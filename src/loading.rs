@@ -0,0 +1,143 @@
+use crate::trie::{Trie, TriePathBuilder};
+
+type Loader<K, T> = Box<dyn FnMut(&K) -> Option<T>>;
+
+/// A read-through cache: missing keys under a registered prefix are produced by that prefix's
+/// loader and kept in the trie.
+pub struct LoadingTrie<K: ?Sized, T, const N: usize> {
+    // `None` marks a key its loader could not produce, when negative caching is on.
+    trie: Trie<K, Option<T>, N>,
+    // Each prefix maps to an index into `loaders`; the longest registered prefix wins.
+    prefixes: Trie<K, usize, N>,
+    loaders: Vec<Loader<K, T>>,
+    negative_cache: bool,
+    misses: usize,
+}
+
+impl<K: ?Sized, T, const N: usize> LoadingTrie<K, T, N>
+where
+    Trie<K, Option<T>, N>: TriePathBuilder<K>,
+    Trie<K, usize, N>: TriePathBuilder<K>,
+{
+    #[must_use]
+    pub fn new(negative_cache: bool) -> LoadingTrie<K, T, N> {
+        LoadingTrie {
+            trie: Trie::new(),
+            prefixes: Trie::new(),
+            loaders: Vec::new(),
+            negative_cache,
+            misses: 0,
+        }
+    }
+
+    // Replaces any loader already registered for exactly this prefix.
+    pub fn register_loader(&mut self, prefix: &K, loader: impl FnMut(&K) -> Option<T> + 'static) {
+        if let Some(&index) = self.prefixes.get(prefix) {
+            self.loaders[index] = Box::new(loader);
+        } else {
+            self.prefixes.insert(prefix, self.loaders.len());
+            self.loaders.push(Box::new(loader));
+        }
+    }
+
+    // Looks only at what is already cached.
+    #[must_use]
+    pub fn get(&self, key: &K) -> Option<&T> {
+        self.trie.get(key)?.as_ref()
+    }
+
+    pub fn get_or_load(&mut self, key: &K) -> Option<&T> {
+        let path = <Trie<K, Option<T>, N> as TriePathBuilder<K>>::build_path(key);
+        if self.trie.get_path(&path).is_none() {
+            let prefix_path = <Trie<K, usize, N> as TriePathBuilder<K>>::build_path(key);
+            let &index = self.prefixes.longest_prefix_path(&prefix_path)?.1;
+            match (self.loaders[index])(key) {
+                Some(val) => {
+                    self.trie.insert_path(&path, Some(val));
+                }
+                None if self.negative_cache => {
+                    self.trie.insert_path(&path, None);
+                    self.misses += 1;
+                }
+                None => return None,
+            }
+        }
+        self.trie.get_path(&path)?.as_ref()
+    }
+
+    pub fn insert(&mut self, key: &K, val: T) -> Option<T> {
+        let old = self.trie.insert(key, Some(val));
+        self.settle(old)
+    }
+
+    #[deprecated(note = "renamed to `remove`")]
+    pub fn delete(&mut self, key: &K) -> Option<T> {
+        self.remove(key)
+    }
+
+    // Also forgets a cached miss, so the next `get_or_load` asks the loader again.
+    pub fn remove(&mut self, key: &K) -> Option<T> {
+        let old = self.trie.remove(key);
+        self.settle(old)
+    }
+
+    // Number of cached values, not counting cached misses.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.trie.len() - self.misses
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    #[must_use]
+    pub fn cached_misses(&self) -> usize {
+        self.misses
+    }
+
+    fn settle(&mut self, old: Option<Option<T>>) -> Option<T> {
+        match old {
+            Some(None) => {
+                self.misses -= 1;
+                None
+            }
+            Some(val) => val,
+            None => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use crate::loading::LoadingTrie;
+
+    #[test]
+    fn read_through() {
+        let calls = Rc::new(Cell::new(0));
+        let mut cache: LoadingTrie<str, String, 16> = LoadingTrie::new(true);
+        let counter = Rc::clone(&calls);
+        cache.register_loader("user/", move |key| {
+            counter.set(counter.get() + 1);
+            key.strip_prefix("user/").filter(|id| !id.is_empty()).map(str::to_uppercase)
+        });
+        cache.register_loader("user/admin/", |_| Some("ADMIN".to_string()));
+
+        assert_eq!(cache.get_or_load("user/ann").map(String::as_str), Some("ANN"));
+        assert_eq!(cache.get_or_load("user/ann").map(String::as_str), Some("ANN"));
+        assert_eq!(cache.get_or_load("user/admin/root").map(String::as_str), Some("ADMIN"));
+        assert_eq!(cache.get_or_load("user/"), None);
+        assert_eq!(cache.get_or_load("user/"), None);
+        assert_eq!(cache.get_or_load("group/x"), None);
+        assert_eq!(calls.get(), 2);
+        assert_eq!((cache.len(), cache.cached_misses()), (2, 1));
+
+        assert_eq!(cache.remove("user/"), None);
+        assert_eq!(cache.cached_misses(), 0);
+        assert_eq!(cache.get("user/ann").map(String::as_str), Some("ANN"));
+    }
+}
@@ -0,0 +1,212 @@
+use std::mem;
+
+use crate::trie::{Trie, TriePathBuilder};
+
+// Marks the end of the recency list.
+const NIL: usize = usize::MAX;
+
+/// A capacity-bounded cache that evicts the least recently used key and can drop whole prefixes.
+pub struct LruTrie<K: ?Sized, T, const N: usize> {
+    // Each value carries its slot in `recency`.
+    trie: Trie<K, (T, usize), N>,
+    recency: Recency,
+    capacity: usize,
+}
+
+impl<K: ?Sized, T, const N: usize> LruTrie<K, T, N>
+where
+    Trie<K, (T, usize), N>: TriePathBuilder<K>,
+{
+    #[must_use]
+    pub fn new(capacity: usize) -> LruTrie<K, T, N> {
+        assert!(capacity > 0, "LruTrie capacity must be positive");
+        LruTrie {
+            trie: Trie::new(),
+            recency: Recency {
+                slots: Vec::new(),
+                free: Vec::new(),
+                oldest: NIL,
+                newest: NIL,
+            },
+            capacity,
+        }
+    }
+
+    // Marks the key as most recently used.
+    pub fn get(&mut self, key: &K) -> Option<&T> {
+        let (val, slot) = self.trie.get_mut(key)?;
+        self.recency.touch(*slot);
+        Some(val)
+    }
+
+    // Looks the key up without changing its recency.
+    #[must_use]
+    pub fn peek(&self, key: &K) -> Option<&T> {
+        self.trie.get(key).map(|(val, _)| val)
+    }
+
+    pub fn insert(&mut self, key: &K, val: T) -> Option<T> {
+        let slot = self.recency.push(<Trie<K, (T, usize), N> as TriePathBuilder<K>>::build_path(key).into_boxed_slice());
+        let old = self.trie.insert_path(&self.recency.slots[slot].path, (val, slot));
+        if let Some((_, old_slot)) = &old {
+            self.recency.remove(*old_slot);
+        }
+        while self.trie.len() > self.capacity {
+            self.evict();
+        }
+        old.map(|(val, _)| val)
+    }
+
+    #[deprecated(note = "renamed to `remove`")]
+    pub fn delete(&mut self, key: &K) -> Option<T> {
+        self.remove(key)
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<T> {
+        let (val, slot) = self.trie.remove(key)?;
+        self.recency.remove(slot);
+        Some(val)
+    }
+
+    // Drops every key starting with `prefix` and returns how many were removed.
+    pub fn invalidate_prefix(&mut self, prefix: &K) -> usize {
+        let path = <Trie<K, (T, usize), N> as TriePathBuilder<K>>::build_path(prefix);
+        let recency = &mut self.recency;
+        self.trie
+            .remove_prefix_path(&path, |_, (_, slot)| {
+                recency.remove(slot);
+            })
+            .values_removed
+    }
+
+    // Removes the least recently used entry, returning its value.
+    pub fn evict(&mut self) -> Option<T> {
+        let path = self.recency.pop_oldest()?;
+        self.trie.delete_path(&path).map(|(val, _)| val)
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.trie.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.trie.is_empty()
+    }
+
+    #[must_use]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+// The entries as a doubly linked list of slots, from least to most recently used. Moving an
+// entry to the front takes its slot from the trie value, without a walk; each slot also keeps the
+// entry's path, which is how eviction finds it in the trie.
+struct Recency {
+    slots: Vec<Slot>,
+    // Slots of removed entries, reused before `slots` grows.
+    free: Vec<usize>,
+    oldest: usize,
+    newest: usize,
+}
+
+struct Slot {
+    path: Box<[usize]>,
+    prev: usize,
+    next: usize,
+}
+
+impl Recency {
+    // A slot for a new most recently used entry.
+    fn push(&mut self, path: Box<[usize]>) -> usize {
+        let slot = Slot { path, prev: NIL, next: NIL };
+        let id = match self.free.pop() {
+            Some(id) => {
+                self.slots[id] = slot;
+                id
+            }
+            None => {
+                self.slots.push(slot);
+                self.slots.len() - 1
+            }
+        };
+        self.link_newest(id);
+        id
+    }
+
+    fn touch(&mut self, id: usize) {
+        if self.newest != id {
+            self.unlink(id);
+            self.link_newest(id);
+        }
+    }
+
+    // Frees the slot, returning the path it kept.
+    fn remove(&mut self, id: usize) -> Box<[usize]> {
+        self.unlink(id);
+        self.free.push(id);
+        mem::take(&mut self.slots[id].path)
+    }
+
+    fn pop_oldest(&mut self) -> Option<Box<[usize]>> {
+        (self.oldest != NIL).then(|| self.remove(self.oldest))
+    }
+
+    fn unlink(&mut self, id: usize) {
+        let Slot { prev, next, .. } = self.slots[id];
+        if prev == NIL {
+            self.oldest = next;
+        } else {
+            self.slots[prev].next = next;
+        }
+        if next == NIL {
+            self.newest = prev;
+        } else {
+            self.slots[next].prev = prev;
+        }
+    }
+
+    fn link_newest(&mut self, id: usize) {
+        self.slots[id].prev = self.newest;
+        self.slots[id].next = NIL;
+        if self.newest == NIL {
+            self.oldest = id;
+        } else {
+            self.slots[self.newest].next = id;
+        }
+        self.newest = id;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lru::LruTrie;
+
+    #[test]
+    fn eviction_and_invalidation() {
+        let mut cache: LruTrie<str, usize, 16> = LruTrie::new(3);
+        cache.insert("/api/v1/a", 1);
+        cache.insert("/api/v1/b", 2);
+        cache.insert("/api/v2/a", 3);
+        assert_eq!(cache.get("/api/v1/a"), Some(&1));
+        cache.insert("/web", 4);
+        assert_eq!(cache.peek("/api/v1/b"), None);
+        assert_eq!(cache.len(), 3);
+
+        assert_eq!(cache.invalidate_prefix("/api/v1/"), 1);
+        assert_eq!(cache.peek("/api/v1/a"), None);
+        assert_eq!(cache.evict(), Some(3));
+        assert_eq!(cache.remove("/web"), Some(4));
+        assert!(cache.is_empty());
+        assert_eq!(cache.evict(), None);
+
+        cache.insert("a", 5);
+        cache.insert("b", 6);
+        cache.insert("c", 7);
+        assert_eq!(cache.insert("a", 8), Some(5));
+        assert_eq!(cache.get("b"), Some(&6));
+        assert_eq!((cache.evict(), cache.evict(), cache.evict(), cache.len()), (Some(7), Some(8), Some(6), 0));
+    }
+}
@@ -0,0 +1,63 @@
+use crate::trie::{Trie, TriePathBuilder};
+
+// Two-level keyed data, such as (table, row), stored as a trie whose values are tries. The outer
+// lookup lands directly on the inner trie, so no key is decoded in between.
+impl<K: ?Sized, J: ?Sized, T, const N: usize, const M: usize> Trie<K, Trie<J, T, M>, N>
+where
+    Trie<K, Trie<J, T, M>, N>: TriePathBuilder<K>,
+    Trie<J, T, M>: TriePathBuilder<J>,
+{
+    #[must_use]
+    pub fn get_nested(&self, outer: &K, inner: &J) -> Option<&T> {
+        self.get(outer)?.get(inner)
+    }
+
+    pub fn get_nested_mut(&mut self, outer: &K, inner: &J) -> Option<&mut T> {
+        self.get_mut(outer)?.get_mut(inner)
+    }
+
+    // Creates the inner trie on first use.
+    pub fn insert_nested(&mut self, outer: &K, inner: &J, val: T) -> Option<T> {
+        let path = <Trie<K, Trie<J, T, M>, N> as TriePathBuilder<K>>::build_path(outer);
+        self.upsert_path(&path, Trie::new, |inner_trie| inner_trie.insert(inner, val))
+    }
+
+    // Drops the inner trie once its last entry is gone.
+    pub fn delete_nested(&mut self, outer: &K, inner: &J) -> Option<T> {
+        let inner_trie = self.get_mut(outer)?;
+        let retval = inner_trie.remove(inner);
+        if inner_trie.is_empty() {
+            let _ = self.remove(outer);
+        }
+        retval
+    }
+
+    // Entries across all inner tries.
+    #[must_use]
+    pub fn nested_len(&self) -> usize {
+        let mut len = 0;
+        self.root.visit_paths(|_, inner_trie| len += inner_trie.len());
+        len
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trie::Trie;
+
+    #[test]
+    fn two_levels() {
+        let mut tables: Trie<str, Trie<[u8], String, 16>, 16> = Trie::new();
+        assert_eq!(tables.insert_nested("users", &[0, 1], "ann".to_string()), None);
+        assert_eq!(tables.insert_nested("users", &[0, 2], "bob".to_string()), None);
+        assert_eq!(tables.insert_nested("orders", &[7], "book".to_string()), None);
+        assert_eq!(tables.get_nested("users", &[0, 2]).map(String::as_str), Some("bob"));
+        assert_eq!(tables.get_nested("orders", &[0, 2]), None);
+        assert_eq!((tables.len(), tables.nested_len()), (2, 3));
+
+        assert_eq!(tables.delete_nested("orders", &[7]).as_deref(), Some("book"));
+        assert!(tables.get("orders").is_none());
+        tables.get_nested_mut("users", &[0, 1]).unwrap().push('!');
+        assert_eq!(tables.get_nested("users", &[0, 1]).map(String::as_str), Some("ann!"));
+    }
+}
@@ -0,0 +1,104 @@
+use std::borrow::Borrow;
+
+use rayon::iter::{FromParallelIterator, IndexedParallelIterator, IntoParallelIterator, ParallelExtend, ParallelIterator};
+
+use crate::trie::{Trie, TriePathBuilder};
+
+type Bucket<T> = Vec<(Vec<usize>, T)>;
+
+// Entries are bucketed by the first index of their path, so each bucket becomes a shard trie that
+// owns exactly one child of the root and can be built independently.
+impl<K, T, const N: usize> ParallelExtend<(K::Owned, T)> for Trie<K, T, N>
+where
+    K: ?Sized + ToOwned + Send,
+    K::Owned: Send,
+    T: Send,
+    Trie<K, T, N>: TriePathBuilder<K>,
+{
+    fn par_extend<I>(&mut self, par_iter: I)
+    where
+        I: IntoParallelIterator<Item = (K::Owned, T)>,
+    {
+        let mut buckets = par_iter
+            .into_par_iter()
+            .map(|(key, val)| (<Trie<K, T, N> as TriePathBuilder<K>>::build_path(key.borrow()), val))
+            .fold(empty_buckets::<T, N>, |mut buckets, (path, val)| {
+                let index = path.first().copied().unwrap_or(N);
+                buckets[index].push((path, val));
+                buckets
+            })
+            .reduce(empty_buckets::<T, N>, |mut left, right| {
+                for (into, from) in left.iter_mut().zip(right) {
+                    into.extend(from);
+                }
+                left
+            });
+
+        // The empty path lands on the root itself, which no shard owns.
+        for (path, val) in buckets.pop().unwrap_or_default() {
+            self.insert_path(&path, val);
+        }
+
+        let layout = self.layout;
+        let shards: Vec<(usize, Trie<K, T, N>)> = buckets
+            .into_par_iter()
+            .enumerate()
+            .filter(|(_, bucket)| !bucket.is_empty())
+            .map(|(index, bucket)| {
+                let mut shard = Trie::with_layout(layout);
+                for (path, val) in bucket {
+                    shard.insert_path(&path, val);
+                }
+                (index, shard)
+            })
+            .collect();
+        for (index, shard) in shards {
+            self.merge_shard(index, shard);
+        }
+    }
+}
+
+impl<K, T, const N: usize> FromParallelIterator<(K::Owned, T)> for Trie<K, T, N>
+where
+    K: ?Sized + ToOwned + Send,
+    K::Owned: Send,
+    T: Send,
+    Trie<K, T, N>: TriePathBuilder<K>,
+{
+    fn from_par_iter<I>(par_iter: I) -> Self
+    where
+        I: IntoParallelIterator<Item = (K::Owned, T)>,
+    {
+        let mut trie = Trie::new();
+        trie.par_extend(par_iter);
+        trie
+    }
+}
+
+fn empty_buckets<T, const N: usize>() -> Vec<Bucket<T>> {
+    (0..=N).map(|_| Vec::new()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use rayon::iter::{IntoParallelIterator, ParallelExtend, ParallelIterator};
+
+    use crate::trie::Trie;
+
+    #[test]
+    fn par_extend() {
+        let mut trie: Trie<str, usize, 16> = Trie::new();
+        trie.insert("7", 0);
+        trie.insert("", 1);
+        trie.enable_filter(1000, 10);
+        trie.par_extend((0..1000usize).into_par_iter().map(|i| (i.to_string(), i)));
+        assert_eq!(trie.len(), 1001);
+        assert_eq!(trie.get("7"), Some(&7));
+        assert_eq!(trie.get("999"), Some(&999));
+        assert_eq!(trie.get(""), Some(&1));
+
+        let collected: Trie<str, usize, 16> = (0..100usize).into_par_iter().map(|i| (format!("k{i}"), i)).collect();
+        assert_eq!(collected.len(), 100);
+        assert_eq!(collected.get("k42"), Some(&42));
+    }
+}
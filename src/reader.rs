@@ -0,0 +1,108 @@
+use std::ops::Deref;
+use std::sync::Arc;
+
+use crate::trie::{Trie, TriePathBuilder};
+
+/// A cheaply clonable, read-only handle on a published trie.
+///
+/// Clones share one `Arc`, so worker threads can each hold a reader without borrowing from the
+/// writer. Reads go through `Deref` to the shared `Trie`; it is never mutated again, and the
+/// writer publishes changes by handing out a new reader.
+pub struct TrieReader<K: ?Sized, T, const N: usize> {
+    trie: Arc<Trie<K, T, N>>,
+}
+
+impl<K: ?Sized, T, const N: usize> Clone for TrieReader<K, T, N> {
+    fn clone(&self) -> TrieReader<K, T, N> {
+        TrieReader {
+            trie: Arc::clone(&self.trie),
+        }
+    }
+}
+
+impl<K: ?Sized, T, const N: usize> Deref for TrieReader<K, T, N> {
+    type Target = Trie<K, T, N>;
+
+    fn deref(&self) -> &Trie<K, T, N> {
+        &self.trie
+    }
+}
+
+impl<K: ?Sized, T, const N: usize> From<Trie<K, T, N>> for TrieReader<K, T, N> {
+    fn from(trie: Trie<K, T, N>) -> TrieReader<K, T, N> {
+        TrieReader { trie: Arc::new(trie) }
+    }
+}
+
+impl<K: ?Sized, T, const N: usize> TrieReader<K, T, N> {
+    // Whether both readers share the same published trie.
+    #[must_use]
+    pub fn ptr_eq(&self, other: &TrieReader<K, T, N>) -> bool {
+        Arc::ptr_eq(&self.trie, &other.trie)
+    }
+}
+
+impl<K: ?Sized, T, const N: usize> Trie<K, T, N>
+where
+    Trie<K, T, N>: TriePathBuilder<K>,
+{
+    #[must_use]
+    pub fn into_reader(self) -> TrieReader<K, T, N> {
+        TrieReader::from(self)
+    }
+
+    // Publishes a copy of the current entries, leaving this trie free to keep changing. Use a
+    // `SharedTrie` to make the value copies cheap. The copy has no filter or prefix counts.
+    #[must_use]
+    pub fn reader(&self) -> TrieReader<K, T, N>
+    where
+        T: Clone,
+    {
+        let mut copy = Trie::with_layout(self.layout);
+        copy.root = self.root.clone_subtree();
+        copy.len = self.len;
+        // The copy keeps the valueless nodes `take` left behind.
+        copy.hollow = self.hollow;
+        TrieReader::from(copy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use crate::trie::Trie;
+
+    #[test]
+    fn shared_across_threads() {
+        let mut trie: Trie<str, usize, 16> = Trie::new();
+        trie.insert("a", 1);
+        trie.insert("ab", 2);
+        let reader = trie.reader();
+        trie.insert("b", 3);
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let reader = reader.clone();
+                thread::spawn(move || (reader.get("ab").copied(), reader.count_prefix("a"), reader.iter().count()))
+            })
+            .collect();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), (Some(2), 2, 2));
+        }
+        assert!(reader.ptr_eq(&reader.clone()));
+        assert_eq!(trie.into_reader().len(), 3);
+    }
+
+    #[test]
+    fn reader_of_hollow_trie() {
+        let mut trie: Trie<str, usize, 16> = Trie::new();
+        trie.insert("b", 1);
+        trie.insert("c", 2);
+        let _ = (trie.take("b"), trie.take("c"));
+        trie.insert("a", 3);
+        let reader = trie.reader();
+        assert_eq!(reader.last_key_value(), Some(("a".to_string(), &3)));
+        assert!(reader.self_test().is_healthy());
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+
+use crate::trie::{Trie, TriePathBuilder};
+use crate::trie_node::TrieNode;
+
+/// Lookup counts gathered with `Trie::get_counted`, for `Trie::retune`.
+#[derive(Clone, Debug, Default)]
+pub struct AccessStats {
+    hits: HashMap<Vec<usize>, usize>,
+}
+
+impl AccessStats {
+    #[must_use]
+    pub fn new() -> AccessStats {
+        AccessStats::default()
+    }
+
+    pub fn record_path(&mut self, path: &[usize]) {
+        match self.hits.get_mut(path) {
+            Some(hits) => *hits += 1,
+            None => {
+                self.hits.insert(path.to_vec(), 1);
+            }
+        }
+    }
+
+    // Distinct paths recorded.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.hits.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.hits.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.hits.clear();
+    }
+}
+
+impl<K: ?Sized, T, const N: usize> Trie<K, T, N>
+where
+    Trie<K, T, N>: TriePathBuilder<K>,
+{
+    // `get`, also counting the lookup in `stats`, hit or miss.
+    pub fn get_counted(&self, key: &K, stats: &mut AccessStats) -> Option<&T> {
+        let path = <Trie<K, T, N> as TriePathBuilder<K>>::build_path(key);
+        stats.record_path(&path);
+        self.get_path(&path)
+    }
+}
+
+impl<K: ?Sized, T, const N: usize> Trie<K, T, N> {
+    // Reorders the children of every sorted-layout node so the ones that led to the most recorded
+    // lookups come first and are found by a short scan, for workloads skewed towards a few
+    // branches. Nodes without any recorded lookups below them go back to binary search, so
+    // retuning with empty stats undoes it. Iteration order is unaffected. Children added later
+    // start at the back, and clones are not ranked.
+    pub fn retune(&mut self, stats: &AccessStats) {
+        let mut heat: Trie<K, usize, N> = Trie::empty(self.layout);
+        for (path, &hits) in &stats.hits {
+            if path.iter().all(|&index| index < N) {
+                heat.upsert_path(path, || 0, |total| *total += hits);
+            }
+        }
+        retune_node(&mut self.root, Some(&heat.root));
+    }
+}
+
+// Ranks the children below `node` and returns the lookups that ended at or below it.
+fn retune_node<T, const N: usize>(node: &mut TrieNode<T, N>, heat: Option<&TrieNode<usize, N>>) -> usize {
+    let mut child_heat = Vec::new();
+    for index in node.child_indices().collect::<Vec<_>>() {
+        let below = heat.and_then(|heat| heat.child(index));
+        child_heat.push((index, retune_node(node.child_mut(index).unwrap(), below)));
+    }
+    node.rank_children(|index| child_heat.binary_search_by_key(&index, |(i, _)| *i).map_or(0, |pos| child_heat[pos].1));
+    heat.and_then(TrieNode::value).copied().unwrap_or(0) + child_heat.iter().map(|(_, hits)| hits).sum::<usize>()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::retune::AccessStats;
+    use crate::trie::{NodeLayout, Trie};
+
+    #[test]
+    fn hot_children_first() {
+        let mut trie: Trie<str, usize, 16> = Trie::with_layout(NodeLayout::Sorted);
+        for (i, key) in ["alpha", "beta", "gamma", "zeta"].iter().enumerate() {
+            trie.insert(key, i);
+        }
+        let mut stats = AccessStats::new();
+        for _ in 0..10 {
+            assert_eq!(trie.get_counted("zeta", &mut stats), Some(&3));
+        }
+        assert_eq!(trie.get_counted("beta", &mut stats), Some(&1));
+        trie.retune(&stats);
+
+        // 'z' is 0x7a, the others start with high nibble 6.
+        assert_eq!(trie.root.stored_order(), vec![7, 6]);
+        assert_eq!(trie.get("zeta"), Some(&3));
+        trie.insert("omega", 4);
+        let keys: Vec<String> = trie.iter_owned().map(|(key, _)| key).collect();
+        assert_eq!(keys, vec!["alpha", "beta", "gamma", "omega", "zeta"]);
+        assert_eq!(trie.remove("zeta"), Some(3));
+
+        assert_eq!(trie.root.child(6).unwrap().stored_order(), vec![2, 1, 7, 15]);
+
+        trie.retune(&AccessStats::new());
+        assert_eq!(trie.root.child(6).unwrap().stored_order(), vec![1, 2, 7, 15]);
+        assert_eq!(trie.iter().count(), 4);
+    }
+}
@@ -0,0 +1,183 @@
+use crate::trie::{Trie, TriePathBuilder, TriePathDecoder};
+
+/// Key prefix aliases for `Trie::rewritten`, e.g. to keep serving a legacy namespace from
+/// entries already migrated to a new one.
+pub struct RewriteRules<K: ?Sized, const N: usize> {
+    // Each `from` prefix maps to the path of its `to` prefix; the longest matching `from` wins.
+    rules: Trie<K, Vec<usize>, N>,
+}
+
+impl<K: ?Sized, const N: usize> RewriteRules<K, N>
+where
+    Trie<K, Vec<usize>, N>: TriePathBuilder<K>,
+{
+    #[must_use]
+    pub fn new() -> RewriteRules<K, N> {
+        RewriteRules { rules: Trie::new() }
+    }
+
+    // Replaces any rule already registered for exactly this `from` prefix, returning whether
+    // there was one.
+    pub fn add(&mut self, from: &K, to: &K) -> bool {
+        let to = <Trie<K, Vec<usize>, N> as TriePathBuilder<K>>::build_path(to);
+        self.rules.insert(from, to).is_some()
+    }
+
+    pub fn remove(&mut self, from: &K) -> bool {
+        self.rules.remove(from).is_some()
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+}
+
+impl<K: ?Sized, const N: usize> Default for RewriteRules<K, N>
+where
+    Trie<K, Vec<usize>, N>: TriePathBuilder<K>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: ?Sized, const N: usize> RewriteRules<K, N> {
+    // Length of the longest `from` prefix of `path`, the rule `get` applies to it.
+    fn matched_len(&self, path: &[usize]) -> Option<usize> {
+        self.rules.longest_prefix_path(path).map(|(matched, _)| matched)
+    }
+}
+
+impl<K: ?Sized, T, const N: usize> Trie<K, T, N> {
+    // A read view that looks keys up through `rules`: a key under a `from` prefix is served from
+    // the same key under the `to` prefix, and the `from` namespace itself is no longer consulted.
+    #[must_use]
+    pub fn rewritten<'a>(&'a self, rules: &'a RewriteRules<K, N>) -> Rewritten<'a, K, T, N> {
+        Rewritten { trie: self, rules }
+    }
+}
+
+pub struct Rewritten<'a, K: ?Sized, T, const N: usize> {
+    trie: &'a Trie<K, T, N>,
+    rules: &'a RewriteRules<K, N>,
+}
+
+impl<'a, K: ?Sized, T, const N: usize> Rewritten<'a, K, T, N> {
+    #[must_use]
+    pub fn get_path(&self, path: &[usize]) -> Option<&'a T> {
+        let Some((matched, to)) = self.rules.rules.longest_prefix_path(path) else {
+            return self.trie.get_path(path);
+        };
+        // Walks the target prefix and then the rest of the key, without building the new path.
+        let mut current_node = &self.trie.root;
+        for &child_index in to.iter().chain(&path[matched..]) {
+            current_node = current_node.child(child_index)?;
+        }
+        current_node.value()
+    }
+}
+
+impl<'a, K: ?Sized, T, const N: usize> Rewritten<'a, K, T, N>
+where
+    Trie<K, T, N>: TriePathBuilder<K>,
+{
+    #[must_use]
+    pub fn get(&self, key: &K) -> Option<&'a T> {
+        self.get_path(&<Trie<K, T, N> as TriePathBuilder<K>>::build_path(key))
+    }
+
+    #[must_use]
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+}
+
+impl<'a, K: ?Sized + ToOwned, T, const N: usize> Rewritten<'a, K, T, N>
+where
+    Trie<K, T, N>: TriePathBuilder<K> + TriePathDecoder<K>,
+{
+    // The keys under `prefix` that `get` finds, in key order, with the values it returns for
+    // them. Each rule contributes the entries under its `to` prefix that it is the most specific
+    // rule for, keyed under its `from` prefix; stored keys under any `from` prefix are shadowed.
+    pub fn iter_prefix(&self, prefix: &K) -> impl Iterator<Item = (K::Owned, &'a T)> {
+        let prefix = <Trie<K, T, N> as TriePathBuilder<K>>::build_path(prefix);
+        let mut entries = Vec::new();
+        let mut full_path = Vec::new();
+        if let Some(node) = self.trie.node_at(&prefix) {
+            node.visit_paths(|path, val| {
+                full_path.clear();
+                full_path.extend(prefix.iter().chain(path));
+                if self.rules.matched_len(&full_path).is_none() {
+                    entries.push((full_path.clone(), val));
+                }
+            });
+        }
+        self.rules.rules.root.visit_paths(|from, to| {
+            // The part of `prefix` past `from`, when one starts with the other.
+            let rest = match prefix.strip_prefix(from) {
+                Some(rest) => rest,
+                None if from.starts_with(&prefix) => &[],
+                None => return,
+            };
+            let target: Vec<usize> = to.iter().chain(rest).copied().collect();
+            let Some(node) = self.trie.node_at(&target) else {
+                return;
+            };
+            node.visit_paths(|path, val| {
+                full_path.clear();
+                full_path.extend(from.iter().chain(rest).chain(path));
+                if self.rules.matched_len(&full_path) == Some(from.len()) {
+                    entries.push((full_path.clone(), val));
+                }
+            });
+        });
+        entries.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+        entries.into_iter().map(|(path, val)| {
+            let key = <Trie<K, T, N> as TriePathDecoder<K>>::decode_path(&path).expect("trie holds a path that is not a valid key");
+            (key, val)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rewrite::RewriteRules;
+    use crate::trie::Trie;
+
+    #[test]
+    fn aliases() {
+        let mut trie: Trie<str, usize, 16> = Trie::new();
+        for (i, key) in ["v2/users/ann", "v2/users/bob", "v3/users/ann", "v1/other"].iter().enumerate() {
+            trie.insert(key, i);
+        }
+        let mut rules = RewriteRules::new();
+        assert!(!rules.add("v1/", "v2/"));
+        assert!(!rules.add("v1/users/", "v3/users/"));
+        let view = trie.rewritten(&rules);
+
+        assert_eq!(view.get("v1/users/ann"), Some(&2));
+        assert_eq!(view.get("v1/users"), None);
+        assert_eq!(view.get("v2/users/bob"), Some(&1));
+        // Shadowed by the rule, although it is stored.
+        assert_eq!(view.get("v1/other"), None);
+        // Listing agrees with lookups: the most specific rule serves each key, and shadowed keys
+        // are left out.
+        let entries: Vec<(String, &usize)> = view.iter_prefix("v1").collect();
+        assert_eq!(entries, vec![("v1/users/ann".to_string(), &2)]);
+        assert!(entries.iter().all(|(key, val)| view.get(key) == Some(*val)));
+        assert_eq!(view.iter_prefix("v1/users/a").count(), 1);
+        let all: Vec<(String, &usize)> = view.iter_prefix("").collect();
+        assert_eq!(all.iter().map(|(key, _)| key.as_str()).collect::<Vec<_>>(), vec!["v1/users/ann", "v2/users/ann", "v2/users/bob", "v3/users/ann"]);
+        assert!(all.iter().all(|(key, val)| view.get(key) == Some(*val)));
+
+        assert!(rules.remove("v1/"));
+        assert_eq!(trie.rewritten(&rules).get("v1/other"), Some(&3));
+        assert_eq!(rules.len(), 1);
+    }
+}
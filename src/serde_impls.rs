@@ -0,0 +1,87 @@
+use std::borrow::Borrow;
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::{Deserialize, Deserializer, MapAccess, Visitor};
+use serde::ser::{Error, Serialize, SerializeMap, Serializer};
+
+use crate::trie::{Trie, TriePathBuilder, TriePathDecoder};
+
+// A map from decoded keys to values, in key order. Paths that do not decode to a key, e.g. ones
+// inserted through the raw path API, fail the serialization.
+impl<K, T, const N: usize> Serialize for Trie<K, T, N>
+where
+    K: ?Sized + ToOwned,
+    K::Owned: Serialize,
+    T: Serialize,
+    Trie<K, T, N>: TriePathDecoder<K>,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.len))?;
+        for (path, val) in self.iter_paths() {
+            let key = <Trie<K, T, N> as TriePathDecoder<K>>::decode_path(&path).map_err(S::Error::custom)?;
+            map.serialize_entry(&key, val)?;
+        }
+        map.end()
+    }
+}
+
+// Rebuilds the trie by inserting every entry, with the default layout; a repeated key keeps the
+// last value.
+impl<'de, K, T, const N: usize> Deserialize<'de> for Trie<K, T, N>
+where
+    K: ?Sized + ToOwned,
+    K::Owned: Deserialize<'de>,
+    T: Deserialize<'de>,
+    Trie<K, T, N>: TriePathBuilder<K>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(TrieVisitor(PhantomData))
+    }
+}
+
+struct TrieVisitor<K: ?Sized, T, const N: usize>(PhantomData<Trie<K, T, N>>);
+
+impl<'de, K, T, const N: usize> Visitor<'de> for TrieVisitor<K, T, N>
+where
+    K: ?Sized + ToOwned,
+    K::Owned: Deserialize<'de>,
+    T: Deserialize<'de>,
+    Trie<K, T, N>: TriePathBuilder<K>,
+{
+    type Value = Trie<K, T, N>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a map of trie keys to values")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut trie = Trie::new();
+        while let Some((key, val)) = map.next_entry::<K::Owned, T>()? {
+            trie.insert(key.borrow(), val);
+        }
+        Ok(trie)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trie::Trie;
+
+    #[test]
+    fn json_round_trip() {
+        let mut trie: Trie<str, Vec<u32>, 16> = Trie::new();
+        trie.insert("b", vec![2]);
+        trie.insert("", vec![]);
+        trie.insert("a", vec![1, 1]);
+        let json = serde_json::to_string(&trie).unwrap();
+        assert_eq!(json, r#"{"":[],"a":[1,1],"b":[2]}"#);
+
+        let back: Trie<str, Vec<u32>, 16> = serde_json::from_str(&json).unwrap();
+        assert!(back.iter().eq(trie.iter()));
+        assert!(serde_json::from_str::<Trie<str, u32, 16>>("[1]").is_err());
+
+        trie.insert_path(&[15, 15], vec![]);
+        assert!(serde_json::to_string(&trie).is_err());
+    }
+}
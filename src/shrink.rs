@@ -0,0 +1,74 @@
+use crate::trie::Trie;
+
+impl<K: ?Sized, T, const N: usize> Trie<K, T, N> {
+    // Test-case minimization: given a predicate that holds while the bug still reproduces,
+    // greedily removes whole subtrees, widest first, then single entries, keeping each removal
+    // only if the predicate still holds. Returns the trie unchanged if it does not hold at all.
+    //
+    // Removed entries are put back with `insert_path` when the predicate stops holding, so the
+    // values need not be `Clone`. Expect one predicate call per node.
+    #[must_use]
+    pub fn shrink_to_witness(mut self, mut still_fails: impl FnMut(&Trie<K, T, N>) -> bool) -> Trie<K, T, N> {
+        if !still_fails(&self) {
+            return self;
+        }
+        let mut level = vec![Vec::new()];
+        while !level.is_empty() {
+            let mut next_level = Vec::new();
+            for path in level {
+                let Some(node) = self.node_at(&path) else {
+                    continue;
+                };
+                for index in node.child_indices().collect::<Vec<_>>() {
+                    let mut child_path = path.clone();
+                    child_path.push(index);
+                    let mut removed = Vec::new();
+                    self.remove_prefix_path(&child_path, |full_path, val| removed.push((full_path.to_vec(), val)));
+                    if still_fails(&self) {
+                        continue;
+                    }
+                    for (full_path, val) in removed {
+                        self.insert_path(&full_path, val);
+                    }
+                    next_level.push(child_path);
+                }
+            }
+            level = next_level;
+        }
+
+        let mut paths = Vec::new();
+        self.root.visit_paths(|path, _| paths.push(path.to_vec()));
+        for path in paths {
+            let Some(val) = self.delete_path(&path) else {
+                continue;
+            };
+            if !still_fails(&self) {
+                self.insert_path(&path, val);
+            }
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trie::Trie;
+
+    #[test]
+    fn minimizes() {
+        let mut trie: Trie<str, usize, 16> = Trie::new();
+        for (i, key) in ["a", "ab", "abc", "b", "bad", "cab", "zzz"].iter().enumerate() {
+            trie.insert(key, i);
+        }
+        // "Fails" while both an entry starting with "ab" and one ending in 'd' remain.
+        let fails = |trie: &Trie<str, usize, 16>| {
+            let keys: Vec<String> = trie.iter_owned().map(|(key, _)| key).collect();
+            keys.iter().any(|key| key.starts_with("ab")) && keys.iter().any(|key| key.ends_with('d'))
+        };
+        let witness = trie.shrink_to_witness(fails);
+        let keys: Vec<String> = witness.iter_owned().map(|(key, _)| key).collect();
+        assert_eq!(keys.len(), 2);
+        assert!(keys.contains(&"bad".to_string()));
+        assert_eq!(witness.len(), 2);
+    }
+}
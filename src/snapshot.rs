@@ -0,0 +1,323 @@
+use std::io::{self, Read, Write};
+
+use crate::codec::{read_varint, write_varint, ValueCodec};
+use crate::trie::{NodeLayout, Trie, TriePathBuilder};
+use crate::trie_node::TrieNode;
+
+const MAGIC: &[u8; 8] = b"HTRIESNP";
+const NODES_MAGIC: &[u8; 8] = b"HTRIENOD";
+// Bumped whenever the `write_to` layout changes; `read_from` rejects versions it doesn't know.
+const NODES_VERSION: usize = 1;
+const LAYOUTS: [NodeLayout; 4] = [NodeLayout::Dense, NodeLayout::Packed, NodeLayout::Sorted, NodeLayout::Adaptive];
+
+/// Size figures for one `Trie::save`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SnapshotStats {
+    pub entries: usize,
+    // Path indices across all keys, as a plain key dump would store them.
+    pub path_indices: usize,
+    // Path indices left out because they repeat the previous key's prefix.
+    pub shared_indices: usize,
+    pub value_bytes: usize,
+    pub total_bytes: usize,
+}
+
+impl SnapshotStats {
+    // The fraction of path indices front coding did not have to write.
+    #[must_use]
+    pub fn shared_ratio(&self) -> f64 {
+        if self.path_indices == 0 {
+            return 0.0;
+        }
+        self.shared_indices as f64 / self.path_indices as f64
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+// 64-bit FNV-1a, enough to catch truncation and bit rot in a snapshot, not tampering.
+fn checksum(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3))
+}
+
+impl<K: ?Sized, T, const N: usize> Trie<K, T, N> {
+    // Writes the entries in path order, each key front coded against the previous one: the
+    // number of leading path indices they share, then only the rest. Sorted keys from natural
+    // text share most of their prefixes, which the trie already stores once in memory.
+    //
+    // Layout: magic, then varints N and the entry count, then per entry varints for the shared
+    // length, the suffix length, each suffix index and the value length, followed by the value.
+    pub fn save<W: Write, C: ValueCodec<T>>(&self, mut out: W, codec: &C) -> io::Result<SnapshotStats> {
+        let mut stats = SnapshotStats::default();
+        let mut buf = Vec::new();
+        buf.extend_from_slice(MAGIC);
+        write_varint(&mut buf, N);
+        write_varint(&mut buf, self.len);
+        let mut previous: Vec<usize> = Vec::new();
+        let mut value = Vec::new();
+        let mut result = Ok(());
+        self.root.visit_paths(|path, val| {
+            if result.is_err() {
+                return;
+            }
+            let shared = previous.iter().zip(path).take_while(|(a, b)| a == b).count();
+            write_varint(&mut buf, shared);
+            write_varint(&mut buf, path.len() - shared);
+            for &index in &path[shared..] {
+                write_varint(&mut buf, index);
+            }
+            value.clear();
+            codec.encode(val, &mut value);
+            write_varint(&mut buf, value.len());
+            buf.extend_from_slice(&value);
+
+            stats.entries += 1;
+            stats.path_indices += path.len();
+            stats.shared_indices += shared;
+            stats.value_bytes += value.len();
+            previous.clear();
+            previous.extend_from_slice(path);
+            if buf.len() >= 1 << 16 {
+                stats.total_bytes += buf.len();
+                result = out.write_all(&buf);
+                buf.clear();
+            }
+        });
+        result?;
+        stats.total_bytes += buf.len();
+        out.write_all(&buf)?;
+        out.flush()?;
+        Ok(stats)
+    }
+}
+
+impl<K: ?Sized, T, const N: usize> Trie<K, T, N> {
+    // Dumps the node structure itself rather than the keys, so `read_from` rebuilds the trie
+    // node by node without walking down from the root for every key.
+    //
+    // Layout: magic, then varints for the format version, N, the layout, the node and value
+    // counts and the node table's length in bytes. The node table lists the nodes in preorder,
+    // each as a varint of its child count shifted left by one with the low bit set when it holds
+    // a value, followed by its child indices. The value table then holds the values in the same
+    // order, each a varint length and the encoded bytes. A little-endian FNV-1a checksum of
+    // everything before it closes the snapshot.
+    pub fn write_to<W: Write, C: ValueCodec<T>>(&self, mut out: W, codec: &C) -> io::Result<()> {
+        let (mut nodes, mut node_table, mut value_table) = (0, Vec::new(), Vec::new());
+        let mut value = Vec::new();
+        let mut stack = vec![&self.root];
+        while let Some(node) = stack.pop() {
+            nodes += 1;
+            let children: Vec<usize> = node.child_indices().collect();
+            write_varint(&mut node_table, children.len() << 1 | usize::from(node.value().is_some()));
+            for &index in &children {
+                write_varint(&mut node_table, index);
+            }
+            if let Some(val) = node.value() {
+                value.clear();
+                codec.encode(val, &mut value);
+                write_varint(&mut value_table, value.len());
+                value_table.extend_from_slice(&value);
+            }
+            stack.extend(children.iter().rev().map(|&index| node.child(index).unwrap()));
+        }
+
+        let mut buf = Vec::with_capacity(node_table.len() + value_table.len() + 64);
+        buf.extend_from_slice(NODES_MAGIC);
+        write_varint(&mut buf, NODES_VERSION);
+        write_varint(&mut buf, N);
+        write_varint(&mut buf, LAYOUTS.iter().position(|&layout| layout == self.layout).unwrap());
+        write_varint(&mut buf, nodes);
+        write_varint(&mut buf, self.len);
+        write_varint(&mut buf, node_table.len());
+        buf.append(&mut node_table);
+        buf.append(&mut value_table);
+        buf.extend_from_slice(&checksum(&buf).to_le_bytes());
+        out.write_all(&buf)?;
+        out.flush()
+    }
+
+    // Reads a `write_to` snapshot, checking the checksum before anything else. The trie comes
+    // back with its layout, without a filter or prefix counts.
+    pub fn read_from<R: Read, C: ValueCodec<T>>(mut input: R, codec: &C) -> io::Result<Trie<K, T, N>> {
+        let mut bytes = Vec::new();
+        input.read_to_end(&mut bytes)?;
+        let body_len = bytes.len().checked_sub(8).ok_or_else(|| invalid("truncated trie snapshot"))?;
+        let (body, sum) = bytes.split_at(body_len);
+        let mut body = body.strip_prefix(NODES_MAGIC.as_slice()).ok_or_else(|| invalid("not a trie node snapshot"))?;
+        if checksum(&bytes[..body_len]).to_le_bytes() != sum {
+            return Err(invalid("trie snapshot checksum mismatch"));
+        }
+        let varint = |bytes: &mut &[u8]| read_varint(bytes).ok_or_else(|| invalid("truncated trie snapshot"));
+        if varint(&mut body)? != NODES_VERSION {
+            return Err(invalid("unsupported trie snapshot version"));
+        }
+        if varint(&mut body)? != N {
+            return Err(invalid("trie snapshot was saved with a different arity"));
+        }
+        let layout = *LAYOUTS.get(varint(&mut body)?).ok_or_else(|| invalid("unknown node layout in trie snapshot"))?;
+        let (nodes, values, table_len) = (varint(&mut body)?, varint(&mut body)?, varint(&mut body)?);
+        if table_len > body.len() {
+            return Err(invalid("truncated trie snapshot"));
+        }
+        let (mut node_table, mut value_table) = body.split_at(table_len);
+
+        let mut read_node = |node_table: &mut &[u8], value_table: &mut &[u8]| -> io::Result<(TrieNode<T, N>, Vec<usize>)> {
+            let header = varint(node_table)?;
+            let mut children = Vec::with_capacity((header >> 1).min(N));
+            for _ in 0..header >> 1 {
+                let index = varint(node_table)?;
+                if index >= N || children.last().is_some_and(|&last| index <= last) {
+                    return Err(invalid("bad child index in trie snapshot"));
+                }
+                children.push(index);
+            }
+            let mut node = TrieNode::with_layout(layout);
+            if header & 1 == 1 {
+                let len = varint(value_table)?;
+                if value_table.len() < len {
+                    return Err(invalid("truncated trie snapshot"));
+                }
+                let (value, rest) = value_table.split_at(len);
+                *value_table = rest;
+                node.value_replace(codec.decode(value)?);
+            }
+            Ok((node, children))
+        };
+
+        // Nodes still missing children, each with the index it hangs from and the children to go.
+        let (root, children) = read_node(&mut node_table, &mut value_table)?;
+        let mut stack = vec![(root, 0, children.into_iter())];
+        let (mut read, mut valued) = (1, usize::from(stack[0].0.value().is_some()));
+        // Valueless leaves, as `take` leaves behind, must be flagged for `keys_eq` and friends.
+        let mut hollow = false;
+        let root = loop {
+            let (_, _, children) = stack.last_mut().unwrap();
+            if let Some(index) = children.next() {
+                let (node, children) = read_node(&mut node_table, &mut value_table)?;
+                read += 1;
+                valued += usize::from(node.value().is_some());
+                hollow |= node.value().is_none() && children.is_empty();
+                stack.push((node, index, children.into_iter()));
+                continue;
+            }
+            let (node, index, _) = stack.pop().unwrap();
+            match stack.last_mut() {
+                Some((parent, _, _)) => {
+                    parent.child_set(index, node);
+                }
+                None => break node,
+            }
+        };
+        if read != nodes || valued != values || !node_table.is_empty() || !value_table.is_empty() {
+            return Err(invalid("trie snapshot counts do not match its tables"));
+        }
+        let mut trie = Trie::empty(layout);
+        trie.root = root;
+        trie.len = values;
+        trie.hollow = hollow;
+        Ok(trie)
+    }
+}
+
+impl<K: ?Sized, T, const N: usize> Trie<K, T, N>
+where
+    Trie<K, T, N>: TriePathBuilder<K>,
+{
+    pub fn load<R: Read, C: ValueCodec<T>>(mut input: R, codec: &C) -> io::Result<Trie<K, T, N>> {
+        let mut bytes = Vec::new();
+        input.read_to_end(&mut bytes)?;
+        let mut bytes = bytes
+            .strip_prefix(MAGIC.as_slice())
+            .ok_or_else(|| invalid("not a trie snapshot"))?;
+        let mut varint = |bytes: &mut &[u8]| read_varint(bytes).ok_or_else(|| invalid("truncated trie snapshot"));
+        if varint(&mut bytes)? != N {
+            return Err(invalid("trie snapshot was saved with a different arity"));
+        }
+        let entries = varint(&mut bytes)?;
+        let mut trie = Trie::new();
+        let mut path: Vec<usize> = Vec::new();
+        for _ in 0..entries {
+            let shared = varint(&mut bytes)?;
+            if shared > path.len() {
+                return Err(invalid("trie snapshot key shares more than the previous key"));
+            }
+            path.truncate(shared);
+            for _ in 0..varint(&mut bytes)? {
+                let index = varint(&mut bytes)?;
+                if index >= N {
+                    return Err(invalid("trie snapshot path index out of range"));
+                }
+                path.push(index);
+            }
+            let len = varint(&mut bytes)?;
+            if bytes.len() < len {
+                return Err(invalid("truncated trie snapshot"));
+            }
+            let (value, rest) = bytes.split_at(len);
+            bytes = rest;
+            if trie.insert_path(&path, codec.decode(value)?).is_some() {
+                return Err(invalid("trie snapshot holds a key twice"));
+            }
+        }
+        if !bytes.is_empty() {
+            return Err(invalid("trailing bytes after trie snapshot"));
+        }
+        Ok(trie)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::codec::BytesCodec;
+    use crate::trie::{NodeLayout, Trie};
+
+    #[test]
+    fn front_coded_round_trip() {
+        let mut trie: Trie<str, String, 16> = Trie::new();
+        for key in ["inter", "internal", "international", "internet", "interval", ""] {
+            trie.insert(key, key.to_uppercase());
+        }
+        let mut bytes = Vec::new();
+        let stats = trie.save(&mut bytes, &BytesCodec).unwrap();
+        assert_eq!(stats.entries, 6);
+        assert_eq!(stats.total_bytes, bytes.len());
+        assert!(stats.shared_ratio() > 0.5);
+
+        let loaded: Trie<str, String, 16> = Trie::load(bytes.as_slice(), &BytesCodec).unwrap();
+        assert_eq!(loaded.len(), 6);
+        assert_eq!(loaded.get("internet").map(String::as_str), Some("INTERNET"));
+        assert!(Trie::<str, String, 16>::load(&bytes[..bytes.len() - 1], &BytesCodec).is_err());
+    }
+
+    #[test]
+    fn node_snapshot_round_trip() {
+        let mut trie: Trie<str, String, 16> = Trie::with_layout(NodeLayout::Sorted);
+        for key in ["inter", "internal", "", "zebra"] {
+            trie.insert(key, key.to_uppercase());
+        }
+        let _ = trie.take("zebra");
+        let mut bytes = Vec::new();
+        trie.write_to(&mut bytes, &BytesCodec).unwrap();
+
+        let loaded: Trie<str, String, 16> = Trie::read_from(bytes.as_slice(), &BytesCodec).unwrap();
+        assert!(loaded.iter().eq(trie.iter()));
+        assert_eq!((loaded.len(), loaded.layout(), loaded.root.footprint().1), (3, NodeLayout::Sorted, trie.root.footprint().1));
+        assert!(loaded.keys_eq(&trie));
+
+        let mut corrupt = bytes.clone();
+        corrupt[20] ^= 1;
+        let Err(err) = Trie::<str, String, 16>::read_from(corrupt.as_slice(), &BytesCodec) else { panic!("corrupt snapshot loaded") };
+        assert_eq!(err.to_string(), "trie snapshot checksum mismatch");
+        assert!(Trie::<str, String, 16>::read_from(&bytes[..bytes.len() - 1], &BytesCodec).is_err());
+        // A snapshot from a later format version.
+        let mut newer = bytes.clone();
+        newer[8] = 2;
+        let body = newer.len() - 8;
+        let sum = super::checksum(&newer[..body]).to_le_bytes();
+        newer[body..].copy_from_slice(&sum);
+        let Err(err) = Trie::<str, String, 16>::read_from(newer.as_slice(), &BytesCodec) else { panic!("newer snapshot loaded") };
+        assert_eq!(err.to_string(), "unsupported trie snapshot version");
+    }
+}
@@ -0,0 +1,203 @@
+use crate::frozen::{FrozenEdge, FrozenNode, NO_VALUE};
+use crate::keys::digits_per_byte;
+
+// Const builders for the tables behind `static_trie!`. Keys are byte strings split into `N`-ary
+// digits as `push_digits` splits them, which is how the `str` and `[u8]` tries build paths.
+
+// The `pos`th digit of the path of `key`.
+const fn digit<const N: usize>(key: &[u8], pos: usize) -> usize {
+    let per_byte = digits_per_byte::<N>();
+    let bits = N.trailing_zeros() as usize;
+    let shift = (per_byte - 1 - pos % per_byte) * bits;
+    (key[pos / per_byte] as usize >> shift) & (N - 1)
+}
+
+const fn path_len<const N: usize>(key: &[u8]) -> usize {
+    key.len() * digits_per_byte::<N>()
+}
+
+// How the first `len` digits of `a` and `b` compare: -1, 0 or 1. Both paths must be that long.
+const fn cmp_prefix<const N: usize>(a: &[u8], b: &[u8], len: usize) -> i8 {
+    let mut pos = 0;
+    while pos < len {
+        let (x, y) = (digit::<N>(a, pos), digit::<N>(b, pos));
+        if x != y {
+            return if x < y { -1 } else { 1 };
+        }
+        pos += 1;
+    }
+    0
+}
+
+// Nodes in the trie holding `keys`: the root plus, for each key, the digits past its longest
+// common prefix with the keys before it.
+#[doc(hidden)]
+#[must_use]
+pub const fn node_count<const N: usize>(keys: &[&[u8]]) -> usize {
+    let mut count = 1;
+    let mut i = 0;
+    while i < keys.len() {
+        let len = path_len::<N>(keys[i]);
+        let mut shared = 0;
+        let mut j = 0;
+        while j < i {
+            let mut common = 0;
+            let limit = if path_len::<N>(keys[j]) < len { path_len::<N>(keys[j]) } else { len };
+            while common < limit && digit::<N>(keys[i], common) == digit::<N>(keys[j], common) {
+                common += 1;
+            }
+            if common > shared {
+                shared = common;
+            }
+            j += 1;
+        }
+        count += len - shared;
+        i += 1;
+    }
+    count
+}
+
+// The node and edge tables of the trie holding `keys`, laid out breadth first as `Trie::freeze`
+// lays them out. Node values index `keys`, so the values can stay in the order they were given.
+#[doc(hidden)]
+#[must_use]
+pub const fn tables<const N: usize, const NODES: usize, const EDGES: usize>(keys: &[&[u8]]) -> ([FrozenNode; NODES], [FrozenEdge; EDGES]) {
+    assert!(EDGES + 1 == NODES, "a trie has one edge per node below the root");
+    // Each node as the first key through it and its depth, level by level and in key order
+    // within a level.
+    let mut through = [0; NODES];
+    let mut depths = [0; NODES];
+    let mut count = 1;
+    let mut depth = 1;
+    while count < NODES {
+        assert!(depth <= 8 * digits_per_byte::<N>() * NODES, "node count does not match the keys");
+        let mut prev: Option<usize> = None;
+        loop {
+            let mut next: Option<usize> = None;
+            let mut i = 0;
+            while i < keys.len() {
+                if path_len::<N>(keys[i]) < depth {
+                    i += 1;
+                    continue;
+                }
+                let after_prev = match prev {
+                    Some(p) => cmp_prefix::<N>(keys[i], keys[p], depth) > 0,
+                    None => true,
+                };
+                let before_next = match next {
+                    Some(n) => cmp_prefix::<N>(keys[i], keys[n], depth) < 0,
+                    None => true,
+                };
+                if after_prev && before_next {
+                    next = Some(i);
+                }
+                i += 1;
+            }
+            let Some(n) = next else {
+                break;
+            };
+            through[count] = n;
+            depths[count] = depth;
+            count += 1;
+            prev = next;
+        }
+        depth += 1;
+    }
+
+    let mut nodes = [FrozenNode::new(NO_VALUE, 0, 0); NODES];
+    let mut edges = [FrozenEdge::new(0, 0); EDGES];
+    let mut first_edge = 0;
+    let mut v = 0;
+    while v < NODES {
+        let (key, depth) = (keys[through[v]], depths[v]);
+        let mut value = NO_VALUE;
+        let mut i = 0;
+        while i < keys.len() {
+            if path_len::<N>(keys[i]) == depth && cmp_prefix::<N>(keys[i], key, depth) == 0 {
+                assert!(value == NO_VALUE, "static_trie! keys must be distinct");
+                value = i as u32;
+            }
+            i += 1;
+        }
+        let mut edge_count = 0;
+        let mut w = v + 1;
+        while w < NODES {
+            if depths[w] == depth + 1 && cmp_prefix::<N>(keys[through[w]], key, depth) == 0 {
+                edge_count += 1;
+            }
+            w += 1;
+        }
+        nodes[v] = FrozenNode::new(value, first_edge, edge_count);
+        first_edge += edge_count;
+        if v > 0 {
+            edges[v - 1] = FrozenEdge::new(digit::<N>(key, depth - 1) as u32, v as u32);
+        }
+        v += 1;
+    }
+    (nodes, edges)
+}
+
+/// Declares a `static` `FrozenTrie` whose tables are built during compilation, so a small
+/// dictionary costs nothing at startup.
+///
+/// Keys are `str` or `[u8]` literals and values constant expressions, for a trie of arity 16 or
+/// 256. The tables are worked out by const evaluation in time quadratic in the number of keys;
+/// large dictionaries are better generated in a build script with `FrozenTrie::write_rust`.
+#[macro_export]
+macro_rules! static_trie {
+    ($(#[$attr:meta])* $vis:vis static $name:ident: FrozenTrie<str, $value:ty, $n:literal> = { $($k:expr => $v:expr),* $(,)? };) => {
+        $crate::static_trie! { @tables $(#[$attr])* $vis $name, str, $value, $n, [$(str::as_bytes($k)),*], [$($v),*] }
+    };
+    ($(#[$attr:meta])* $vis:vis static $name:ident: FrozenTrie<[u8], $value:ty, $n:literal> = { $($k:expr => $v:expr),* $(,)? };) => {
+        $crate::static_trie! { @tables $(#[$attr])* $vis $name, [u8], $value, $n, [$($k),*], [$($v),*] }
+    };
+    (@tables $(#[$attr:meta])* $vis:vis $name:ident, $key:ty, $value:ty, $n:literal, [$($bytes:expr),*], [$($v:expr),*]) => {
+        $(#[$attr])*
+        $vis static $name: $crate::frozen::FrozenTrie<'static, $key, $value, $n> = {
+            const KEYS: &[&[u8]] = &[$($bytes),*];
+            const NODES: usize = $crate::static_trie::node_count::<$n>(KEYS);
+            static TABLES: ([$crate::frozen::FrozenNode; NODES], [$crate::frozen::FrozenEdge; NODES - 1]) =
+                $crate::static_trie::tables::<$n, NODES, { NODES - 1 }>(KEYS);
+            static VALUES: [$value; KEYS.len()] = [$($v),*];
+            $crate::frozen::FrozenTrie::from_static(&TABLES.0, &TABLES.1, &VALUES)
+        };
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trie::Trie;
+
+    crate::static_trie! {
+        static NUMBERS: FrozenTrie<str, u32, 16> = {
+            "two" => 2,
+            "one" => 1,
+            "three" => 3,
+            "" => 0,
+            "thirty" => 30,
+        };
+    }
+
+    crate::static_trie! {
+        static BYTES: FrozenTrie<[u8], u8, 256> = {
+            b"ab" => 1,
+            b"a" => 2,
+            b"b\xff" => 3,
+        };
+    }
+
+    #[test]
+    fn static_trie() {
+        assert_eq!(NUMBERS.len(), 5);
+        assert_eq!((NUMBERS.get("two"), NUMBERS.get(""), NUMBERS.get("thirty")), (Some(&2), Some(&0), Some(&30)));
+        assert_eq!((NUMBERS.get("four"), NUMBERS.get("thir")), (None, None));
+        assert_eq!((BYTES.get(b"a"), BYTES.get(b"b\xff"), BYTES.get(b"b")), (Some(&2), Some(&3), None));
+
+        // Same entries in the same order as a frozen runtime trie.
+        let mut trie: Trie<str, u32, 16> = Trie::new();
+        for (key, val) in [("two", 2), ("one", 1), ("three", 3), ("", 0), ("thirty", 30)] {
+            trie.insert(key, val);
+        }
+        assert!(trie.freeze().iter().eq(NUMBERS.iter()));
+    }
+}
@@ -0,0 +1,160 @@
+use crate::trie::{Iter, Trie, TriePathBuilder, TriePathDecoder};
+use crate::trie_node::TrieNode;
+
+/// A read-only view of the entries whose keys start with a prefix, rooted at the prefix node so
+/// lookups inside it skip the walk down to the prefix. Keys passed in and handed out are full
+/// keys, prefix included.
+pub struct SubTrie<'a, K: ?Sized, T, const N: usize> {
+    trie: &'a Trie<K, T, N>,
+    prefix: Vec<usize>,
+    // `None` when no key has the prefix.
+    node: Option<&'a TrieNode<T, N>>,
+}
+
+/// A mutable view of the entries under a prefix. Inserting a key outside the prefix panics;
+/// lookups and removals treat such keys as absent.
+pub struct SubTrieMut<'a, K: ?Sized, T, const N: usize> {
+    trie: &'a mut Trie<K, T, N>,
+    prefix: Vec<usize>,
+}
+
+impl<K: ?Sized, T, const N: usize> Trie<K, T, N>
+where
+    Trie<K, T, N>: TriePathBuilder<K>,
+{
+    #[must_use]
+    pub fn subtrie(&self, prefix: &K) -> SubTrie<'_, K, T, N> {
+        let prefix = <Trie<K, T, N> as TriePathBuilder<K>>::build_path(prefix);
+        SubTrie {
+            trie: self,
+            node: self.node_at(&prefix),
+            prefix,
+        }
+    }
+
+    #[must_use]
+    pub fn subtrie_mut(&mut self, prefix: &K) -> SubTrieMut<'_, K, T, N> {
+        SubTrieMut {
+            prefix: <Trie<K, T, N> as TriePathBuilder<K>>::build_path(prefix),
+            trie: self,
+        }
+    }
+}
+
+impl<'a, K: ?Sized, T, const N: usize> SubTrie<'a, K, T, N>
+where
+    Trie<K, T, N>: TriePathBuilder<K>,
+{
+    #[must_use]
+    pub fn get(&self, key: &K) -> Option<&'a T> {
+        let path = <Trie<K, T, N> as TriePathBuilder<K>>::build_path(key);
+        let rest = path.strip_prefix(self.prefix.as_slice())?;
+        let mut current_node = self.node?;
+        for &child_index in rest {
+            current_node = (child_index < N).then(|| current_node.child(child_index)).flatten()?;
+        }
+        current_node.value()
+    }
+
+    // Uses the cached prefix counts when the prefix is shallow enough.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        match self.node {
+            None => 0,
+            Some(node) if self.prefix.len() < self.trie.count_depth => node.cached_count(),
+            Some(node) => node.count_values(),
+        }
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<'a, K: ?Sized + ToOwned, T, const N: usize> SubTrie<'a, K, T, N>
+where
+    Trie<K, T, N>: TriePathDecoder<K>,
+{
+    // The entries under the prefix in key order, with full keys.
+    #[must_use]
+    pub fn iter(&self) -> Iter<'a, K, T, N> {
+        self.trie.iter_below_path(self.prefix.clone())
+    }
+}
+
+impl<K: ?Sized, T, const N: usize> SubTrieMut<'_, K, T, N>
+where
+    Trie<K, T, N>: TriePathBuilder<K>,
+{
+    // A read-only view of the same prefix, for `len` and iteration.
+    #[must_use]
+    pub fn as_subtrie(&self) -> SubTrie<'_, K, T, N> {
+        SubTrie {
+            trie: self.trie,
+            node: self.trie.node_at(&self.prefix),
+            prefix: self.prefix.clone(),
+        }
+    }
+
+    #[must_use]
+    pub fn get(&self, key: &K) -> Option<&T> {
+        self.as_subtrie().get(key)
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut T> {
+        let path = self.inside(key)?;
+        self.trie.get_path_mut(&path)
+    }
+
+    pub fn insert(&mut self, key: &K, val: T) -> Option<T> {
+        let path = self.inside(key).expect("key is outside the subtrie prefix");
+        self.trie.insert_path(&path, val)
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<T> {
+        let path = self.inside(key)?;
+        self.trie.delete_path(&path)
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.as_subtrie().len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.as_subtrie().is_empty()
+    }
+
+    // The path of `key` if it starts with the prefix.
+    fn inside(&self, key: &K) -> Option<Vec<usize>> {
+        let path = <Trie<K, T, N> as TriePathBuilder<K>>::build_path(key);
+        path.starts_with(&self.prefix).then_some(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trie::Trie;
+
+    #[test]
+    fn views_under_a_prefix() {
+        let mut trie: Trie<str, usize, 16> = Trie::new();
+        for (i, key) in ["ape", "app", "apple", "bee"].iter().enumerate() {
+            trie.insert(key, i);
+        }
+        let app = trie.subtrie("app");
+        assert_eq!((app.get("apple"), app.get("ape"), app.get("ap"), app.len()), (Some(&2), None, None, 2));
+        assert_eq!(app.iter().map(|(key, _)| key).collect::<Vec<_>>(), vec!["app", "apple"]);
+        assert!(trie.subtrie("c").is_empty());
+
+        let mut sub = trie.subtrie_mut("ap");
+        assert_eq!(sub.insert("apt", 4), None);
+        assert_eq!(sub.remove("bee"), None);
+        assert_eq!(sub.remove("ape"), Some(0));
+        *sub.get_mut("app").unwrap() += 10;
+        assert_eq!((sub.len(), sub.get("app")), (3, Some(&11)));
+        assert_eq!((trie.len(), trie.get("bee")), (4, Some(&3)));
+    }
+}
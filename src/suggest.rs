@@ -0,0 +1,124 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+
+use crate::trie::{Trie, TriePathBuilder, TriePathDecoder};
+
+/// A value that ranks its key among completions, such as a search count. Higher scores are
+/// suggested first.
+pub trait Scored {
+    type Score: Ord;
+
+    fn score(&self) -> Self::Score;
+}
+
+macro_rules! scored_ints {
+    ($($int:ty)*) => {$(
+        impl Scored for $int {
+            type Score = $int;
+
+            fn score(&self) -> $int {
+                *self
+            }
+        }
+    )*};
+}
+
+scored_ints!(u8 u16 u32 u64 u128 usize i8 i16 i32 i64 i128 isize);
+
+impl<K: ?Sized + ToOwned, T, const N: usize> Trie<K, T, N>
+where
+    Trie<K, T, N>: TriePathBuilder<K> + TriePathDecoder<K>,
+{
+    // The `k` best-scored completions of `prefix`, best first, with ties in key order.
+    #[must_use]
+    pub fn suggest(&self, prefix: &K, k: usize) -> Vec<(K::Owned, &T)>
+    where
+        T: Scored,
+    {
+        self.suggest_by(prefix, k, Scored::score)
+    }
+
+    // `suggest` with the score computed by `score`. Only the best `k` entries seen so far are
+    // kept while the subtree is walked, so the rest are never decoded or collected.
+    #[must_use]
+    pub fn suggest_by<S: Ord>(&self, prefix: &K, k: usize, score: impl Fn(&T) -> S) -> Vec<(K::Owned, &T)> {
+        let prefix = <Trie<K, T, N> as TriePathBuilder<K>>::build_path(prefix);
+        let Some(node) = self.node_at(&prefix).filter(|_| k > 0) else {
+            return Vec::new();
+        };
+        // A min-heap of the kept entries, so the worst is the one to evict.
+        let mut best = BinaryHeap::with_capacity(k + 1);
+        let mut seq = 0;
+        node.visit_paths(|path, val| {
+            let candidate = Candidate {
+                score: score(val),
+                seq,
+                path: Vec::new(),
+                val,
+            };
+            seq += 1;
+            if best.len() == k && best.peek().is_some_and(|Reverse(worst)| candidate <= *worst) {
+                return;
+            }
+            best.push(Reverse(Candidate { path: path.to_vec(), ..candidate }));
+            if best.len() > k {
+                best.pop();
+            }
+        });
+        best.into_sorted_vec()
+            .into_iter()
+            .map(|Reverse(candidate)| {
+                let full: Vec<usize> = prefix.iter().chain(&candidate.path).copied().collect();
+                let key = <Trie<K, T, N> as TriePathDecoder<K>>::decode_path(&full);
+                (key.expect("trie holds a path that is not a valid key"), candidate.val)
+            })
+            .collect()
+    }
+}
+
+// Ordered by score, then earlier keys above later ones.
+struct Candidate<'a, S, T> {
+    score: S,
+    seq: usize,
+    path: Vec<usize>,
+    val: &'a T,
+}
+
+impl<S: Ord, T> Ord for Candidate<'_, S, T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.score.cmp(&other.score).then(other.seq.cmp(&self.seq))
+    }
+}
+
+impl<S: Ord, T> PartialOrd for Candidate<'_, S, T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<S: Ord, T> PartialEq for Candidate<'_, S, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<S: Ord, T> Eq for Candidate<'_, S, T> {}
+
+#[cfg(test)]
+mod tests {
+    use crate::trie::Trie;
+
+    #[test]
+    fn top_completions() {
+        let mut searches: Trie<str, u32, 16> = Trie::new();
+        for (key, count) in [("car", 50), ("card", 7), ("care", 50), ("cart", 90), ("cat", 300), ("carp", 1)] {
+            searches.insert(key, count);
+        }
+        let keys = |found: Vec<(String, &u32)>| found.into_iter().map(|(key, _)| key).collect::<Vec<_>>();
+        assert_eq!(keys(searches.suggest("car", 3)), vec!["cart", "car", "care"]);
+        assert_eq!(keys(searches.suggest("ca", 1)), vec!["cat"]);
+        assert_eq!(searches.suggest("car", 10).len(), 5);
+        assert!(searches.suggest("dog", 3).is_empty() && searches.suggest("car", 0).is_empty());
+        assert_eq!(keys(searches.suggest_by("car", 2, |count| std::cmp::Reverse(*count))), vec!["carp", "card"]);
+    }
+}
@@ -0,0 +1,189 @@
+use std::cmp::Ordering;
+use std::iter::Peekable;
+use std::mem;
+
+use crate::frozen::{FrozenIter, FrozenTrie};
+use crate::trie::{Trie, TrieIter, TriePathBuilder};
+
+/// A small mutable trie layered over a large frozen one.
+///
+/// Writes land in the mutable delta, with deletions of frozen entries recorded as tombstones.
+/// Lookups and iteration see the delta first. `compact` folds the delta into a new frozen base.
+pub struct TieredTrie<'a, K: ?Sized, T, const N: usize> {
+    base: FrozenTrie<'a, K, T, N>,
+    delta: Trie<K, Option<T>, N>,
+    len: usize,
+}
+
+impl<'a, K: ?Sized, T: Clone, const N: usize> TieredTrie<'a, K, T, N>
+where
+    Trie<K, T, N>: TriePathBuilder<K>,
+    Trie<K, Option<T>, N>: TriePathBuilder<K>,
+{
+    #[must_use]
+    pub fn new(base: FrozenTrie<'a, K, T, N>) -> TieredTrie<'a, K, T, N> {
+        TieredTrie {
+            len: base.len(),
+            base,
+            delta: Trie::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn get(&self, key: &K) -> Option<&T> {
+        let path = <Trie<K, T, N> as TriePathBuilder<K>>::build_path(key);
+        match self.delta.get_path(&path) {
+            Some(val) => val.as_ref(),
+            None => self.base.get_path(&path),
+        }
+    }
+
+    pub fn insert(&mut self, key: &K, val: T) -> Option<T> {
+        let path = <Trie<K, T, N> as TriePathBuilder<K>>::build_path(key);
+        let retval = match self.delta.insert_path(&path, Some(val)) {
+            Some(old) => old,
+            None => self.base.get_path(&path).cloned(),
+        };
+        if retval.is_none() {
+            self.len += 1;
+        }
+        retval
+    }
+
+    #[deprecated(note = "renamed to `remove`")]
+    pub fn delete(&mut self, key: &K) -> Option<T> {
+        self.remove(key)
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<T> {
+        let path = <Trie<K, T, N> as TriePathBuilder<K>>::build_path(key);
+        let in_base = self.base.get_path(&path);
+        let retval = if let Some(slot) = self.delta.get_path_mut(&path) {
+            let retval = slot.take();
+            if retval.is_some() && in_base.is_none() {
+                self.delta.delete_path(&path);
+            }
+            retval
+        } else {
+            let retval = in_base.cloned();
+            if retval.is_some() {
+                self.delta.insert_path(&path, None);
+            }
+            retval
+        };
+        if retval.is_some() {
+            self.len -= 1;
+        }
+        retval
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Entries written since the last compaction, tombstones included.
+    #[must_use]
+    pub fn delta_len(&self) -> usize {
+        self.delta.len()
+    }
+
+    #[must_use]
+    pub fn base(&self) -> &FrozenTrie<'a, K, T, N> {
+        &self.base
+    }
+
+    #[must_use]
+    pub fn iter(&self) -> TieredIter<'_, T, N> {
+        TieredIter {
+            base: self.base.iter().peekable(),
+            delta: self.delta.iter_paths().peekable(),
+        }
+    }
+
+    pub fn compact(&mut self) {
+        let mut merged: Trie<K, T, N> = Trie::new();
+        for (path, val) in self.base.iter() {
+            merged.insert_path(&path, val.clone());
+        }
+        let delta = mem::replace(&mut self.delta, Trie::new());
+        delta.root.drain_paths(|path, val| {
+            if let Some(val) = val {
+                merged.insert_path(path, val);
+            } else {
+                merged.delete_path(path);
+            }
+        });
+        self.base = merged.freeze();
+        self.len = self.base.len();
+    }
+}
+
+pub struct TieredIter<'b, T, const N: usize> {
+    base: Peekable<FrozenIter<'b, T>>,
+    delta: Peekable<TrieIter<'b, Option<T>, N>>,
+}
+
+impl<'b, T, const N: usize> Iterator for TieredIter<'b, T, N> {
+    type Item = (Vec<usize>, &'b T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let order = match (self.base.peek(), self.delta.peek()) {
+                (None, None) => return None,
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (Some((base_path, _)), Some((delta_path, _))) => base_path.cmp(delta_path),
+            };
+            if order == Ordering::Less {
+                return self.base.next();
+            }
+            if order == Ordering::Equal {
+                self.base.next();
+            }
+            if let Some((path, Some(val))) = self.delta.next() {
+                return Some((path, val));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tiered::TieredTrie;
+    use crate::trie::Trie;
+
+    #[test]
+    fn layered() {
+        let mut base: Trie<str, usize, 16> = Trie::new();
+        base.insert("a", 1);
+        base.insert("b", 2);
+        base.insert("c", 3);
+        let mut trie = TieredTrie::new(base.freeze());
+
+        assert_eq!(trie.insert("b", 20), Some(2));
+        assert_eq!(trie.insert("d", 4), None);
+        assert_eq!(trie.remove("a"), Some(1));
+        assert_eq!(trie.remove("a"), None);
+        assert_eq!(trie.remove("d"), Some(4));
+        assert_eq!(trie.insert("d", 40), None);
+        assert_eq!(trie.len(), 3);
+        assert_eq!(trie.get("a"), None);
+        assert_eq!(trie.get("b"), Some(&20));
+        assert_eq!(trie.get("c"), Some(&3));
+
+        let values: Vec<usize> = trie.iter().map(|(_, val)| *val).collect();
+        assert_eq!(values, vec![20, 3, 40]);
+
+        trie.compact();
+        assert_eq!(trie.delta_len(), 0);
+        assert_eq!(trie.base().len(), 3);
+        let values: Vec<usize> = trie.iter().map(|(_, val)| *val).collect();
+        assert_eq!(values, vec![20, 3, 40]);
+    }
+}
@@ -0,0 +1,83 @@
+use std::ops::Range;
+
+use crate::trie::{ByteKey, Trie};
+
+/// What `tokenize_longest` does with bytes no stored key starts at.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Unmatched {
+    Skip,
+    // One `None` token per unmatched byte.
+    Bytes,
+    // One `None` token per run of consecutive unmatched bytes.
+    Runs,
+}
+
+impl<K: ?Sized + ByteKey, T> Trie<K, T, 16> {
+    // Maximal-munch segmentation: at each position takes the longest stored key that matches,
+    // or handles the byte according to `unmatched`. The empty key never matches.
+    #[must_use]
+    pub fn tokenize_longest<'a, 't>(&'a self, text: &'t [u8], unmatched: Unmatched) -> Tokens<'a, 't, K, T> {
+        Tokens {
+            trie: self,
+            text,
+            pos: 0,
+            unmatched,
+        }
+    }
+
+    fn longest_match(&self, text: &[u8], at: usize) -> Option<(usize, &T)> {
+        self.scan_prefixes(text, at).into_iter().rev().find(|&(len, _)| len > 0)
+    }
+}
+
+pub struct Tokens<'a, 't, K: ?Sized, T> {
+    trie: &'a Trie<K, T, 16>,
+    text: &'t [u8],
+    pos: usize,
+    unmatched: Unmatched,
+}
+
+impl<'a, K: ?Sized + ByteKey, T> Iterator for Tokens<'a, '_, K, T> {
+    type Item = (Range<usize>, Option<&'a T>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pos < self.text.len() {
+            let start = self.pos;
+            if let Some((len, val)) = self.trie.longest_match(self.text, start) {
+                self.pos += len;
+                return Some((start..self.pos, Some(val)));
+            }
+            self.pos += 1;
+            match self.unmatched {
+                Unmatched::Skip => {}
+                Unmatched::Bytes => return Some((start..self.pos, None)),
+                Unmatched::Runs => {
+                    while self.pos < self.text.len() && self.trie.longest_match(self.text, self.pos).is_none() {
+                        self.pos += 1;
+                    }
+                    return Some((start..self.pos, None));
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tokenize::Unmatched;
+    use crate::trie::Trie;
+
+    #[test]
+    fn longest_match() {
+        let mut trie: Trie<str, &str, 16> = Trie::new();
+        for word in ["new", "newyork", "york", "city"] {
+            trie.insert(word, word);
+        }
+        let text = b"newyork!!city";
+        let tokens: Vec<_> = trie.tokenize_longest(text, Unmatched::Runs).collect();
+        assert_eq!(tokens, vec![(0..7, Some(&"newyork")), (7..9, None), (9..13, Some(&"city"))]);
+        assert_eq!(trie.tokenize_longest(text, Unmatched::Bytes).count(), 4);
+        assert_eq!(trie.tokenize_longest(text, Unmatched::Skip).count(), 2);
+    }
+}
@@ -1,5 +1,18 @@
+use std::borrow::Borrow;
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::hint::black_box;
 use std::marker::PhantomData;
+use std::ops::{Add, Bound, RangeBounds, Sub};
+use std::sync::Arc;
+#[cfg(feature = "zeroize")]
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+use crate::bloom::BloomFilter;
+use crate::codec::{read_varint, write_varint};
 use crate::trie_node::TrieNode;
+pub use crate::trie_node::NodeLayout;
 
 pub trait TriePathBuilder<K: ?Sized> {
     fn populate_path(key: &K, path: &mut Vec<usize>);
@@ -10,11 +23,64 @@ pub trait TriePathBuilder<K: ?Sized> {
         Self::populate_path(key, &mut v);
         v
     }
+
+    // The path of `key` one index at a time, so lookups can follow it without collecting it.
+    // Encodings that can produce it lazily should override this.
+    fn path_iter(key: &K) -> impl Iterator<Item = usize> + '_ {
+        Self::build_path(key).into_iter()
+    }
+}
+
+/// The inverse of `TriePathBuilder`: turns a stored path back into the key's owned form,
+/// `K::Owned`, which borrows as `K` (`String` for `str`, `Vec<u8>` for `[u8]`). Every API that
+/// hands out keys rather than paths needs the trie's key type to implement it.
+pub trait TriePathDecoder<K: ?Sized + ToOwned> {
+    fn decode_path(path: &[usize]) -> Result<K::Owned, DecodeError>;
+}
+
+/// Why a path could not be turned back into a key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    // The path ends partway through a key unit, e.g. on a high nibble.
+    Truncated { len: usize },
+    IndexOutOfRange { position: usize, index: usize },
+    InvalidUtf8(std::str::Utf8Error),
+    // The path is not as long as every key of a fixed-width key type, e.g. an integer.
+    WrongLength { len: usize, expected: usize },
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Truncated { len } => write!(f, "path of length {len} ends partway through a key unit"),
+            DecodeError::IndexOutOfRange { position, index } => {
+                write!(f, "path index {index} at position {position} is out of range for the key encoding")
+            }
+            DecodeError::InvalidUtf8(err) => write!(f, "path does not decode to UTF-8: {err}"),
+            DecodeError::WrongLength { len, expected } => {
+                write!(f, "path of length {len} does not match the key length {expected}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DecodeError::InvalidUtf8(err) => Some(err),
+            _ => None,
+        }
+    }
 }
 
 pub struct Trie<K: ?Sized, T, const N: usize> {
-    len: usize,
-    root: TrieNode<T, N>,
+    pub(crate) len: usize,
+    pub(crate) layout: NodeLayout,
+    pub(crate) filter: Option<BloomFilter>,
+    pub(crate) count_depth: usize,
+    pub(crate) root: TrieNode<T, N>,
+    // Set by `take`, which can leave valueless leaves behind until `prune` clears them.
+    pub(crate) hollow: bool,
     _key_type: PhantomData<K>,
 }
 
@@ -31,6 +97,10 @@ impl<T> TriePathBuilder<[u8]> for Trie<[u8], T, 16> {
     fn init_path(key: &[u8]) -> Vec<usize> {
         Vec::with_capacity(2 * key.len())
     }
+
+    fn path_iter(key: &[u8]) -> impl Iterator<Item = usize> + '_ {
+        key.iter().flat_map(|&byte| [usize::from(byte >> 4), usize::from(byte & 0x0F)])
+    }
 }
 
 impl<T> TriePathBuilder<str> for Trie<str, T, 16> {
@@ -46,6 +116,126 @@ impl<T> TriePathBuilder<str> for Trie<str, T, 16> {
     fn init_path(key: &str) -> Vec<usize> {
         Vec::with_capacity(2 * key.len())
     }
+
+    fn path_iter(key: &str) -> impl Iterator<Item = usize> + '_ {
+        key.bytes().flat_map(|byte| [usize::from(byte >> 4), usize::from(byte & 0x0F)])
+    }
+}
+
+pub(crate) fn decode_nibbles(path: &[usize]) -> Result<Vec<u8>, DecodeError> {
+    if path.len() % 2 != 0 {
+        return Err(DecodeError::Truncated { len: path.len() });
+    }
+    if let Some((position, &index)) = path.iter().enumerate().find(|(_, &index)| index > 0x0F) {
+        return Err(DecodeError::IndexOutOfRange { position, index });
+    }
+    Ok(path.chunks(2).map(|pair| (pair[0] << 4 | pair[1]) as u8).collect())
+}
+
+impl<T> TriePathDecoder<[u8]> for Trie<[u8], T, 16> {
+    fn decode_path(path: &[usize]) -> Result<Vec<u8>, DecodeError> {
+        decode_nibbles(path)
+    }
+}
+
+impl<T> TriePathDecoder<str> for Trie<str, T, 16> {
+    fn decode_path(path: &[usize]) -> Result<String, DecodeError> {
+        String::from_utf8(decode_nibbles(path)?).map_err(|err| DecodeError::InvalidUtf8(err.utf8_error()))
+    }
+}
+
+/// A trie over byte strings with one level per byte, half as deep as `Trie<[u8], T, 16>` but
+/// with 256-way nodes. `Trie<str, T, 256>` stores strings the same way.
+pub type ByteTrie<T> = Trie<[u8], T, 256>;
+
+impl<T> TriePathBuilder<[u8]> for Trie<[u8], T, 256> {
+    fn populate_path(key: &[u8], path: &mut Vec<usize>) {
+        path.extend(key.iter().map(|&byte| usize::from(byte)));
+    }
+
+    fn init_path(key: &[u8]) -> Vec<usize> {
+        Vec::with_capacity(key.len())
+    }
+
+    fn path_iter(key: &[u8]) -> impl Iterator<Item = usize> + '_ {
+        key.iter().map(|&byte| usize::from(byte))
+    }
+}
+
+impl<T> TriePathBuilder<str> for Trie<str, T, 256> {
+    fn populate_path(key: &str, path: &mut Vec<usize>) {
+        path.extend(key.bytes().map(usize::from));
+    }
+
+    fn init_path(key: &str) -> Vec<usize> {
+        Vec::with_capacity(key.len())
+    }
+
+    fn path_iter(key: &str) -> impl Iterator<Item = usize> + '_ {
+        key.bytes().map(usize::from)
+    }
+}
+
+pub(crate) fn decode_bytes(path: &[usize]) -> Result<Vec<u8>, DecodeError> {
+    path.iter()
+        .enumerate()
+        .map(|(position, &index)| u8::try_from(index).map_err(|_| DecodeError::IndexOutOfRange { position, index }))
+        .collect()
+}
+
+impl<T> TriePathDecoder<[u8]> for Trie<[u8], T, 256> {
+    fn decode_path(path: &[usize]) -> Result<Vec<u8>, DecodeError> {
+        decode_bytes(path)
+    }
+}
+
+impl<T> TriePathDecoder<str> for Trie<str, T, 256> {
+    fn decode_path(path: &[usize]) -> Result<String, DecodeError> {
+        String::from_utf8(decode_bytes(path)?).map_err(|err| DecodeError::InvalidUtf8(err.utf8_error()))
+    }
+}
+
+/// Key types stored as their bytes, one nibble per level, in a `Trie<K, T, 16>`.
+pub trait ByteKey {
+    fn key_bytes(&self) -> &[u8];
+}
+
+impl ByteKey for [u8] {
+    fn key_bytes(&self) -> &[u8] {
+        self
+    }
+}
+
+impl ByteKey for str {
+    fn key_bytes(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl<K: ?Sized + ByteKey, T> Trie<K, T, 16> {
+    // Every stored key that occurs in `text` starting at byte `at`, as (length in bytes, value),
+    // shortest first. The building block for dictionary segmenters.
+    #[must_use]
+    pub fn scan_prefixes(&self, text: &[u8], at: usize) -> Vec<(usize, &T)> {
+        let mut matches = Vec::new();
+        let mut current_node = &self.root;
+        if let Some(val) = current_node.value() {
+            matches.push((0, val));
+        }
+        for (i, &byte) in text.get(at..).unwrap_or_default().iter().enumerate() {
+            let next = current_node
+                .child(usize::from(byte >> 4))
+                .and_then(|node| node.child(usize::from(byte & 0x0F)));
+            match next {
+                Some(node) => current_node = node,
+                None => break,
+            }
+            if let Some(val) = current_node.value() {
+                matches.push((i + 1, val));
+            }
+        }
+        matches
+    }
 }
 
 impl<K: ?Sized, T, const N: usize> Trie<K, T, N>
@@ -54,90 +244,233 @@ where
 {
     #[must_use]
     pub fn new() -> Trie<K, T, N> {
-        Trie {
-            len: 0,
-            root: TrieNode::new(),
-            _key_type: PhantomData,
+        Self::with_layout(NodeLayout::default_for(N))
+    }
+
+    #[must_use]
+    pub fn with_layout(layout: NodeLayout) -> Trie<K, T, N> {
+        Self::empty(layout)
+    }
+
+    #[must_use]
+    pub fn layout(&self) -> NodeLayout {
+        self.root.layout()
+    }
+
+    // Deleted keys stay in the filter until `rebuild_filter`, which only costs extra false positives.
+    pub fn enable_filter(&mut self, expected_keys: usize, bits_per_key: usize) {
+        self.filter = Some(BloomFilter::new(expected_keys.max(self.len), bits_per_key));
+        self.rebuild_filter();
+    }
+
+    pub fn disable_filter(&mut self) {
+        self.filter = None;
+    }
+
+    pub fn rebuild_filter(&mut self) {
+        if let Some(filter) = self.filter.as_mut() {
+            filter.clear();
+            self.root.visit_paths(|path, _| filter.insert_path(path));
+        }
+    }
+
+    #[must_use]
+    pub fn filter(&self) -> Option<&BloomFilter> {
+        self.filter.as_ref()
+    }
+
+    // A standalone filter over the current keys, sized for `len`, e.g. for another process to
+    // pre-check membership with. Independent of `enable_filter`.
+    #[must_use]
+    pub fn to_filter(&self, bits_per_key: usize) -> BloomFilter {
+        let mut filter = BloomFilter::new(self.len, bits_per_key);
+        self.root.visit_paths(|path, _| filter.insert_path(path));
+        filter
+    }
+
+    // Keeps exact value counts on the nodes of the top `depth` levels (the root is level 0), so
+    // `count_prefix` is cheap for prefixes shorter than `depth` path indices. Every insert or
+    // delete that changes `len` then pays up to `depth` extra node visits. `usize::MAX` keeps a
+    // count on every node, making `count_prefix` O(prefix length) for any prefix.
+    pub fn enable_prefix_counts(&mut self, depth: usize) {
+        self.count_depth = depth;
+        self.root.refresh_counts(depth);
+    }
+
+    pub fn disable_prefix_counts(&mut self) {
+        self.count_depth = 0;
+    }
+
+    #[must_use]
+    pub fn prefix_count_depth(&self) -> usize {
+        self.count_depth
+    }
+
+    // Number of keys starting with `prefix`; falls back to counting the subtree below the
+    // cached levels.
+    #[must_use]
+    pub fn count_prefix(&self, prefix: &K) -> usize {
+        let path = <Trie<K, T, N> as TriePathBuilder<K>>::build_path(prefix);
+        let Some(current_node) = self.node_at(&path) else {
+            return 0;
+        };
+        if path.len() < self.count_depth {
+            current_node.cached_count()
+        } else {
+            current_node.count_values()
+        }
+    }
+
+    // Like `count_prefix`, but gives up after visiting `max_nodes` nodes, returning a lower bound
+    // and whether it is exact, for "1000+" style displays under huge prefixes.
+    #[must_use]
+    pub fn approx_count_prefix(&self, prefix: &K, max_nodes: usize) -> (usize, bool) {
+        let path = <Trie<K, T, N> as TriePathBuilder<K>>::build_path(prefix);
+        let Some(current_node) = self.node_at(&path) else {
+            return (0, true);
+        };
+        if path.len() < self.count_depth {
+            (current_node.cached_count(), true)
+        } else {
+            current_node.count_values_within(max_nodes)
+        }
+    }
+
+    // Eager prefix query: the values of every key starting with `prefix`, in key order.
+    #[must_use]
+    pub fn values_with_prefix(&self, prefix: &K) -> Vec<&T> {
+        let mut values = Vec::new();
+        if let Some(node) = self.node_at(&<Trie<K, T, N> as TriePathBuilder<K>>::build_path(prefix)) {
+            node.visit_paths(|_, val| values.push(val));
         }
+        values
     }
 
+    // `get`, `get_mut`, `insert` and `remove` follow `path_iter` without building the path,
+    // unless a Bloom filter or prefix counts need it as a slice.
     #[must_use]
     pub fn get(&self, key: &K) -> Option<&T> {
-        let mut current_node = &self.root;
-        let mut path = <Trie<K, T, N> as TriePathBuilder<K>>::build_path(key);
-        for child_index in path {
-            if let Some(node) = current_node.child(child_index) {
-                current_node = node;
-            } else {
-                return None;
-            }
+        if self.filter.is_some() {
+            return self.get_path(&<Trie<K, T, N> as TriePathBuilder<K>>::build_path(key));
         }
-        current_node.value()
+        self.node_along(<Trie<K, T, N> as TriePathBuilder<K>>::path_iter(key))?.value()
+    }
+
+    // The value stored under the longest prefix of `key` that holds one, with the prefix length
+    // in path indices.
+    #[must_use]
+    pub fn longest_prefix(&self, key: &K) -> Option<(usize, &T)> {
+        self.longest_prefix_path(&<Trie<K, T, N> as TriePathBuilder<K>>::build_path(key))
+    }
+
+    // Every value stored under a prefix of `key`, key included, shortest first, with the prefix
+    // lengths in path indices as for `longest_prefix`. The key is followed in one lazy walk.
+    #[must_use]
+    pub fn common_prefix_search<'k>(&self, key: &'k K) -> CommonPrefixes<'_, T, N, impl Iterator<Item = usize> + 'k> {
+        CommonPrefixes {
+            node: Some(&self.root),
+            path: <Trie<K, T, N> as TriePathBuilder<K>>::path_iter(key),
+            depth: 0,
+        }
+    }
+
+    // For secret keys: always walks the full key depth and probes every child slot on the way, so
+    // timing does not reveal how much of the key matched. Skips the Bloom filter. Best effort only,
+    // since neither the compiler nor the Packed and Sorted layouts promise data-independent timing.
+    #[must_use]
+    pub fn get_uniform(&self, key: &K) -> Option<&T> {
+        self.get_path_uniform(&<Trie<K, T, N> as TriePathBuilder<K>>::build_path(key))
     }
 
+    #[must_use]
+    pub fn get_cloned(&self, key: &K) -> Option<T>
+    where
+        T: Clone,
+    {
+        self.get(key).cloned()
+    }
 
     #[must_use]
     pub fn get_mut(&mut self, key: &K) -> Option<&mut T> {
+        if self.filter.is_some() {
+            return self.get_path_mut(&<Trie<K, T, N> as TriePathBuilder<K>>::build_path(key));
+        }
         let mut current_node = &mut self.root;
-        let mut path = <Trie<K, T, N> as TriePathBuilder<K>>::build_path(key);
-        for child_index in path {
-            if let Some(node) = current_node.child_mut(child_index) {
-                current_node = node;
-            } else {
-                return None;
-            }
+        for child_index in <Trie<K, T, N> as TriePathBuilder<K>>::path_iter(key) {
+            current_node = (child_index < N).then(|| current_node.child_mut(child_index)).flatten()?;
         }
         current_node.value_mut()
     }
 
-    #[must_use]
+    #[deprecated(note = "renamed to `remove`")]
     pub fn delete(&mut self, key: &K) -> Option<T> {
-        let mut current_node = &mut self.root;
-        let mut path = <Trie<K, T, N> as TriePathBuilder<K>>::build_path(key);
-        let mut branch_base = None;
-        for (i, &child_index) in path.iter().enumerate() {
-            if current_node.value().is_some() || current_node.has_multiple_children() || branch_base.is_none() {
-                branch_base = Some(i);
-            }
-            if let Some(node) = current_node.child_mut(child_index) {
-                current_node = node;
-            } else {
-                return None;
-            }
-        }
-        if current_node.has_child() {
-            branch_base = None;
+        self.remove(key)
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<T> {
+        if self.filter.is_some() || self.count_depth > 0 {
+            return self.delete_path(&<Trie<K, T, N> as TriePathBuilder<K>>::build_path(key));
         }
-        let retval = current_node.value_take();
+        self.remove_along(|| <Trie<K, T, N> as TriePathBuilder<K>>::path_iter(key))
+    }
 
-        // Cleanup
-        if retval.is_some() {
-            if let Some(path_index) = branch_base {
-                current_node = &mut self.root;
-                for &child_index in path.iter().take(path_index) {
-                    current_node = current_node.child_mut(child_index).unwrap();
-                }
-                current_node.child_mut(path[path_index]).take();
-            }
-            self.len -= 1;
+    // `remove`, also handing back the key in its owned form, as `HashMap::remove_entry` does.
+    pub fn remove_entry(&mut self, key: &K) -> Option<(K::Owned, T)>
+    where
+        K: ToOwned,
+    {
+        self.remove(key).map(|val| (key.to_owned(), val))
+    }
+
+    // Removes the value but keeps the nodes on its path, so putting a value back under the same
+    // key (e.g. after checking it out for an update) allocates nothing. Call `prune` to drop the
+    // nodes of keys that were never put back.
+    #[must_use]
+    pub fn take(&mut self, key: &K) -> Option<T> {
+        let path = <Trie<K, T, N> as TriePathBuilder<K>>::build_path(key);
+        if !valid_path::<N>(&path) || self.filter_excludes(&path) {
+            return None;
         }
-        retval
+        let mut current_node = &mut self.root;
+        for &child_index in &path {
+            current_node = current_node.child_mut(child_index)?;
+        }
+        let val = current_node.value_take()?;
+        self.len = self.len.checked_sub(1).expect("trie len underflow");
+        self.adjust_prefix_counts(&path, false);
+        self.hollow = true;
+        Some(val)
     }
 
     pub fn insert(&mut self, key: &K, val: T) -> Option<T> {
-        let mut current_node = &mut self.root;
-        let mut path = <Trie<K, T, N> as TriePathBuilder<K>>::build_path(key);
-        for child_index in path {
-            if current_node.child(child_index).is_some() {
-                current_node = current_node.child_mut(child_index).unwrap();
-            } else {
-                current_node = current_node.child_set(child_index, TrieNode::new());
-            }
+        if self.filter.is_some() || self.count_depth > 0 {
+            return self.insert_path(&<Trie<K, T, N> as TriePathBuilder<K>>::build_path(key), val);
         }
-        if current_node.value().is_none() {
-            self.len += 1;
+        self.insert_along(<Trie<K, T, N> as TriePathBuilder<K>>::path_iter(key), val)
+    }
+
+    // Replaces the value of an existing key in place, following `path_iter` without building the
+    // path. A missing key is inserted as by `insert`.
+    pub fn swap(&mut self, key: &K, new_val: T) -> Option<T> {
+        let mut current_node = Some(&mut self.root);
+        for child_index in <Trie<K, T, N> as TriePathBuilder<K>>::path_iter(key) {
+            current_node = current_node.and_then(|node| (child_index < N).then(|| node.child_mut(child_index)).flatten());
         }
-        current_node.value_replace(val)
+        if let Some(val) = current_node.and_then(TrieNode::value_mut) {
+            return Some(std::mem::replace(val, new_val));
+        }
+        self.insert(key, new_val)
+    }
+
+    // The value at `key`, first inserting `f()` if there is none, in a single walk down the key:
+    // a `get` and `insert` pair for caches without walking twice on a miss.
+    pub fn get_or_insert_with(&mut self, key: &K, f: impl FnOnce() -> T) -> &mut T {
+        if let Some(filter) = self.filter.as_mut() {
+            let path = <Trie<K, T, N> as TriePathBuilder<K>>::build_path(key);
+            filter.insert_path(&path);
+            return self.value_along(path.into_iter(), f);
+        }
+        self.value_along(<Trie<K, T, N> as TriePathBuilder<K>>::path_iter(key), f)
     }
 
     pub fn len(&self) -> usize {
@@ -147,52 +480,2496 @@ where
     pub fn is_empty(&self) -> bool {
         self.len == 0
     }
-}
 
-impl<K, T, const N: usize> Default for Trie<K, T, N>
-where
-    Trie<K, T, N>: TriePathBuilder<K>,
-{
-    fn default() -> Self {
-        Self::new()
+    // Copies the entries under `prefix`, keeping their full keys, into an independent trie.
+    #[must_use]
+    pub fn clone_prefix(&self, prefix: &K) -> Trie<K, T, N>
+    where
+        T: Clone,
+    {
+        let path = <Trie<K, T, N> as TriePathBuilder<K>>::build_path(prefix);
+        let mut trie = Trie::with_layout(self.layout);
+        let Some(source) = self.node_at(&path) else {
+            return trie;
+        };
+        let subtree = source.clone_subtree();
+        trie.len = subtree.count_values();
+        trie.hollow = self.hollow;
+        let mut target = &mut trie.root;
+        for (i, &child_index) in path.iter().enumerate() {
+            if i + 1 == path.len() {
+                target.child_set(child_index, subtree);
+                return trie;
+            }
+            target = target.child_set(child_index, TrieNode::with_layout(self.layout));
+        }
+        trie.root = subtree;
+        trie
+    }
+
+    // The entries under `prefix`, with paths relative to it, in key order.
+    #[must_use]
+    pub fn iter_prefix_relative(&self, prefix: &K) -> TrieIter<'_, T, N> {
+        match self.node_at(&<Trie<K, T, N> as TriePathBuilder<K>>::build_path(prefix)) {
+            Some(node) => TrieIter::new(node),
+            None => TrieIter::empty(),
+        }
+    }
+
+    // Deletes every key starting with `prefix`, detaching the subtree in one step.
+    pub fn remove_prefix(&mut self, prefix: &K) -> RemovalReport {
+        let path = <Trie<K, T, N> as TriePathBuilder<K>>::build_path(prefix);
+        self.remove_prefix_path(&path, |_, _| {})
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use crate::trie::Trie;
+impl<K: ?Sized, T, const N: usize> Trie<K, T, N> {
+    // For tries whose key type has no path builder at this arity, which can still be used
+    // through the raw path API.
+    pub(crate) fn empty(layout: NodeLayout) -> Trie<K, T, N> {
+        Trie {
+            len: 0,
+            layout,
+            filter: None,
+            count_depth: 0,
+            root: TrieNode::with_layout(layout),
+            hollow: false,
+            _key_type: PhantomData,
+        }
+    }
 
-    #[test]
-    fn it_works() {
-        let mut trie: Trie<[u8], usize, 16> = Trie::new();
+    // The raw paths and values in path order, without decoding the keys.
+    #[must_use]
+    pub fn iter_paths(&self) -> TrieIter<'_, T, N> {
+        TrieIter::new(&self.root)
+    }
 
-        assert_eq!(trie.insert(&[1, 3, 7, 2], 3), None);
-        assert_eq!(trie.get(&[]), None);
-        assert_eq!(trie.get(&[1]), None);
-        assert_eq!(trie.get(&[1, 3]), None);
-        assert_eq!(trie.get(&[1, 3, 7]), None);
-        assert_eq!(trie.get(&[1, 3, 7, 2]), Some(&3));
-        assert_eq!(trie.insert(&[], 7), None);
-        assert_eq!(trie.get(&[]), Some(&7));
-        assert_eq!(trie.get(&[1]), None);
-        assert_eq!(trie.get(&[1, 3]), None);
-        assert_eq!(trie.get(&[1, 3, 7]), None);
-        assert_eq!(trie.get(&[1, 3, 7, 2]), Some(&3));
-        assert_eq!(trie.insert(&[1, 3], 5), None);
-        assert_eq!(trie.get(&[]), Some(&7));
-        assert_eq!(trie.get(&[1]), None);
-        assert_eq!(trie.get(&[1, 3]), Some(&5));
-        assert_eq!(trie.get(&[1, 3, 7]), None);
-        assert_eq!(trie.get(&[1, 3, 7, 2]), Some(&3));
-        assert_eq!(trie.len(), 3);
-        assert_eq!(trie.insert(&[1, 3], 6), Some(5));
-        assert_eq!(trie.len(), 3);
-        assert_eq!(trie.delete(&[1, 3]), Some(6));
-        assert_eq!(trie.len(), 2);
-        assert_eq!(trie.get(&[1, 3, 7, 2]), Some(&3));
-        assert_eq!(trie.delete(&[1, 3, 7, 2]), Some(3));
-        assert_eq!(trie.len(), 1);
-        assert_eq!(trie.delete(&[]), Some(7));
-        assert_eq!(trie.len(), 0);
+    // Like `iter_paths`, with the values borrowed mutably. The structure cannot change meanwhile,
+    // so values can be updated in place but not added or removed.
+    #[must_use]
+    pub fn iter_paths_mut(&mut self) -> TrieIterMut<'_, T, N> {
+        let (pending, children) = self.root.parts_mut();
+        TrieIterMut {
+            stack: vec![children.into_iter()],
+            path: Vec::new(),
+            pending,
+        }
+    }
+
+    // The smallest path holding a value, found by always descending the smallest child. A
+    // trie left hollow by `take` may have valueless leaves to backtrack from, so it is scanned.
+    #[must_use]
+    pub fn first_path(&self) -> Option<(Vec<usize>, &T)> {
+        if self.hollow {
+            return self.iter_paths().next();
+        }
+        let mut path = Vec::new();
+        let mut current_node = &self.root;
+        loop {
+            if let Some(val) = current_node.value() {
+                return Some((path, val));
+            }
+            let child_index = current_node.next_index(None)?;
+            path.push(child_index);
+            current_node = current_node.child(child_index).unwrap();
+        }
+    }
+
+    // The largest path holding a value: the leaf at the end of the largest children.
+    #[must_use]
+    pub fn last_path(&self) -> Option<(Vec<usize>, &T)> {
+        if self.hollow {
+            return self.iter_paths().last();
+        }
+        last_below(&self.root, Vec::new())
+    }
+
+    // The smallest path holding a value that is strictly greater than `path`.
+    #[must_use]
+    pub fn next_path_after(&self, path: &[usize]) -> Option<(Vec<usize>, &T)> {
+        TrieIter::starting_at(&self.root, path, false).next()
+    }
+
+    // The largest path holding a value that is strictly less than `path`. Every entry sharing
+    // more of `path` sorts after those sharing less, so the nodes along it are tried deepest
+    // first: the last entry under a smaller sibling, then the node's own value.
+    #[must_use]
+    pub fn prev_path_before(&self, path: &[usize]) -> Option<(Vec<usize>, &T)> {
+        if self.hollow {
+            return self.iter_paths().take_while(|(entry, _)| entry.as_slice() < path).last();
+        }
+        let mut along = vec![&self.root];
+        for &child_index in path {
+            match (child_index < N).then(|| along[along.len() - 1].child(child_index)).flatten() {
+                Some(node) => along.push(node),
+                None => break,
+            }
+        }
+        for depth in (0..along.len().min(path.len())).rev() {
+            let node = along[depth];
+            let before = (path[depth] < N).then_some(path[depth]);
+            if let Some(child_index) = node.prev_index(before) {
+                let mut prefix = path[..depth].to_vec();
+                prefix.push(child_index);
+                return last_below(node.child(child_index).unwrap(), prefix);
+            }
+            if let Some(val) = node.value() {
+                return Some((path[..depth].to_vec(), val));
+            }
+        }
+        None
+    }
+
+    // The longest path shared by every entry under `prefix`, which it starts with, found by
+    // descending while nodes have one child and no value; `None` if nothing is stored there. A
+    // hollow trie may hold valueless branches, so the entries are compared instead.
+    #[must_use]
+    pub fn longest_common_prefix_path(&self, prefix: &[usize]) -> Option<Vec<usize>> {
+        let mut current_node = self.node_along(prefix.iter().copied())?;
+        let mut path = prefix.to_vec();
+        if self.hollow {
+            let mut common: Option<Vec<usize>> = None;
+            current_node.visit_paths(|rest, _| {
+                let shared = common.as_ref().map_or(rest.len(), |common| common.iter().zip(rest).take_while(|(a, b)| a == b).count());
+                common = Some(rest[..shared].to_vec());
+            });
+            path.extend(common?);
+            return Some(path);
+        }
+        while current_node.value().is_none() && !current_node.has_multiple_children() {
+            let child_index = current_node.next_index(None)?;
+            path.push(child_index);
+            current_node = current_node.child(child_index).unwrap();
+        }
+        Some(path)
+    }
+
+    #[must_use]
+    pub fn values(&self) -> Values<'_, T, N> {
+        Values { paths: self.iter_paths() }
+    }
+
+    #[must_use]
+    pub fn values_mut(&mut self) -> ValuesMut<'_, T, N> {
+        ValuesMut {
+            paths: self.iter_paths_mut(),
+        }
+    }
+
+    // Continues an iteration from a `Position` taken from an earlier iterator, possibly over
+    // another version of this trie.
+    #[must_use]
+    pub fn resume_at(&self, position: &Position) -> TrieIter<'_, T, N> {
+        TrieIter::resume(&self.root, position.clone())
+    }
+
+    // Rebuilds the nodes in path order. After heavy churn the nodes end up scattered across the
+    // heap; fresh allocations made in traversal order are usually laid out close to sequentially,
+    // which speeds up iteration and other full walks. Keys, values and layout are unchanged.
+    pub fn optimize_layout(&mut self) {
+        let layout = self.root.layout();
+        let root = std::mem::replace(&mut self.root, TrieNode::with_layout(layout));
+        self.root.fill_from(root);
+    }
+
+    // Hashes the entries in iteration order, which is always path order, so equal contents give
+    // equal hashes whatever order they were inserted in.
+    #[must_use]
+    pub fn content_hash<H: Hasher + Default>(&self) -> u64
+    where
+        T: Hash,
+    {
+        let mut hasher = H::default();
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    // Combines per-entry hashes with wrapping addition, so it can be maintained incrementally or
+    // matched against entries coming from an unordered source.
+    #[must_use]
+    pub fn unordered_content_hash<H: Hasher + Default>(&self) -> u64
+    where
+        T: Hash,
+    {
+        let mut sum = 0u64;
+        self.root.visit_paths(|path, val| sum = sum.wrapping_add(entry_hash::<H, T>(path, val)));
+        sum
+    }
+
+    // Recomputes `len` and the cached prefix counts by counting value-bearing nodes directly,
+    // without going through the iterators, and returns the corrected count.
+    pub fn recount(&mut self) -> usize {
+        self.len = if self.count_depth > 0 {
+            self.root.refresh_counts(self.count_depth)
+        } else {
+            self.root.count_values()
+        };
+        self.len
+    }
+
+    // Removes every entry but keeps the settings: the layout, the prefix count depth and the
+    // filter, which keeps its size and forgets its keys.
+    pub fn clear(&mut self) {
+        self.root = TrieNode::with_layout(self.layout);
+        self.len = 0;
+        self.hollow = false;
+        if let Some(filter) = self.filter.as_mut() {
+            filter.clear();
+        }
+    }
+
+    // Keeps only the entries for which `keep` returns true, in one pass that also frees the nodes
+    // of the dropped keys, like `remove` does. Returns how many entries were dropped.
+    pub fn retain_paths(&mut self, mut keep: impl FnMut(&[usize], &mut T) -> bool) -> usize {
+        let removed = self.root.retain_paths(&mut Vec::new(), &mut keep);
+        self.len = self.len.checked_sub(removed).expect("trie len underflow");
+        self.hollow = false;
+        if self.count_depth > 0 {
+            self.root.refresh_counts(self.count_depth);
+        }
+        removed
+    }
+
+    // Drops the nodes that lead to no value, left behind by `take`, and returns how many.
+    pub fn prune(&mut self) -> usize {
+        self.hollow = false;
+        self.root.prune_hollow()
+    }
+
+    // Compares only which keys are present, so the value types may differ.
+    #[must_use]
+    pub fn keys_eq<U>(&self, other: &Trie<K, U, N>) -> bool {
+        if self.hollow || other.hollow {
+            return self.len == other.len && self.iter_paths().map(|(path, _)| path).eq(other.iter_paths().map(|(path, _)| path));
+        }
+        self.len == other.len && self.root.keys_eq(&other.root)
+    }
+
+    // Orders the tries by their sorted key sequences, as comparing two `iter` outputs would.
+    #[must_use]
+    pub fn keys_cmp<U>(&self, other: &Trie<K, U, N>) -> Ordering {
+        // The structural comparison takes a child as proof of a key below it.
+        if self.hollow || other.hollow {
+            return self.iter_paths().map(|(path, _)| path).cmp(other.iter_paths().map(|(path, _)| path));
+        }
+        self.root.keys_cmp(&other.root).into_ordering()
+    }
+
+    pub(crate) fn filter_excludes(&self, path: &[usize]) -> bool {
+        self.filter.as_ref().is_some_and(|filter| !filter.contains_path(path))
+    }
+
+    // The raw path API bypasses key encoding, e.g. for paths produced by an external tokenizer.
+    // Every index must be below `N`: lookups and deletes treat other paths as absent, and
+    // `insert_path` panics on them. Paths yielded by `iter` can be passed straight back in.
+    #[must_use]
+    pub fn get_path(&self, path: &[usize]) -> Option<&T> {
+        if !valid_path::<N>(path) || self.filter_excludes(path) {
+            return None;
+        }
+        let mut current_node = &self.root;
+        for &child_index in path {
+            if let Some(node) = current_node.child(child_index) {
+                current_node = node;
+            } else {
+                return None;
+            }
+        }
+        current_node.value()
+    }
+
+    fn get_path_uniform(&self, path: &[usize]) -> Option<&T> {
+        let mut current_node = &self.root;
+        let mut matched = 1;
+        for &child_index in path {
+            // On a miss the walk stays on the deepest matched node and keeps probing it as dummy work.
+            let mut next_node = current_node;
+            let mut found = 0;
+            for index in 0..N {
+                let child = current_node.child(index);
+                let hit = black_box(usize::from(child.is_some()) & ct_eq(index, child_index));
+                next_node = [next_node, child.unwrap_or(next_node)][hit];
+                found |= hit;
+            }
+            matched &= found;
+            current_node = next_node;
+        }
+        [None, current_node.value()][black_box(matched)]
+    }
+
+    pub(crate) fn node_at(&self, path: &[usize]) -> Option<&TrieNode<T, N>> {
+        let mut current_node = &self.root;
+        for &child_index in path {
+            current_node = current_node.child(child_index)?;
+        }
+        Some(current_node)
+    }
+
+    // Like `node_at`, treating indices from `N` up as absent.
+    fn node_along(&self, path: impl Iterator<Item = usize>) -> Option<&TrieNode<T, N>> {
+        let mut current_node = &self.root;
+        for child_index in path {
+            current_node = (child_index < N).then(|| current_node.child(child_index)).flatten()?;
+        }
+        Some(current_node)
+    }
+
+    #[must_use]
+    pub fn get_path_mut(&mut self, path: &[usize]) -> Option<&mut T> {
+        if !valid_path::<N>(path) || self.filter_excludes(path) {
+            return None;
+        }
+        let mut current_node = &mut self.root;
+        for &child_index in path {
+            if let Some(node) = current_node.child_mut(child_index) {
+                current_node = node;
+            } else {
+                return None;
+            }
+        }
+        current_node.value_mut()
+    }
+
+    // The deepest value on the way down `path`, with the length of the path prefix it sits at:
+    // the lookup behind a routing table, which wants the most specific route covering an
+    // address. The walk stops at the first index of `N` or above.
+    #[must_use]
+    pub fn longest_prefix_path(&self, path: &[usize]) -> Option<(usize, &T)> {
+        let mut current_node = &self.root;
+        let mut longest = current_node.value().map(|val| (0, val));
+        for (depth, &child_index) in path.iter().enumerate() {
+            match (child_index < N).then(|| current_node.child(child_index)).flatten() {
+                Some(node) => current_node = node,
+                None => break,
+            }
+            if let Some(val) = current_node.value() {
+                longest = Some((depth + 1, val));
+            }
+        }
+        longest
+    }
+
+    #[must_use]
+    pub fn delete_path(&mut self, path: &[usize]) -> Option<T> {
+        if !valid_path::<N>(path) || self.filter_excludes(path) {
+            return None;
+        }
+        let retval = self.remove_along(|| path.iter().copied());
+        if retval.is_some() {
+            self.adjust_prefix_counts(path, false);
+        }
+        retval
+    }
+
+    // Takes the value at the end of `path()` and prunes the branch left without values. `path` is
+    // called a second time for the pruning walk, so a streamed key is never collected. Leaves the
+    // prefix counts to the caller.
+    fn remove_along<I: Iterator<Item = usize>>(&mut self, path: impl Fn() -> I) -> Option<T> {
+        let mut current_node = &mut self.root;
+        let mut branch_base = None;
+        for (i, child_index) in path().enumerate() {
+            if current_node.value().is_some() || current_node.has_multiple_children() || branch_base.is_none() {
+                branch_base = Some(i);
+            }
+            current_node = (child_index < N).then(|| current_node.child_mut(child_index)).flatten()?;
+        }
+        if current_node.has_child() {
+            branch_base = None;
+        }
+        let retval = current_node.value_take();
+
+        // Cleanup
+        if retval.is_some() {
+            if let Some(path_index) = branch_base {
+                let mut indices = path();
+                current_node = &mut self.root;
+                for child_index in indices.by_ref().take(path_index) {
+                    current_node = current_node.child_mut(child_index).unwrap();
+                }
+                current_node.child_take(indices.next().unwrap());
+            }
+            self.len = self.len.checked_sub(1).expect("trie len underflow");
+        }
+        retval
+    }
+
+    pub fn insert_path(&mut self, path: &[usize], val: T) -> Option<T> {
+        assert!(valid_path::<N>(path), "path index out of range for a trie of arity {N}");
+        if let Some(filter) = self.filter.as_mut() {
+            filter.insert_path(path);
+        }
+        let retval = self.insert_along(path.iter().copied(), val);
+        if retval.is_none() {
+            self.adjust_prefix_counts(path, true);
+        }
+        retval
+    }
+
+    // Stores `val` at the end of `path`, creating the missing nodes. Leaves the filter and prefix
+    // counts to the caller.
+    fn insert_along(&mut self, path: impl Iterator<Item = usize>, val: T) -> Option<T> {
+        let current_node = descend_creating(&mut self.root, self.layout, path);
+        if current_node.value().is_none() {
+            self.len = self.len.checked_add(1).expect("trie len overflow");
+            current_node.value_replace(val);
+            return None;
+        }
+        current_node.value_replace(val)
+    }
+
+    // The value at the end of `path`, first storing `default()` there if there is none. The
+    // cached counts passed on the way down are held until the end and bumped there if a value was
+    // created, so the prefix counts are kept without a second walk. Leaves the filter to the caller.
+    fn value_along(&mut self, path: impl Iterator<Item = usize>, default: impl FnOnce() -> T) -> &mut T {
+        // Not sized by `count_depth`, which may be `usize::MAX`.
+        let mut counts = Vec::new();
+        let mut current_node = &mut self.root;
+        for child_index in path {
+            assert!(child_index < N, "path index out of range for a trie of arity {N}");
+            if current_node.child(child_index).is_none() {
+                current_node.child_set(child_index, TrieNode::with_layout(self.layout));
+            }
+            let (child, count) = current_node.child_mut_with_count(child_index);
+            if counts.len() < self.count_depth {
+                counts.push(count);
+            }
+            current_node = child.unwrap();
+        }
+        if current_node.value().is_none() {
+            // Before any count changes, so a panicking `default` leaves them as they were. The
+            // nodes just created for the path would then hold no value, so the trie counts as
+            // hollow until the value is in place.
+            let was_hollow = std::mem::replace(&mut self.hollow, true);
+            let val = default();
+            self.hollow = was_hollow;
+            self.len = self.len.checked_add(1).expect("trie len overflow");
+            for count in counts.iter_mut() {
+                **count += 1;
+            }
+            // The node holding the value is counted too when it sits above the count depth.
+            if counts.len() < self.count_depth {
+                current_node.set_cached_count(current_node.cached_count() + 1);
+            }
+            current_node.value_replace(val);
+        }
+        current_node.value_mut().unwrap()
+    }
+
+    // Applies `f` to the value at `path`, first inserting `default()` if there is none, in a single
+    // descent.
+    pub(crate) fn upsert_path<R>(&mut self, path: &[usize], default: impl FnOnce() -> T, f: impl FnOnce(&mut T) -> R) -> R {
+        if let Some(filter) = self.filter.as_mut() {
+            filter.insert_path(path);
+        }
+        f(self.value_along(path.iter().copied(), default))
+    }
+
+    // Detaches every entry whose path starts with `prefix`, handing each to `f` with its full path,
+    // and prunes the ancestors left without values. Reports what the detached subtree held.
+    pub(crate) fn remove_prefix_path(&mut self, prefix: &[usize], mut f: impl FnMut(&[usize], T)) -> RemovalReport {
+        let mut current_node = &mut self.root;
+        let mut branch_base = 0;
+        for (i, &child_index) in prefix.iter().enumerate() {
+            if current_node.value().is_some() || current_node.has_multiple_children() {
+                branch_base = i;
+            }
+            match current_node.child_mut(child_index) {
+                Some(node) => current_node = node,
+                None => return RemovalReport::default(),
+            }
+        }
+        let subtree = if prefix.is_empty() {
+            std::mem::replace(&mut self.root, TrieNode::with_layout(self.layout))
+        } else {
+            current_node = &mut self.root;
+            for &child_index in &prefix[..branch_base] {
+                current_node = current_node.child_mut(child_index).unwrap();
+            }
+            current_node.child_take(prefix[branch_base]).unwrap()
+        };
+
+        let (_, nodes_freed, bytes_freed_estimate) = subtree.footprint();
+        let base = if prefix.is_empty() { 0 } else { branch_base + 1 };
+        let mut full_path = prefix[..base].to_vec();
+        let mut removed = 0;
+        subtree.drain_paths(|path, val| {
+            full_path.truncate(base);
+            full_path.extend_from_slice(path);
+            f(&full_path, val);
+            removed += 1;
+        });
+        self.len = self.len.checked_sub(removed).expect("trie len underflow");
+        let mut current_node = &mut self.root;
+        for &child_index in prefix.iter().take(self.count_depth.min(base)) {
+            current_node.set_cached_count(current_node.cached_count() - removed);
+            match current_node.child_mut(child_index) {
+                Some(node) => current_node = node,
+                None => break,
+            }
+        }
+        RemovalReport {
+            values_removed: removed,
+            nodes_freed,
+            bytes_freed_estimate,
+        }
+    }
+
+    // Walks the cached levels along `path`, stopping early where delete cleanup already pruned nodes.
+    fn adjust_prefix_counts(&mut self, path: &[usize], added: bool) {
+        let mut current_node = &mut self.root;
+        for depth in 0..self.count_depth {
+            let count = current_node.cached_count();
+            current_node.set_cached_count(if added { count + 1 } else { count - 1 });
+            match path.get(depth).and_then(|&child_index| current_node.child_mut(child_index)) {
+                Some(node) => current_node = node,
+                None => break,
+            }
+        }
+    }
+
+    // Moves the subtree under `index` out of `shard`, grafting it whole when `self` has no child there.
+    pub(crate) fn merge_shard(&mut self, index: usize, mut shard: Trie<K, T, N>) {
+        let Some(node) = shard.root.child_take(index) else {
+            return;
+        };
+        let mut full_path = vec![index];
+        if self.root.child(index).is_some() {
+            node.drain_paths(|path, val| {
+                full_path.truncate(1);
+                full_path.extend_from_slice(path);
+                self.insert_path(&full_path, val);
+            });
+        } else {
+            if let Some(filter) = self.filter.as_mut() {
+                node.visit_paths(|path, _| {
+                    full_path.truncate(1);
+                    full_path.extend_from_slice(path);
+                    filter.insert_path(&full_path);
+                });
+            }
+            let node = self.root.child_set(index, node);
+            if self.count_depth > 0 {
+                node.refresh_counts(self.count_depth - 1);
+                self.root.set_cached_count(self.root.cached_count() + shard.len);
+            }
+            self.len = self.len.checked_add(shard.len).expect("trie len overflow");
+        }
+    }
+}
+
+// The per-entry term of `unordered_content_hash`.
+#[must_use]
+pub fn entry_hash<H: Hasher + Default, T: Hash>(path: &[usize], val: &T) -> u64 {
+    let mut hasher = H::default();
+    path.hash(&mut hasher);
+    val.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn valid_path<const N: usize>(path: &[usize]) -> bool {
+    path.iter().all(|&index| index < N)
+}
+
+// The last entry in the subtree at `node`, whose path is `path`: the leaf at the end of the
+// largest children, which holds a value unless the trie is hollow.
+fn last_below<T, const N: usize>(mut node: &TrieNode<T, N>, mut path: Vec<usize>) -> Option<(Vec<usize>, &T)> {
+    while let Some(child_index) = node.prev_index(None) {
+        path.push(child_index);
+        node = node.child(child_index).unwrap();
+    }
+    node.value().map(|val| (path, val))
+}
+
+// The node at the end of `path` below `root`, creating the missing ones with `layout`.
+fn descend_creating<T, const N: usize>(root: &mut TrieNode<T, N>, layout: NodeLayout, path: impl Iterator<Item = usize>) -> &mut TrieNode<T, N> {
+    let mut current_node = root;
+    for child_index in path {
+        assert!(child_index < N, "path index out of range for a trie of arity {N}");
+        if current_node.child(child_index).is_some() {
+            current_node = current_node.child_mut(child_index).unwrap();
+        } else {
+            current_node = current_node.child_set(child_index, TrieNode::with_layout(layout));
+        }
+    }
+    current_node
+}
+
+// 1 if `a == b`, otherwise 0, without branching.
+fn ct_eq(a: usize, b: usize) -> usize {
+    let diff = a ^ b;
+    ((diff | diff.wrapping_neg()) >> (usize::BITS - 1)) ^ 1
+}
+
+impl<K: ?Sized, T, const N: usize> Trie<K, T, N> {
+    // K-way merge of the tries' entries in path order. A key present in several tries is yielded
+    // once per trie, in the order the tries are given.
+    #[must_use]
+    pub fn merge_iter<'a>(tries: &[&'a Trie<K, T, N>]) -> MergeIter<'a, T, N> {
+        let mut iter = MergeIter {
+            sources: tries.iter().map(|trie| TrieIter::new(&trie.root)).collect(),
+            pending: vec![None; tries.len()],
+            heap: BinaryHeap::with_capacity(tries.len()),
+        };
+        for source in 0..tries.len() {
+            iter.advance(source);
+        }
+        iter
+    }
+}
+
+pub struct MergeIter<'a, T, const N: usize> {
+    sources: Vec<TrieIter<'a, T, N>>,
+    pending: Vec<Option<&'a T>>,
+    heap: BinaryHeap<Reverse<(Vec<usize>, usize)>>,
+}
+
+impl<T, const N: usize> MergeIter<'_, T, N> {
+    fn advance(&mut self, source: usize) {
+        if let Some((path, val)) = self.sources[source].next() {
+            self.pending[source] = Some(val);
+            self.heap.push(Reverse((path, source)));
+        }
+    }
+}
+
+impl<'a, T, const N: usize> Iterator for MergeIter<'a, T, N> {
+    type Item = (Vec<usize>, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Reverse((path, source)) = self.heap.pop()?;
+        let val = self.pending[source].take().unwrap();
+        self.advance(source);
+        Some((path, val))
+    }
+}
+
+/// What a prefix-level deletion removed, for logging and alerting on large invalidations.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RemovalReport {
+    pub values_removed: usize,
+    // Includes the valueless nodes that only led to the removed keys.
+    pub nodes_freed: usize,
+    // Node and child table memory, not counting what the values own.
+    pub bytes_freed_estimate: usize,
+}
+
+// Walks the trie on demand with a stack of the nodes above the current one, so taking a few
+// entries does not pay for the whole trie.
+pub struct TrieIter<'a, T, const N: usize> {
+    // Each node on the way down with the last child index already entered, `None` before the first.
+    stack: Vec<(&'a TrieNode<T, N>, Option<usize>)>,
+    // Child indices from the root to the node at the top of the stack.
+    path: Vec<usize>,
+    // The value of the node just entered, yielded before descending any further.
+    pending: Option<&'a T>,
+    position: Position,
+}
+
+impl<'a, T, const N: usize> TrieIter<'a, T, N> {
+    fn new(root: &'a TrieNode<T, N>) -> TrieIter<'a, T, N> {
+        TrieIter::resume(root, Position::start())
+    }
+
+    // Iterates the subtree at `node`, reporting full paths that start with `prefix`, the path to it.
+    fn below(node: &'a TrieNode<T, N>, prefix: Vec<usize>) -> TrieIter<'a, T, N> {
+        TrieIter {
+            stack: vec![(node, None)],
+            path: prefix,
+            pending: node.value(),
+            position: Position::start(),
+        }
+    }
+
+    fn empty() -> TrieIter<'a, T, N> {
+        TrieIter {
+            stack: Vec::new(),
+            path: Vec::new(),
+            pending: None,
+            position: Position::start(),
+        }
+    }
+
+    fn resume(root: &'a TrieNode<T, N>, position: Position) -> TrieIter<'a, T, N> {
+        let mut iter = TrieIter {
+            stack: vec![(root, None)],
+            path: Vec::new(),
+            pending: None,
+            position,
+        };
+        let Some(after) = iter.position.after.clone() else {
+            iter.pending = root.value();
+            return iter;
+        };
+        // Follow `after` down as far as it exists. Every node on it has its value at or before
+        // `after`, and only the children past the followed index are left to visit.
+        let mut node = root;
+        for &bound in &after {
+            let top = iter.stack.len() - 1;
+            iter.stack[top].1 = Some(bound.min(N - 1));
+            match (bound < N).then(|| node.child(bound)).flatten() {
+                Some(child) => {
+                    iter.path.push(bound);
+                    iter.stack.push((child, None));
+                    node = child;
+                }
+                _ => break,
+            }
+        }
+        iter
+    }
+
+    // The entries at `start` (if `inclusive`) and after it.
+    fn starting_at(root: &'a TrieNode<T, N>, start: &[usize], inclusive: bool) -> TrieIter<'a, T, N> {
+        let mut iter = TrieIter::resume(root, Position::after_path(start));
+        if inclusive && iter.path.len() == start.len() {
+            iter.pending = iter.stack.last().and_then(|(node, _)| node.value());
+        }
+        iter
+    }
+
+    // Where the iterator stands: after the last entry it yielded.
+    #[must_use]
+    pub fn position(&self) -> Position {
+        self.position.clone()
+    }
+
+    // Yields only the first entry under each distinct path prefix of length `depth`, jumping
+    // over the rest of its subtree instead of walking it. Entries with shorter paths are all
+    // yielded.
+    #[must_use]
+    pub fn dedup_by_prefix(self, depth: usize) -> DedupByPrefix<'a, T, N> {
+        DedupByPrefix { iter: self, depth }
+    }
+
+    // Drops what is left of the subtree at `depth` around the entry just yielded.
+    fn skip_subtree(&mut self, depth: usize) {
+        if self.path.len() < depth {
+            return;
+        }
+        // Iterators over a subtree start with the path to it and a one-node stack.
+        let base = self.path.len() + 1 - self.stack.len();
+        if depth <= base {
+            self.stack.clear();
+            return;
+        }
+        self.stack.truncate(depth - base);
+        self.path.truncate(depth - 1);
+    }
+}
+
+pub struct CommonPrefixes<'a, T, const N: usize, I> {
+    // The next node along the path, `None` once the path leaves the trie or ends.
+    node: Option<&'a TrieNode<T, N>>,
+    path: I,
+    depth: usize,
+}
+
+impl<'a, T, const N: usize, I: Iterator<Item = usize>> Iterator for CommonPrefixes<'a, T, N, I> {
+    type Item = (usize, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let node = self.node?;
+            let depth = self.depth;
+            self.node = self.path.next().and_then(|child_index| (child_index < N).then(|| node.child(child_index)).flatten());
+            self.depth += 1;
+            if let Some(val) = node.value() {
+                return Some((depth, val));
+            }
+        }
+    }
+}
+
+pub struct DedupByPrefix<'a, T, const N: usize> {
+    iter: TrieIter<'a, T, N>,
+    depth: usize,
+}
+
+impl<'a, T, const N: usize> Iterator for DedupByPrefix<'a, T, N> {
+    type Item = (Vec<usize>, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.iter.next()?;
+        self.iter.skip_subtree(self.depth);
+        Some(item)
+    }
+}
+
+impl<'a, T, const N: usize> Iterator for TrieIter<'a, T, N> {
+    type Item = (Vec<usize>, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(val) = self.pending.take() {
+                self.position.after = Some(self.path.clone());
+                return Some((self.path.clone(), val));
+            }
+            let (node, last) = self.stack.last_mut()?;
+            let node: &'a TrieNode<T, N> = node;
+            match node.next_index(*last) {
+                Some(index) => {
+                    *last = Some(index);
+                    let child = node.child(index).unwrap();
+                    self.path.push(index);
+                    self.stack.push((child, None));
+                    self.pending = child.value();
+                }
+                None => {
+                    self.stack.pop();
+                    self.path.pop();
+                }
+            }
+        }
+    }
+}
+
+pub struct TrieIterMut<'a, T, const N: usize> {
+    // The children of each node on the way down that are still to be visited, in index order.
+    stack: Vec<std::vec::IntoIter<(usize, &'a mut TrieNode<T, N>)>>,
+    path: Vec<usize>,
+    pending: Option<&'a mut T>,
+}
+
+impl<'a, T, const N: usize> Iterator for TrieIterMut<'a, T, N> {
+    type Item = (Vec<usize>, &'a mut T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(val) = self.pending.take() {
+                return Some((self.path.clone(), val));
+            }
+            let next = self.stack.last_mut()?.next();
+            match next {
+                Some((index, child)) => {
+                    self.path.truncate(self.stack.len() - 1);
+                    self.path.push(index);
+                    let (pending, children) = child.parts_mut();
+                    self.pending = pending;
+                    self.stack.push(children.into_iter());
+                }
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
+    }
+}
+
+pub struct IterMut<'a, K: ?Sized, T, const N: usize> {
+    paths: TrieIterMut<'a, T, N>,
+    _key_type: PhantomData<&'a K>,
+}
+
+impl<'a, K: ?Sized + ToOwned, T, const N: usize> Iterator for IterMut<'a, K, T, N>
+where
+    Trie<K, T, N>: TriePathDecoder<K>,
+{
+    type Item = (K::Owned, &'a mut T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (path, val) = self.paths.next()?;
+        let key = <Trie<K, T, N> as TriePathDecoder<K>>::decode_path(&path).expect("trie holds a path that is not a valid key");
+        Some((key, val))
+    }
+}
+
+pub struct Keys<'a, K: ?Sized, T, const N: usize> {
+    entries: Iter<'a, K, T, N>,
+}
+
+impl<K: ?Sized + ToOwned, T, const N: usize> Iterator for Keys<'_, K, T, N>
+where
+    Trie<K, T, N>: TriePathDecoder<K>,
+{
+    type Item = K::Owned;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.entries.next().map(|(key, _)| key)
+    }
+}
+
+pub struct Values<'a, T, const N: usize> {
+    paths: TrieIter<'a, T, N>,
+}
+
+impl<'a, T, const N: usize> Iterator for Values<'a, T, N> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.paths.next().map(|(_, val)| val)
+    }
+}
+
+pub struct ValuesMut<'a, T, const N: usize> {
+    paths: TrieIterMut<'a, T, N>,
+}
+
+impl<'a, T, const N: usize> Iterator for ValuesMut<'a, T, N> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.paths.next().map(|(_, val)| val)
+    }
+}
+
+pub struct Range<'a, K: ?Sized, T, const N: usize> {
+    paths: TrieIter<'a, T, N>,
+    end: Bound<Vec<usize>>,
+    _key_type: PhantomData<&'a K>,
+}
+
+impl<'a, K: ?Sized + ToOwned, T, const N: usize> Iterator for Range<'a, K, T, N>
+where
+    Trie<K, T, N>: TriePathDecoder<K>,
+{
+    type Item = (K::Owned, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (path, val) = self.paths.next()?;
+        let in_range = match &self.end {
+            Bound::Included(end) => path <= *end,
+            Bound::Excluded(end) => path < *end,
+            Bound::Unbounded => true,
+        };
+        if !in_range {
+            self.paths = TrieIter::empty();
+            return None;
+        }
+        let key = <Trie<K, T, N> as TriePathDecoder<K>>::decode_path(&path).expect("trie holds a path that is not a valid key");
+        Some((key, val))
+    }
+}
+
+pub struct Iter<'a, K: ?Sized, T, const N: usize> {
+    paths: TrieIter<'a, T, N>,
+    _key_type: PhantomData<&'a K>,
+}
+
+impl<K: ?Sized, T, const N: usize> Iter<'_, K, T, N> {
+    #[must_use]
+    pub fn position(&self) -> Position {
+        self.paths.position()
+    }
+}
+
+impl<'a, K: ?Sized + ToOwned, T, const N: usize> Iterator for Iter<'a, K, T, N>
+where
+    Trie<K, T, N>: TriePathDecoder<K>,
+{
+    type Item = (K::Owned, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (path, val) = self.paths.next()?;
+        let key = <Trie<K, T, N> as TriePathDecoder<K>>::decode_path(&path).expect("trie holds a path that is not a valid key");
+        Some((key, val))
+    }
+}
+
+/// A continuation token for `TrieIter`: resuming yields the entries whose paths sort after the
+/// last one yielded, so it stays meaningful across inserts, deletes and other snapshots.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Position {
+    after: Option<Vec<usize>>,
+}
+
+impl Position {
+    // Before every entry.
+    #[must_use]
+    pub fn start() -> Position {
+        Position { after: None }
+    }
+
+    // Just after the entry at `path`.
+    #[must_use]
+    pub fn after_path(path: &[usize]) -> Position {
+        Position {
+            after: Some(path.to_vec()),
+        }
+    }
+
+    // A tag byte followed by each path index as a LEB128 varint.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let Some(after) = &self.after else {
+            return vec![0];
+        };
+        let mut bytes = vec![1];
+        for &index in after {
+            write_varint(&mut bytes, index);
+        }
+        bytes
+    }
+
+    // `None` for bytes that `to_bytes` could not have produced.
+    #[must_use]
+    pub fn from_bytes(bytes: &[u8]) -> Option<Position> {
+        match bytes.split_first()? {
+            (0, []) => Some(Position::start()),
+            (1, mut rest) => {
+                let mut after = Vec::new();
+                while !rest.is_empty() {
+                    after.push(read_varint(&mut rest)?);
+                }
+                Some(Position { after: Some(after) })
+            }
+            _ => None,
+        }
+    }
+}
+
+impl<K: ?Sized + ToOwned, T, const N: usize> Trie<K, T, N>
+where
+    Trie<K, T, N>: TriePathDecoder<K>,
+{
+    // The entries in key order, decoding each key from its path as it goes.
+    #[must_use]
+    pub fn iter(&self) -> Iter<'_, K, T, N> {
+        Iter {
+            paths: self.iter_paths(),
+            _key_type: PhantomData,
+        }
+    }
+
+    // `retain_paths` with decoded keys.
+    pub fn retain(&mut self, mut keep: impl FnMut(&K, &mut T) -> bool) -> usize {
+        self.retain_paths(|path, val| {
+            let key = <Trie<K, T, N> as TriePathDecoder<K>>::decode_path(path).expect("trie holds a path that is not a valid key");
+            keep(key.borrow(), val)
+        })
+    }
+
+    #[must_use]
+    pub fn keys(&self) -> Keys<'_, K, T, N> {
+        Keys { entries: self.iter() }
+    }
+
+    // The entries in key order with mutable values, for updating every value in place.
+    #[must_use]
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, T, N> {
+        IterMut {
+            paths: self.iter_paths_mut(),
+            _key_type: PhantomData,
+        }
+    }
+
+    // The entries with keys inside `range`, in key order, e.g. `trie.range("b".."d")`. Starts at
+    // the lower bound without visiting the subtrees before it and stops at the upper one.
+    #[must_use]
+    pub fn range<'k, R: RangeBounds<&'k K>>(&self, range: R) -> Range<'_, K, T, N>
+    where
+        K: 'k,
+        Trie<K, T, N>: TriePathBuilder<K>,
+    {
+        let build = |key: &K| <Trie<K, T, N> as TriePathBuilder<K>>::build_path(key);
+        let paths = match range.start_bound() {
+            Bound::Included(start) => TrieIter::starting_at(&self.root, &build(start), true),
+            Bound::Excluded(start) => TrieIter::starting_at(&self.root, &build(start), false),
+            Bound::Unbounded => TrieIter::new(&self.root),
+        };
+        Range {
+            paths,
+            end: range.end_bound().map(|end| build(end)),
+            _key_type: PhantomData,
+        }
+    }
+
+    // The entries whose keys start with `prefix`, with their full keys, in key order. Only the
+    // subtree under the prefix is visited.
+    #[must_use]
+    pub fn iter_prefix(&self, prefix: &K) -> Iter<'_, K, T, N>
+    where
+        Trie<K, T, N>: TriePathBuilder<K>,
+    {
+        self.iter_below_path(<Trie<K, T, N> as TriePathBuilder<K>>::build_path(prefix))
+    }
+
+    pub(crate) fn iter_below_path(&self, path: Vec<usize>) -> Iter<'_, K, T, N> {
+        let paths = match self.node_at(&path) {
+            Some(node) => TrieIter::below(node, path),
+            None => TrieIter::empty(),
+        };
+        Iter {
+            paths,
+            _key_type: PhantomData,
+        }
+    }
+
+    // Snapshots the entries, so the iterator owns everything and can outlive the trie or move
+    // to another thread. Use a `SharedTrie` to make the value copies cheap.
+    #[must_use]
+    pub fn iter_owned(&self) -> OwnedIter<K::Owned, T>
+    where
+        T: Clone,
+    {
+        self.try_iter_owned().expect("trie holds a path that is not a valid key")
+    }
+
+    // Like `iter_owned`, but reports the first stored path that does not decode instead of panicking.
+    pub fn try_iter_owned(&self) -> Result<OwnedIter<K::Owned, T>, DecodeError>
+    where
+        T: Clone,
+    {
+        let mut items = Vec::with_capacity(self.len);
+        let mut error = None;
+        self.root.visit_paths(|path, val| {
+            if error.is_some() {
+                return;
+            }
+            match <Trie<K, T, N> as TriePathDecoder<K>>::decode_path(path) {
+                Ok(key) => items.push((key, val.clone())),
+                Err(err) => error = Some(err),
+            }
+        });
+        match error {
+            Some(err) => Err(err),
+            None => Ok(OwnedIter {
+                items: items.into_iter(),
+            }),
+        }
+    }
+
+    // Eager prefix query: the decoded keys starting with `prefix`, in key order.
+    #[must_use]
+    pub fn keys_with_prefix(&self, prefix: &K) -> Vec<K::Owned>
+    where
+        Trie<K, T, N>: TriePathBuilder<K>,
+    {
+        let mut path = <Trie<K, T, N> as TriePathBuilder<K>>::build_path(prefix);
+        let mut keys = Vec::new();
+        if let Some(node) = self.node_at(&path) {
+            let base = path.len();
+            node.visit_paths(|rel, _| {
+                path.truncate(base);
+                path.extend_from_slice(rel);
+                let key = <Trie<K, T, N> as TriePathDecoder<K>>::decode_path(&path);
+                keys.push(key.expect("trie holds a path that is not a valid key"));
+            });
+        }
+        keys
+    }
+
+    // Like `keys_with_prefix`, but each key comes back with the prefix stripped, along with its
+    // value. The part after the prefix must decode on its own, as it does for byte keys.
+    #[must_use]
+    pub fn suffixes_with_prefix(&self, prefix: &K) -> Vec<(K::Owned, &T)>
+    where
+        Trie<K, T, N>: TriePathBuilder<K>,
+    {
+        self.iter_prefix_relative(prefix)
+            .map(|(rel, val)| {
+                let key = <Trie<K, T, N> as TriePathDecoder<K>>::decode_path(&rel);
+                (key.expect("key suffix does not decode on its own"), val)
+            })
+            .collect()
+    }
+
+    // Returns the key as decoded from the stored path, which is the canonical form even when the
+    // lookup key was spelled differently by a custom encoding.
+    #[must_use]
+    pub fn delete_entry(&mut self, key: &K) -> Option<(K::Owned, T)>
+    where
+        Trie<K, T, N>: TriePathBuilder<K>,
+    {
+        let path = <Trie<K, T, N> as TriePathBuilder<K>>::build_path(key);
+        self.get_path(&path)?;
+        let key = <Trie<K, T, N> as TriePathDecoder<K>>::decode_path(&path);
+        let key = key.expect("trie holds a path that is not a valid key");
+        self.delete_path(&path).map(|val| (key, val))
+    }
+
+    #[must_use]
+    pub fn first_key_value(&self) -> Option<(K::Owned, &T)> {
+        let (path, val) = self.first_path()?;
+        let key = <Trie<K, T, N> as TriePathDecoder<K>>::decode_path(&path);
+        Some((key.expect("trie holds a path that is not a valid key"), val))
+    }
+
+    #[must_use]
+    pub fn last_key_value(&self) -> Option<(K::Owned, &T)> {
+        let (path, val) = self.last_path()?;
+        let key = <Trie<K, T, N> as TriePathDecoder<K>>::decode_path(&path);
+        Some((key.expect("trie holds a path that is not a valid key"), val))
+    }
+
+    // The longest key that every stored key starts with, e.g. "inter" for "internal" and
+    // "internet". A shared path ending inside a key's encoding, such as half a byte at arity 16,
+    // is cut back to one that decodes. `None` for an empty trie, or if no part of the shared path
+    // decodes, as with fixed-width keys.
+    #[must_use]
+    pub fn longest_common_prefix(&self) -> Option<K::Owned> {
+        let path = self.longest_common_prefix_path(&[])?;
+        (0..=path.len()).rev().find_map(|len| <Trie<K, T, N> as TriePathDecoder<K>>::decode_path(&path[..len]).ok())
+    }
+
+    // `longest_common_prefix` of the keys starting with `prefix`.
+    #[must_use]
+    pub fn longest_common_prefix_under(&self, prefix: &K) -> Option<K::Owned>
+    where
+        Trie<K, T, N>: TriePathBuilder<K>,
+    {
+        let path = self.longest_common_prefix_path(&<Trie<K, T, N> as TriePathBuilder<K>>::build_path(prefix))?;
+        (0..=path.len()).rev().find_map(|len| <Trie<K, T, N> as TriePathDecoder<K>>::decode_path(&path[..len]).ok())
+    }
+
+    // The entry with the smallest key strictly greater than `key`, which need not be stored.
+    #[must_use]
+    pub fn next_after(&self, key: &K) -> Option<(K::Owned, &T)>
+    where
+        Trie<K, T, N>: TriePathBuilder<K>,
+    {
+        let (path, val) = self.next_path_after(&<Trie<K, T, N> as TriePathBuilder<K>>::build_path(key))?;
+        let key = <Trie<K, T, N> as TriePathDecoder<K>>::decode_path(&path);
+        Some((key.expect("trie holds a path that is not a valid key"), val))
+    }
+
+    // The entry with the largest key strictly less than `key`.
+    #[must_use]
+    pub fn prev_before(&self, key: &K) -> Option<(K::Owned, &T)>
+    where
+        Trie<K, T, N>: TriePathBuilder<K>,
+    {
+        let (path, val) = self.prev_path_before(&<Trie<K, T, N> as TriePathBuilder<K>>::build_path(key))?;
+        let key = <Trie<K, T, N> as TriePathDecoder<K>>::decode_path(&path);
+        Some((key.expect("trie holds a path that is not a valid key"), val))
+    }
+
+    pub fn pop_first(&mut self) -> Option<(K::Owned, T)> {
+        let path = self.first_path()?.0;
+        self.pop_path(&path)
+    }
+
+    pub fn pop_last(&mut self) -> Option<(K::Owned, T)> {
+        let path = self.last_path()?.0;
+        self.pop_path(&path)
+    }
+
+    fn pop_path(&mut self, path: &[usize]) -> Option<(K::Owned, T)> {
+        let key = <Trie<K, T, N> as TriePathDecoder<K>>::decode_path(path);
+        let key = key.expect("trie holds a path that is not a valid key");
+        self.delete_path(path).map(|val| (key, val))
+    }
+
+    #[must_use]
+    pub fn into_keys(self) -> IntoKeys<K::Owned> {
+        let mut keys = Vec::with_capacity(self.len);
+        self.root.drain_paths(|path, _| {
+            let key = <Trie<K, T, N> as TriePathDecoder<K>>::decode_path(path);
+            keys.push(key.expect("trie holds a path that is not a valid key"));
+        });
+        IntoKeys {
+            keys: keys.into_iter(),
+        }
+    }
+}
+
+impl<K: ?Sized + ToOwned, T, const N: usize> IntoIterator for Trie<K, T, N>
+where
+    Trie<K, T, N>: TriePathDecoder<K>,
+{
+    type Item = (K::Owned, T);
+    type IntoIter = OwnedIter<K::Owned, T>;
+
+    // Takes the entries out in key order, decoding the keys as they are drained.
+    fn into_iter(self) -> OwnedIter<K::Owned, T> {
+        let mut items = Vec::with_capacity(self.len);
+        self.root.drain_paths(|path, val| {
+            let key = <Trie<K, T, N> as TriePathDecoder<K>>::decode_path(path);
+            items.push((key.expect("trie holds a path that is not a valid key"), val));
+        });
+        OwnedIter {
+            items: items.into_iter(),
+        }
+    }
+}
+
+impl<K: ?Sized + ToOwned, T, const N: usize> Extend<(K::Owned, T)> for Trie<K, T, N>
+where
+    Trie<K, T, N>: TriePathBuilder<K>,
+{
+    // Later values replace earlier ones under the same key, as with `insert`.
+    fn extend<I: IntoIterator<Item = (K::Owned, T)>>(&mut self, iter: I) {
+        for (key, val) in iter {
+            self.insert(key.borrow(), val);
+        }
+    }
+}
+
+impl<K: ?Sized + ToOwned, T, const N: usize> FromIterator<(K::Owned, T)> for Trie<K, T, N>
+where
+    Trie<K, T, N>: TriePathBuilder<K>,
+{
+    fn from_iter<I: IntoIterator<Item = (K::Owned, T)>>(iter: I) -> Self {
+        let mut trie = Trie::new();
+        trie.extend(iter);
+        trie
+    }
+}
+
+impl<'a, K: ?Sized + ToOwned, T, const N: usize> IntoIterator for &'a Trie<K, T, N>
+where
+    Trie<K, T, N>: TriePathDecoder<K>,
+{
+    type Item = (K::Owned, &'a T);
+    type IntoIter = Iter<'a, K, T, N>;
+
+    fn into_iter(self) -> Iter<'a, K, T, N> {
+        self.iter()
+    }
+}
+
+impl<'a, K: ?Sized + ToOwned, T, const N: usize> IntoIterator for &'a mut Trie<K, T, N>
+where
+    Trie<K, T, N>: TriePathDecoder<K>,
+{
+    type Item = (K::Owned, &'a mut T);
+    type IntoIter = IterMut<'a, K, T, N>;
+
+    fn into_iter(self) -> IterMut<'a, K, T, N> {
+        self.iter_mut()
+    }
+}
+
+impl<K: ?Sized, T, const N: usize> Trie<K, T, N> {
+    #[must_use]
+    pub fn into_values(self) -> IntoValues<T> {
+        let mut values = Vec::with_capacity(self.len);
+        self.root.drain_paths(|_, val| values.push(val));
+        IntoValues {
+            values: values.into_iter(),
+        }
+    }
+}
+
+pub struct IntoKeys<O> {
+    keys: std::vec::IntoIter<O>,
+}
+
+impl<O> Iterator for IntoKeys<O> {
+    type Item = O;
+
+    fn next(&mut self) -> Option<O> {
+        self.keys.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.keys.size_hint()
+    }
+}
+
+impl<O> DoubleEndedIterator for IntoKeys<O> {
+    fn next_back(&mut self) -> Option<O> {
+        self.keys.next_back()
+    }
+}
+
+impl<O> ExactSizeIterator for IntoKeys<O> {}
+
+pub struct IntoValues<T> {
+    values: std::vec::IntoIter<T>,
+}
+
+impl<T> Iterator for IntoValues<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.values.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.values.size_hint()
+    }
+}
+
+impl<T> DoubleEndedIterator for IntoValues<T> {
+    fn next_back(&mut self) -> Option<T> {
+        self.values.next_back()
+    }
+}
+
+impl<T> ExactSizeIterator for IntoValues<T> {}
+
+pub struct OwnedIter<O, T> {
+    items: std::vec::IntoIter<(O, T)>,
+}
+
+impl<O, T> Iterator for OwnedIter<O, T> {
+    type Item = (O, T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.items.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.items.size_hint()
+    }
+}
+
+impl<O, T> DoubleEndedIterator for OwnedIter<O, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.items.next_back()
+    }
+}
+
+impl<O, T> ExactSizeIterator for OwnedIter<O, T> {}
+
+// Read-modify-write operations for counters. A missing key counts as `T::default()`, gets
+// created, and is reported as the previous value.
+impl<K: ?Sized, T: Copy + Default, const N: usize> Trie<K, T, N>
+where
+    Trie<K, T, N>: TriePathBuilder<K>,
+{
+    pub fn fetch_update(&mut self, key: &K, f: impl FnOnce(T) -> T) -> T {
+        let path = <Trie<K, T, N> as TriePathBuilder<K>>::build_path(key);
+        self.upsert_path(&path, T::default, |val| {
+            let old = *val;
+            *val = f(old);
+            old
+        })
+    }
+
+    pub fn fetch_add(&mut self, key: &K, delta: T) -> T
+    where
+        T: Add<Output = T>,
+    {
+        self.fetch_update(key, |val| val + delta)
+    }
+
+    pub fn fetch_sub(&mut self, key: &K, delta: T) -> T
+    where
+        T: Sub<Output = T>,
+    {
+        self.fetch_update(key, |val| val - delta)
+    }
+
+    pub fn fetch_max(&mut self, key: &K, val: T) -> T
+    where
+        T: PartialOrd,
+    {
+        self.fetch_update(key, |old| if val > old { val } else { old })
+    }
+
+    pub fn fetch_min(&mut self, key: &K, val: T) -> T
+    where
+        T: PartialOrd,
+    {
+        self.fetch_update(key, |old| if val < old { val } else { old })
+    }
+}
+
+pub type SharedTrie<K, T, const N: usize> = Trie<K, Arc<T>, N>;
+
+// Values wrapped in `Zeroizing` are wiped whenever they are dropped: overwritten by `insert` once
+// the caller drops the old value, removed, cleared, pruned by `retain` or `remove_prefix`, or
+// dropped along with the trie. `insert_zeroizing` and `delete_zeroizing` suit plain `T: Zeroize`
+// values that cannot be wrapped.
+#[cfg(feature = "zeroize")]
+pub type SecretTrie<K, T, const N: usize> = Trie<K, Zeroizing<T>, N>;
+
+impl<K: ?Sized, T, const N: usize> Trie<K, Arc<T>, N>
+where
+    Trie<K, Arc<T>, N>: TriePathBuilder<K>,
+{
+    #[must_use]
+    pub fn get_shared(&self, key: &K) -> Option<Arc<T>> {
+        self.get(key).map(Arc::clone)
+    }
+
+    pub fn insert_shared(&mut self, key: &K, val: T) -> Option<Arc<T>> {
+        self.insert(key, Arc::new(val))
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<K: ?Sized, T: Zeroize, const N: usize> Trie<K, T, N>
+where
+    Trie<K, T, N>: TriePathBuilder<K>,
+{
+    pub fn insert_zeroizing(&mut self, key: &K, val: T) -> bool {
+        if let Some(mut old) = self.insert(key, val) {
+            old.zeroize();
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn delete_zeroizing(&mut self, key: &K) -> bool {
+        if let Some(mut old) = self.remove(key) {
+            old.zeroize();
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<K: ?Sized, T: Zeroize, const N: usize> ZeroizeOnDrop for Trie<K, Zeroizing<T>, N> {}
+
+#[cfg(feature = "zeroize")]
+impl<K: ?Sized, T: Zeroize, const N: usize> Zeroize for Trie<K, T, N> {
+    fn zeroize(&mut self) {
+        self.root.visit_values_mut(Zeroize::zeroize);
+        self.root = TrieNode::with_layout(self.layout);
+        self.len = 0;
+        if let Some(filter) = self.filter.as_mut() {
+            filter.clear();
+        }
+    }
+}
+
+// A deep copy of the nodes, keeping the layout, filter and prefix counts.
+impl<K: ?Sized, T: Clone, const N: usize> Clone for Trie<K, T, N> {
+    fn clone(&self) -> Self {
+        Trie {
+            len: self.len,
+            layout: self.layout,
+            filter: self.filter.clone(),
+            count_depth: self.count_depth,
+            root: self.root.clone_subtree(),
+            hollow: self.hollow,
+            _key_type: PhantomData,
+        }
+    }
+}
+
+// Tries are equal when they hold the same paths with equal values, whatever their layouts or the
+// order the entries were inserted and removed in.
+impl<K: ?Sized, T: PartialEq, const N: usize> PartialEq for Trie<K, T, N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter_paths().eq(other.iter_paths())
+    }
+}
+
+impl<K: ?Sized, T: Eq, const N: usize> Eq for Trie<K, T, N> {}
+
+// Agrees with `PartialEq`: the length, then every path and value in path order.
+impl<K: ?Sized, T: Hash, const N: usize> Hash for Trie<K, T, N> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.len.hash(state);
+        self.root.visit_paths(|path, val| {
+            path.hash(state);
+            val.hash(state);
+        });
+    }
+}
+
+// Entries map-style with decoded keys. A path that is not a valid key, e.g. one inserted through
+// the raw path API, shows as its indices rather than failing the whole output.
+impl<K, T, const N: usize> fmt::Debug for Trie<K, T, N>
+where
+    K: ?Sized + ToOwned,
+    K::Owned: fmt::Debug,
+    T: fmt::Debug,
+    Trie<K, T, N>: TriePathDecoder<K>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut map = f.debug_map();
+        for (path, val) in self.iter_paths() {
+            match <Trie<K, T, N> as TriePathDecoder<K>>::decode_path(&path) {
+                Ok(key) => map.entry(&key, val),
+                Err(_) => map.entry(&path, val),
+            };
+        }
+        map.finish()
+    }
+}
+
+impl<K, T, const N: usize> Default for Trie<K, T, N>
+where
+    Trie<K, T, N>: TriePathBuilder<K>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Bound;
+
+    use crate::trie::{entry_hash, ByteTrie, DecodeError, NodeLayout, Position, Range, RemovalReport, SharedTrie, Trie, TriePathBuilder, TriePathDecoder};
+
+    #[test]
+    fn it_works() {
+        let mut trie: Trie<[u8], usize, 16> = Trie::new();
+
+        assert_eq!(trie.insert(&[1, 3, 7, 2], 3), None);
+        assert_eq!(trie.get(&[]), None);
+        assert_eq!(trie.get(&[1]), None);
+        assert_eq!(trie.get(&[1, 3]), None);
+        assert_eq!(trie.get(&[1, 3, 7]), None);
+        assert_eq!(trie.get(&[1, 3, 7, 2]), Some(&3));
+        assert_eq!(trie.insert(&[], 7), None);
+        assert_eq!(trie.get(&[]), Some(&7));
+        assert_eq!(trie.get(&[1]), None);
+        assert_eq!(trie.get(&[1, 3]), None);
+        assert_eq!(trie.get(&[1, 3, 7]), None);
+        assert_eq!(trie.get(&[1, 3, 7, 2]), Some(&3));
+        assert_eq!(trie.insert(&[1, 3], 5), None);
+        assert_eq!(trie.get(&[]), Some(&7));
+        assert_eq!(trie.get(&[1]), None);
+        assert_eq!(trie.get(&[1, 3]), Some(&5));
+        assert_eq!(trie.get(&[1, 3, 7]), None);
+        assert_eq!(trie.get(&[1, 3, 7, 2]), Some(&3));
+        assert_eq!(trie.len(), 3);
+        assert_eq!(trie.insert(&[1, 3], 6), Some(5));
+        assert_eq!(trie.len(), 3);
+        assert_eq!(trie.remove(&[1, 3]), Some(6));
+        assert_eq!(trie.len(), 2);
+        assert_eq!(trie.get(&[1, 3, 7, 2]), Some(&3));
+        assert_eq!(trie.remove(&[1, 3, 7, 2]), Some(3));
+        assert_eq!(trie.len(), 1);
+        assert_eq!(trie.remove(&[]), Some(7));
+        assert_eq!(trie.len(), 0);
+    }
+
+    #[test]
+    fn shared_values() {
+        let mut trie: SharedTrie<str, String, 16> = Trie::new();
+
+        assert_eq!(trie.insert_shared("key", "value".to_string()), None);
+        let shared = trie.get_shared("key").unwrap();
+        assert_eq!(trie.remove("key").as_deref(), Some(&"value".to_string()));
+        assert_eq!(shared.as_str(), "value");
+        assert_eq!(trie.get_shared("key"), None);
+        assert_eq!(trie.get_cloned("key"), None);
+    }
+
+    #[test]
+    fn iter() {
+        let mut trie: Trie<[u8], usize, 16> = Trie::new();
+        trie.insert(&[0x12], 2);
+        trie.insert(&[], 0);
+        trie.insert(&[0x01, 0xff], 1);
+        let items: Vec<(Vec<usize>, &usize)> = trie.iter_paths().collect();
+        assert_eq!(items, vec![(vec![], &0), (vec![0, 1, 15, 15], &1), (vec![1, 2], &2)]);
+        let items: Vec<(Vec<u8>, &usize)> = trie.iter().collect();
+        assert_eq!(items, vec![(vec![], &0), (vec![0x01, 0xff], &1), (vec![0x12], &2)]);
+
+        let mut words: Trie<str, usize, 16> = Trie::new();
+        words.insert("b", 1);
+        words.insert("a", 0);
+        let mut iter = words.iter();
+        assert_eq!(iter.next(), Some(("a".to_string(), &0)));
+        assert_eq!(words.resume_at(&iter.position()).count(), 1);
+    }
+
+    #[test]
+    fn resume_at() {
+        let mut trie: Trie<str, usize, 16> = Trie::new();
+        for (i, key) in ["a", "ab", "b", "ba", "c"].iter().enumerate() {
+            trie.insert(key, i);
+        }
+        let mut page = trie.iter();
+        assert_eq!(page.by_ref().take(2).count(), 2);
+        let token = page.position().to_bytes();
+
+        trie.remove("b");
+        trie.insert("aa", 10);
+        let position = Position::from_bytes(&token).unwrap();
+        let rest: Vec<usize> = trie.resume_at(&position).map(|(_, val)| *val).collect();
+        assert_eq!(rest, vec![3, 4]);
+        assert_eq!(trie.resume_at(&Position::start()).count(), 5);
+        assert_eq!(Position::from_bytes(&[1, 0x80]), None);
+    }
+
+    #[test]
+    fn resume_every_layout() {
+        for layout in [NodeLayout::Dense, NodeLayout::Packed, NodeLayout::Sorted, NodeLayout::Adaptive] {
+            let mut trie: Trie<str, usize, 16> = Trie::with_layout(layout);
+            for (i, key) in ["", "a", "ab", "abc", "b", "zz"].iter().enumerate() {
+                trie.insert(key, i);
+            }
+            let all: Vec<(Vec<usize>, &usize)> = trie.iter_paths().collect();
+            assert_eq!(all.iter().map(|(_, val)| **val).collect::<Vec<_>>(), vec![0, 1, 2, 3, 4, 5]);
+            // Every stored path, plus ones that end inside a node, miss a child or are out of range.
+            let mut afters: Vec<Vec<usize>> = all.iter().map(|(path, _)| path.clone()).collect();
+            afters.extend([vec![6], vec![6, 1, 0], vec![6, 2, 0, 0], vec![16], vec![7, 10, 7, 10, 0]]);
+            for after in afters {
+                let expected: Vec<usize> = all.iter().filter(|(path, _)| *path > after).map(|(_, val)| **val).collect();
+                let rest: Vec<usize> = trie.resume_at(&Position::after_path(&after)).map(|(_, val)| *val).collect();
+                assert_eq!(rest, expected, "{layout:?} after {after:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn swap_take_prune() {
+        let mut trie: Trie<str, usize, 16> = Trie::new();
+        trie.insert("ab", 1);
+        assert_eq!(trie.swap("ab", 2), Some(1));
+        assert_eq!(trie.swap("abc", 3), None);
+        assert_eq!(trie.swap("a", 4), None);
+        assert_eq!((trie.get("ab"), trie.len()), (Some(&2), 3));
+
+        assert_eq!(trie.take("abc"), Some(3));
+        assert_eq!(trie.take("abc"), None);
+        assert_eq!(trie.get("abc"), None);
+        let mut other: Trie<str, (), 16> = Trie::new();
+        other.insert("a", ());
+        other.insert("ab", ());
+        assert!(trie.keys_eq(&other));
+        assert_eq!(trie.keys_cmp(&other), std::cmp::Ordering::Equal);
+
+        let nodes = trie.root.footprint().1;
+        assert_eq!(trie.swap("abc", 5), None);
+        assert_eq!(trie.root.footprint().1, nodes);
+        assert_eq!(trie.take("abc"), Some(5));
+        assert_eq!(trie.prune(), 2);
+        assert_eq!(trie.prune(), 0);
+        assert_eq!(trie.len(), 2);
+        assert!(trie.root.keys_eq(&other.root));
+
+        let mut numbers: Trie<u64, char, 16> = Trie::new();
+        assert_eq!(numbers.swap(&7, 'a'), None);
+        assert_eq!(numbers.swap(&7, 'b'), Some('a'));
+        let mut bytes: ByteTrie<u8> = Trie::new();
+        bytes.insert(b"k", 1);
+        assert_eq!((bytes.swap(b"k", 2), bytes.get(b"k")), (Some(1), Some(&2)));
+    }
+
+    #[test]
+    fn iter_prefix() {
+        let mut trie: Trie<str, usize, 16> = Trie::new();
+        for (i, key) in ["a", "ab", "abc", "abd", "b", "ba"].iter().enumerate() {
+            trie.insert(key, i);
+        }
+        let entries: Vec<(String, &usize)> = trie.iter_prefix("ab").collect();
+        assert_eq!(entries, vec![("ab".to_string(), &1), ("abc".to_string(), &2), ("abd".to_string(), &3)]);
+        assert_eq!(trie.iter_prefix("").count(), 6);
+        assert_eq!(trie.iter_prefix("bb").count(), 0);
+        assert_eq!(trie.iter_prefix("abcd").count(), 0);
+        // Positions hold full paths, so they resume over the whole trie.
+        let mut iter = trie.iter_prefix("ab");
+        iter.next();
+        assert_eq!(trie.resume_at(&iter.position()).count(), 4);
+    }
+
+    #[test]
+    fn iter_mut() {
+        for layout in [NodeLayout::Dense, NodeLayout::Packed, NodeLayout::Sorted, NodeLayout::Adaptive] {
+            let mut trie: Trie<str, usize, 16> = Trie::with_layout(layout);
+            for (i, key) in ["b", "", "ab", "a", "ba"].iter().enumerate() {
+                trie.insert(key, i);
+            }
+            let keys: Vec<String> = trie
+                .iter_mut()
+                .map(|(key, val)| {
+                    *val += 10;
+                    key
+                })
+                .collect();
+            assert_eq!(keys, vec!["", "a", "ab", "b", "ba"]);
+            let paths: Vec<Vec<usize>> = trie.iter_paths().map(|(path, _)| path).collect();
+            assert_eq!(trie.iter_paths_mut().map(|(path, _)| path).collect::<Vec<_>>(), paths);
+            assert_eq!(trie.get("ab"), Some(&12));
+            assert_eq!(trie.iter().map(|(_, val)| *val).sum::<usize>(), 60);
+        }
+    }
+
+    #[test]
+    fn dedup_by_prefix() {
+        let mut trie: Trie<str, usize, 16> = Trie::new();
+        for (i, key) in ["", "a/1", "a/2", "b", "b/1", "c/x/1", "c/y/2"].iter().enumerate() {
+            trie.insert(key, i);
+        }
+        let firsts: Vec<usize> = trie.iter_paths().dedup_by_prefix(2).map(|(_, val)| *val).collect();
+        assert_eq!(firsts, vec![0, 1, 3, 5]);
+        let firsts: Vec<usize> = trie.iter_paths().dedup_by_prefix(4).map(|(_, val)| *val).collect();
+        assert_eq!(firsts, vec![0, 1, 3, 4, 5]);
+        assert_eq!(trie.iter_paths().dedup_by_prefix(0).count(), 1);
+        let firsts: Vec<usize> = trie.iter_prefix_relative("c/").dedup_by_prefix(2).map(|(_, val)| *val).collect();
+        assert_eq!(firsts, vec![5, 6]);
+        let firsts: Vec<usize> = trie.iter_prefix("c/").paths.dedup_by_prefix(4).map(|(_, val)| *val).collect();
+        assert_eq!(firsts, vec![5]);
+    }
+
+    #[test]
+    fn into_iterator() {
+        let mut trie: Trie<str, usize, 16> = Trie::new();
+        trie.insert("b", 2);
+        trie.insert("a", 1);
+        for (_, val) in &mut trie {
+            *val *= 10;
+        }
+        let mut keys = Vec::new();
+        for (key, _) in &trie {
+            keys.push(key);
+        }
+        assert_eq!(keys, vec!["a", "b"]);
+        let entries: Vec<(String, usize)> = trie.into_iter().collect();
+        assert_eq!(entries, vec![("a".to_string(), 10), ("b".to_string(), 20)]);
+    }
+
+    #[test]
+    fn range() {
+        let mut trie: Trie<str, usize, 16> = Trie::new();
+        for (i, key) in ["", "a", "b", "ba", "bb", "c", "d"].iter().enumerate() {
+            trie.insert(key, i);
+        }
+        let values = |iter: Range<'_, str, usize, 16>| iter.map(|(_, val)| *val).collect::<Vec<_>>();
+        assert_eq!(values(trie.range("b".."c")), vec![2, 3, 4]);
+        assert_eq!(values(trie.range("b"..="c")), vec![2, 3, 4, 5]);
+        assert_eq!(values(trie.range("bab".."z")), vec![4, 5, 6]);
+        assert_eq!(values(trie.range(.."b")), vec![0, 1]);
+        assert_eq!(trie.range(..).count(), 7);
+        let excluded = (Bound::Excluded("b"), Bound::Unbounded);
+        let keys: Vec<String> = trie.range(excluded).map(|(key, _)| key).collect();
+        assert_eq!(keys, vec!["ba", "bb", "c", "d"]);
+        assert_eq!(trie.range("c".."b").count(), 0);
+    }
+
+    #[test]
+    fn keys_values() {
+        let mut trie: Trie<str, usize, 16> = Trie::new();
+        trie.insert("b", 2);
+        trie.insert("a", 1);
+        for val in trie.values_mut() {
+            *val += 1;
+        }
+        assert_eq!(trie.keys().collect::<Vec<_>>(), vec!["a", "b"]);
+        assert_eq!(trie.values().copied().collect::<Vec<_>>(), vec![2, 3]);
+    }
+
+    #[test]
+    fn clear() {
+        let mut trie: Trie<str, usize, 16> = Trie::with_layout(NodeLayout::Sorted);
+        trie.enable_prefix_counts(2);
+        trie.enable_filter(10, 8);
+        trie.insert("a", 1);
+        trie.insert("ab", 2);
+        trie.clear();
+        assert!(trie.is_empty());
+        assert_eq!((trie.get("a"), trie.count_prefix("a"), trie.iter().count()), (None, 0, 0));
+        trie.insert("ab", 3);
+        assert_eq!((trie.count_prefix("a"), trie.layout()), (1, NodeLayout::Sorted));
+        assert!(trie.filter().unwrap().contains_path(&[6, 1, 6, 2]));
+        assert!(!trie.filter().unwrap().contains_path(&[6, 1]));
+    }
+
+    #[test]
+    fn retain() {
+        let mut trie: Trie<str, usize, 16> = Trie::new();
+        trie.enable_prefix_counts(2);
+        for (i, key) in ["a", "ab", "abc", "b", "bcd"].iter().enumerate() {
+            trie.insert(key, i);
+        }
+        let mut seen = Vec::new();
+        let removed = trie.retain(|key, val| {
+            seen.push(key.to_string());
+            *val *= 10;
+            key.len() != 3
+        });
+        assert_eq!(seen, vec!["a", "ab", "abc", "b", "bcd"]);
+        assert_eq!((removed, trie.len(), trie.count_prefix("a")), (2, 3, 2));
+        assert_eq!(trie.values().copied().collect::<Vec<_>>(), vec![0, 10, 30]);
+        // The root, the shared high nibble 6, then 1 and 6, 2 for "a" and "ab", and 2 for "b".
+        assert_eq!(trie.root.footprint().1, 6);
+        assert!(trie.self_test().is_healthy());
+    }
+
+    #[test]
+    fn from_iter_extend() {
+        let mut trie: Trie<str, usize, 16> = ["b", "a"].iter().map(|key| (key.to_string(), key.len())).collect();
+        trie.extend([("c".to_string(), 3), ("a".to_string(), 4)]);
+        let entries: Vec<(String, usize)> = trie.into_iter().collect();
+        assert_eq!(entries, vec![("a".to_string(), 4), ("b".to_string(), 1), ("c".to_string(), 3)]);
+        let bytes: Trie<[u8], (), 16> = vec![(vec![1, 2], ())].into_iter().collect();
+        assert_eq!(bytes.get(&[1, 2]), Some(&()));
+    }
+
+    #[test]
+    fn merge_iter() {
+        let mut first: Trie<str, usize, 16> = Trie::new();
+        first.insert("a", 1);
+        first.insert("c", 3);
+        let mut second: Trie<str, usize, 16> = Trie::new();
+        second.insert("b", 2);
+        second.insert("c", 30);
+        let empty: Trie<str, usize, 16> = Trie::new();
+        let values: Vec<usize> = Trie::merge_iter(&[&first, &empty, &second]).map(|(_, val)| *val).collect();
+        assert_eq!(values, vec![1, 2, 3, 30]);
+    }
+
+    #[test]
+    fn iter_owned() {
+        let mut trie: SharedTrie<str, String, 16> = Trie::new();
+        trie.insert_shared("b", "two".to_string());
+        trie.insert_shared("a", "one".to_string());
+        let iter = trie.iter_owned();
+        drop(trie);
+        let handle = std::thread::spawn(move || iter.map(|(key, val)| format!("{key}={val}")).collect::<Vec<_>>());
+        assert_eq!(handle.join().unwrap(), vec!["a=one", "b=two"]);
+    }
+
+    #[test]
+    fn get_uniform() {
+        let mut trie: Trie<str, usize, 16> = Trie::new();
+        trie.insert("token-a1", 1);
+        trie.insert("token", 2);
+        trie.insert("", 3);
+        for key in ["token-a1", "token", "", "token-a2", "tok", "other", "token-a1x"] {
+            assert_eq!(trie.get_uniform(key), trie.get(key), "{key}");
+        }
+    }
+
+    #[test]
+    fn clone_prefix() {
+        let mut trie: Trie<str, String, 16> = Trie::new();
+        for key in ["app", "apple", "apply", "banana", ""] {
+            trie.insert(key, key.to_uppercase());
+        }
+        let mut copy = trie.clone_prefix("app");
+        assert_eq!(copy.len(), 3);
+        assert_eq!(copy.get("apple").map(String::as_str), Some("APPLE"));
+        assert_eq!(copy.get("banana"), None);
+        copy.insert("apps", "APPS".to_string());
+        assert_eq!(trie.get("apps"), None);
+        assert_eq!(trie.clone_prefix("").len(), 5);
+        assert!(trie.clone_prefix("cherry").is_empty());
+
+        // Nodes left valueless by `take` come along with the copy.
+        let _ = trie.take("apply");
+        let copy = trie.clone_prefix("app");
+        assert_eq!(copy.last_key_value().map(|(key, _)| key), Some("apple".to_string()));
+        assert!(copy.self_test().is_healthy());
+    }
+
+    #[test]
+    fn remove_prefix() {
+        let mut trie: Trie<str, usize, 16> = Trie::new();
+        for (i, key) in ["ab", "tmp/a", "tmp/b", "tmq"].iter().enumerate() {
+            trie.insert(key, i);
+        }
+        let report = trie.remove_prefix("tmp/");
+        assert_eq!(report.values_removed, 2);
+        // "tmp" and "tmq" split on the low nibble of 'p': three nodes down to "tmp/", then a
+        // shared high nibble and one low nibble each for "a" and "b".
+        assert_eq!(report.nodes_freed, 3 + 1 + 2);
+        assert!(report.bytes_freed_estimate > 0);
+        assert_eq!(trie.len(), 2);
+        assert_eq!(trie.get("tmq"), Some(&3));
+        assert_eq!(trie.remove_prefix("zzz"), RemovalReport::default());
+    }
+
+    #[test]
+    fn prefix_counts() {
+        let mut trie: Trie<str, usize, 16> = Trie::new();
+        for (i, key) in ["/api/v1/a", "/api/v1/b", "/api/v2/a", "/web"].iter().enumerate() {
+            trie.insert(key, i);
+        }
+        trie.enable_prefix_counts(4);
+        trie.insert("/api/v1/c", 4);
+        trie.insert("/api/v1/c", 5);
+        assert_eq!(trie.remove("/web"), Some(3));
+        assert_eq!(trie.remove("/nope"), None);
+        assert_eq!(trie.count_prefix(""), 4);
+        assert_eq!(trie.count_prefix("/"), 4);
+        assert_eq!(trie.count_prefix("/api/v1/"), 3);
+        assert_eq!(trie.count_prefix("/w"), 0);
+        trie.disable_prefix_counts();
+        assert_eq!(trie.count_prefix("/a"), 4);
+
+        let mut users: Trie<str, (), 16> = Trie::new();
+        users.enable_prefix_counts(usize::MAX);
+        for i in 0..200 {
+            users.insert(&format!("user:{}:{i}", i % 7), ());
+        }
+        users.remove("user:3:3");
+        users.take("user:3:10");
+        users.remove_prefix("user:4:");
+        users.retain(|key, _| !key.ends_with('9'));
+        assert_eq!(users.count_prefix("user:3:"), users.iter_prefix("user:3:").count());
+        assert_eq!(users.count_prefix("user:3:1"), users.iter_prefix("user:3:1").count());
+        assert_eq!(users.count_prefix("user:"), users.len());
+        assert!(users.self_test().is_healthy());
+
+        let mut hits: Trie<str, u64, 16> = Trie::new();
+        hits.enable_prefix_counts(usize::MAX);
+        assert_eq!(hits.fetch_add("/a", 2), 0);
+        *hits.get_or_insert_with("/b", || 5) += 1;
+        let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            hits.get_or_insert_with("/c", || panic!("no default"));
+        }));
+        assert!(panicked.is_err());
+        assert_eq!((hits.len(), hits.count_prefix("/"), hits.get("/b")), (2, 2, Some(&6)));
+        assert!(hits.self_test().is_healthy() && hits.last_key_value().is_some_and(|(key, _)| key == "/b"));
+    }
+
+    #[test]
+    fn approx_count_prefix() {
+        let mut trie: Trie<str, usize, 16> = Trie::new();
+        for i in 0..100 {
+            trie.insert(&format!("k{i}"), i);
+        }
+        assert_eq!(trie.approx_count_prefix("k", 10_000), (100, true));
+        let (lower, exact) = trie.approx_count_prefix("k", 50);
+        assert!(!exact && lower < 100);
+        assert_eq!(trie.approx_count_prefix("x", 1), (0, true));
+        trie.enable_prefix_counts(4);
+        assert_eq!(trie.approx_count_prefix("k", 1), (100, true));
+    }
+
+    #[test]
+    fn recount() {
+        let mut trie: Trie<str, usize, 16> = Trie::new();
+        trie.insert("a", 1);
+        trie.insert("ab", 2);
+        trie.len = 7;
+        assert_eq!(trie.recount(), 2);
+        assert_eq!(trie.len(), 2);
+    }
+
+    #[test]
+    fn content_hash() {
+        use std::collections::hash_map::DefaultHasher;
+        use std::collections::HashSet;
+
+        let mut first: Trie<str, usize, 16> = Trie::new();
+        let mut second: Trie<str, usize, 16> = Trie::new();
+        for (key, val) in [("a", 1), ("b", 2), ("ab", 3)] {
+            first.insert(key, val);
+        }
+        for (key, val) in [("ab", 3), ("b", 2), ("a", 1)] {
+            second.insert(key, val);
+        }
+        assert_eq!(first.content_hash::<DefaultHasher>(), second.content_hash::<DefaultHasher>());
+        let mut replicas = HashSet::new();
+        replicas.insert(first.clone());
+        assert!(!replicas.insert(second.clone()));
+        second.insert("b", 4);
+        assert_ne!(first.content_hash::<DefaultHasher>(), second.content_hash::<DefaultHasher>());
+        let unordered = first.unordered_content_hash::<DefaultHasher>();
+        let path = <Trie<str, usize, 16> as TriePathBuilder<str>>::build_path("b");
+        second.remove("b");
+        let partial = second.unordered_content_hash::<DefaultHasher>();
+        assert_eq!(partial.wrapping_add(entry_hash::<DefaultHasher, usize>(&path, &2)), unordered);
+    }
+
+    #[test]
+    fn keys_cmp() {
+        let build = |keys: &[&str]| {
+            let mut trie: Trie<str, usize, 16> = Trie::new();
+            for (i, key) in keys.iter().enumerate() {
+                trie.insert(key, i);
+            }
+            trie
+        };
+        let cases: [&[&str]; 7] = [&[], &[""], &["", "a"], &["a"], &["a", "ab"], &["a", "b"], &["ab"]];
+        for left in cases {
+            for right in cases {
+                let (a, b) = (build(left), build(right));
+                let expected: Vec<Vec<usize>> = a.iter_paths().map(|(path, _)| path).collect();
+                let actual: Vec<Vec<usize>> = b.iter_paths().map(|(path, _)| path).collect();
+                assert_eq!(a.keys_cmp(&b), expected.cmp(&actual), "{left:?} vs {right:?}");
+                assert_eq!(a.keys_eq(&b), left == right);
+            }
+        }
+        let mut other: Trie<str, String, 16> = Trie::new();
+        other.insert("a", "x".to_string());
+        other.insert("ab", "y".to_string());
+        assert!(build(&["ab", "a"]).keys_eq(&other));
+    }
+
+    #[test]
+    fn raw_paths() {
+        let mut trie: Trie<str, usize, 16> = Trie::new();
+        trie.insert("a", 1);
+        assert_eq!(trie.get_path(&[6, 1]), Some(&1));
+        assert_eq!(trie.insert_path(&[6, 2], 2), None);
+        assert_eq!(trie.get("b"), Some(&2));
+        assert_eq!(trie.get_path(&[6, 16]), None);
+        assert_eq!(trie.delete_path(&[16]), None);
+        let paths: Vec<Vec<usize>> = trie.iter_paths().map(|(path, _)| path).collect();
+        for path in paths {
+            *trie.get_path_mut(&path).unwrap() += 10;
+        }
+        assert_eq!(trie.delete_path(&[6, 1]), Some(11));
+        assert!(std::panic::catch_unwind(move || trie.insert_path(&[16], 3)).is_err());
+    }
+
+    #[test]
+    fn decode_errors() {
+        type StrTrie = Trie<str, usize, 16>;
+        assert_eq!(StrTrie::decode_path(&[6, 1, 6, 2]), Ok("ab".to_string()));
+        assert_eq!(StrTrie::decode_path(&[6]), Err(DecodeError::Truncated { len: 1 }));
+        assert_eq!(StrTrie::decode_path(&[6, 16]), Err(DecodeError::IndexOutOfRange { position: 1, index: 16 }));
+        assert!(matches!(StrTrie::decode_path(&[15, 15]), Err(DecodeError::InvalidUtf8(_))));
+
+        let mut trie = StrTrie::new();
+        trie.insert_path(&[15, 15], 1);
+        assert!(matches!(trie.try_iter_owned(), Err(DecodeError::InvalidUtf8(_))));
+    }
+
+    #[test]
+    fn streamed_paths() {
+        fn check<K: ?Sized, const N: usize>(key: &K)
+        where
+            Trie<K, (), N>: TriePathBuilder<K>,
+        {
+            let streamed: Vec<usize> = Trie::<K, (), N>::path_iter(key).collect();
+            assert_eq!(streamed, Trie::<K, (), N>::build_path(key));
+        }
+        check::<str, 16>("héllo");
+        check::<str, 256>("héllo");
+        check::<[u8], 16>(&[0, 0x9f, 255]);
+        check::<[u8], 256>(&[0, 0x9f, 255]);
+
+        // The streamed walks and the slice-based ones used with prefix counts agree.
+        let mut counted: Trie<str, usize, 16> = Trie::new();
+        counted.enable_prefix_counts(3);
+        let mut streamed: Trie<str, usize, 16> = Trie::new();
+        for (i, key) in ["ab", "a", "abc", "b"].iter().enumerate() {
+            assert_eq!(counted.insert(key, i), streamed.insert(key, i));
+        }
+        for key in ["abc", "x", "a", "a"] {
+            assert_eq!(counted.remove(key), streamed.remove(key));
+        }
+        assert!(counted.iter_paths().eq(streamed.iter_paths()));
+        assert_eq!((streamed.len(), streamed.root.footprint().1), (2, 6));
+    }
+
+    #[test]
+    fn owned_keys_round_trip() {
+        fn round_trip<K: ?Sized + ToOwned, const N: usize>(key: &K) -> K::Owned
+        where
+            Trie<K, (), N>: TriePathBuilder<K> + TriePathDecoder<K>,
+        {
+            let owned = Trie::<K, (), N>::decode_path(&Trie::<K, (), N>::build_path(key)).unwrap();
+            assert_eq!(Trie::<K, (), N>::build_path(std::borrow::Borrow::borrow(&owned)), Trie::<K, (), N>::build_path(key));
+            owned
+        }
+        let owned: String = round_trip::<str, 16>("clé");
+        assert_eq!(owned, "clé");
+        let owned: Vec<u8> = round_trip::<[u8], 256>(&[0, 255]);
+        assert_eq!(owned, [0, 255]);
+    }
+
+    #[test]
+    fn byte_trie() {
+        let mut bytes: ByteTrie<usize> = ByteTrie::new();
+        let mut nibbles: Trie<[u8], usize, 16> = Trie::new();
+        for (i, key) in [&b"\xffzz"[..], b"abc", b"ab", b""].iter().enumerate() {
+            bytes.insert(key, i);
+            nibbles.insert(key, i);
+        }
+        assert_eq!((bytes.get(b"ab"), bytes.get(b"a"), bytes.count_prefix(b"a")), (Some(&2), None, 2));
+        assert_eq!((bytes.longest_prefix(b"abz"), nibbles.longest_prefix(b"abz")), (Some((2, &2)), Some((4, &2))));
+        let keys: Vec<Vec<u8>> = bytes.iter().map(|(key, _)| key).collect();
+        assert_eq!(keys, nibbles.iter().map(|(key, _)| key).collect::<Vec<_>>());
+        // One node per byte instead of two, plus the root.
+        assert_eq!((bytes.root.footprint().1, nibbles.root.footprint().1), (7, 13));
+
+        type StrTrie = Trie<str, usize, 256>;
+        assert_eq!(StrTrie::decode_path(&[0x61, 0x62]), Ok("ab".to_string()));
+        assert_eq!(StrTrie::decode_path(&[0x61, 256]), Err(DecodeError::IndexOutOfRange { position: 1, index: 256 }));
+    }
+
+    #[test]
+    fn clone_is_deep() {
+        let mut trie: Trie<str, Vec<usize>, 16> = Trie::with_layout(NodeLayout::Sorted);
+        trie.enable_prefix_counts(2);
+        trie.enable_filter(4, 10);
+        trie.insert("a", vec![1]);
+        trie.insert("ab", vec![2]);
+        let mut copy = trie.clone();
+        copy.get_mut("a").unwrap().push(3);
+        copy.insert("b", vec![4]);
+        assert_eq!((trie.get("a"), trie.get("b"), trie.len()), (Some(&vec![1]), None, 2));
+        assert_eq!((copy.get("a"), copy.len(), copy.layout()), (Some(&vec![1, 3]), 3, NodeLayout::Sorted));
+        assert_eq!((copy.count_prefix(""), copy.count_prefix("a")), (3, 2));
+        assert!(copy.filter().is_some());
+    }
+
+    #[test]
+    fn debug_shows_entries() {
+        let mut trie: Trie<str, usize, 16> = Trie::new();
+        assert_eq!(format!("{trie:?}"), "{}");
+        trie.insert("apple", 2);
+        trie.insert("app", 1);
+        assert_eq!(format!("{trie:?}"), r#"{"app": 1, "apple": 2}"#);
+        trie.insert_path(&[15], 3);
+        assert_eq!(format!("{trie:?}"), r#"{"app": 1, "apple": 2, [15]: 3}"#);
+    }
+
+    #[test]
+    fn structural_equality() {
+        let mut a: Trie<str, usize, 16> = Trie::new();
+        let mut b: Trie<str, usize, 16> = Trie::with_layout(NodeLayout::Adaptive);
+        a.insert("one", 1);
+        a.insert("two", 2);
+        b.insert("two", 2);
+        b.insert("three", 3);
+        b.insert("one", 1);
+        assert_ne!(a, b);
+        b.remove("three");
+        assert_eq!(a, b);
+        b.insert("two", 20);
+        assert_ne!(a, b);
+        assert_eq!(Trie::<str, usize, 16>::new(), Trie::new());
+    }
+
+    #[test]
+    fn first_and_last() {
+        let mut trie: Trie<str, usize, 16> = Trie::new();
+        assert_eq!(trie.first_key_value(), None);
+        assert_eq!(trie.pop_last(), None);
+        for (i, key) in ["m", "mo", "a", "zz", "z"].iter().enumerate() {
+            trie.insert(key, i);
+        }
+        assert_eq!(trie.first_key_value(), Some(("a".to_string(), &2)));
+        assert_eq!(trie.last_key_value(), Some(("zz".to_string(), &3)));
+        assert_eq!(trie.pop_last(), Some(("zz".to_string(), 3)));
+        assert_eq!(trie.pop_first(), Some(("a".to_string(), 2)));
+        assert_eq!((trie.first_path().unwrap().1, trie.last_path().unwrap().1, trie.len()), (&0, &4, 3));
+
+        // A valueless leaf left by `take` is stepped over.
+        assert_eq!(trie.take("z"), Some(4));
+        assert_eq!(trie.last_key_value(), Some(("mo".to_string(), &1)));
+    }
+
+    #[test]
+    fn successor_and_predecessor() {
+        let mut trie: Trie<u32, char, 16> = Trie::new();
+        for (key, val) in [(10, 'a'), (20, 'b'), (35, 'c'), (1000, 'd')] {
+            trie.insert(&key, val);
+        }
+        assert_eq!(trie.next_after(&20), Some((35, &'c')));
+        assert_eq!(trie.next_after(&21), Some((35, &'c')));
+        assert_eq!(trie.next_after(&1000), None);
+        assert_eq!(trie.prev_before(&20), Some((10, &'a')));
+        assert_eq!(trie.prev_before(&999), Some((35, &'c')));
+        assert_eq!(trie.prev_before(&10), None);
+
+        let mut words: Trie<str, usize, 16> = Trie::new();
+        for (i, key) in ["b", "ba", "bab", "c"].iter().enumerate() {
+            words.insert(key, i);
+        }
+        let prev = |key: &str| words.prev_before(key).map(|(key, _)| key);
+        assert_eq!((prev("bb"), prev("bab"), prev("ba"), prev("b")), (Some("bab".into()), Some("ba".into()), Some("b".into()), None));
+        assert_eq!(words.next_after("bab").map(|(key, _)| key), Some("c".into()));
+        assert_eq!(words.prev_path_before(&[15, 15]).map(|(_, val)| *val), Some(3));
+    }
+
+    #[test]
+    fn common_prefix_search() {
+        let mut trie: ByteTrie<&str> = Trie::new();
+        for word in ["a", "ap", "app", "apply", "b"] {
+            trie.insert(word.as_bytes(), word);
+        }
+        let found: Vec<(usize, &str)> = trie.common_prefix_search(b"application").map(|(len, val)| (len, *val)).collect();
+        assert_eq!(found, vec![(1, "a"), (2, "ap"), (3, "app")]);
+        assert_eq!(trie.common_prefix_search(b"apply").count(), 4);
+        assert_eq!(trie.common_prefix_search(b"").count(), 0);
+
+        let mut nibbles: Trie<str, usize, 16> = Trie::new();
+        nibbles.insert("", 0);
+        nibbles.insert("to", 1);
+        assert_eq!(nibbles.common_prefix_search("tokenize").collect::<Vec<_>>(), vec![(0, &0), (4, &1)]);
+    }
+
+    #[test]
+    fn longest_common_prefix() {
+        let mut trie: Trie<str, usize, 16> = Trie::new();
+        assert_eq!(trie.longest_common_prefix(), None);
+        for (i, key) in ["internal", "internet", "interval", "intern"].iter().enumerate() {
+            trie.insert(key, i);
+        }
+        assert_eq!(trie.longest_common_prefix(), Some("inter".to_string()));
+        assert_eq!(trie.longest_common_prefix_under("intern"), Some("intern".to_string()));
+        assert_eq!(trie.longest_common_prefix_under("interna"), Some("internal".to_string()));
+        assert_eq!(trie.longest_common_prefix_under("x"), None);
+
+        // "internal" and "internet" also share the high nibble of 'a' and 'e', which is cut off.
+        trie.remove("interval");
+        assert_eq!(trie.take("intern"), Some(3));
+        assert_eq!(trie.longest_common_prefix_path(&[]).map(|path| path.len()), Some(13));
+        assert_eq!(trie.longest_common_prefix(), Some("intern".to_string()));
+        let mut numbers: Trie<u32, (), 16> = Trie::new();
+        numbers.insert(&0x1234, ());
+        numbers.insert(&0x1299, ());
+        assert_eq!((numbers.longest_common_prefix_path(&[]).map(|path| path.len()), numbers.longest_common_prefix()), (Some(6), None));
+    }
+
+    #[test]
+    fn remove_entry() {
+        let mut trie: Trie<str, usize, 16> = Trie::new();
+        trie.insert("key", 1);
+        trie.insert("keys", 2);
+        assert_eq!(trie.remove_entry("key"), Some(("key".to_string(), 1)));
+        assert_eq!(trie.remove_entry("key"), None);
+        #[allow(deprecated)]
+        let old = trie.delete("keys");
+        assert_eq!((old, trie.is_empty()), (Some(2), true));
+    }
+
+    #[test]
+    fn get_or_insert_with() {
+        let mut trie: Trie<str, Vec<usize>, 16> = Trie::new();
+        let mut calls = 0;
+        for (i, key) in ["fib", "fact", "fib"].iter().enumerate() {
+            trie.get_or_insert_with(key, || {
+                calls += 1;
+                Vec::new()
+            })
+            .push(i);
+        }
+        assert_eq!((calls, trie.len(), trie.get("fib")), (2, 2, Some(&vec![0, 2])));
+
+        trie.enable_prefix_counts(4);
+        trie.enable_filter(8, 10);
+        trie.get_or_insert_with("fig", Vec::new).push(3);
+        assert_eq!((trie.count_prefix("fi"), trie.get("fig")), (2, Some(&vec![3])));
+        trie.get_or_insert_with("fib", Vec::new).push(4);
+        trie.get_or_insert_with("f", Vec::new).push(5);
+        assert_eq!((trie.count_prefix("f"), trie.count_prefix("fi"), trie.count_prefix("")), (4, 2, 4));
+    }
+
+    #[test]
+    fn fetch_ops() {
+        let mut trie: Trie<str, i64, 16> = Trie::new();
+        trie.enable_prefix_counts(2);
+        assert_eq!(trie.fetch_add("hits", 5), 0);
+        assert_eq!(trie.fetch_add("hits", 2), 5);
+        assert_eq!(trie.fetch_sub("hits", 3), 7);
+        assert_eq!(trie.fetch_max("peak", -4), 0);
+        assert_eq!(trie.fetch_min("peak", -4), 0);
+        assert_eq!(trie.get("hits"), Some(&4));
+        assert_eq!(trie.get("peak"), Some(&-4));
+        assert_eq!(trie.len(), 2);
+        assert_eq!(trie.count_prefix(""), 2);
+    }
+
+    #[test]
+    fn with_prefix() {
+        let mut trie: Trie<str, usize, 16> = Trie::new();
+        for (i, key) in ["car", "cart", "cat", "dog"].iter().enumerate() {
+            trie.insert(key, i);
+        }
+        assert_eq!(trie.keys_with_prefix("car"), vec!["car", "cart"]);
+        assert_eq!(trie.values_with_prefix("ca"), vec![&0, &1, &2]);
+        assert!(trie.keys_with_prefix("cow").is_empty());
+        assert_eq!(trie.values_with_prefix("").len(), 4);
+    }
+
+    #[test]
+    fn relative_prefix() {
+        let mut trie: Trie<str, usize, 16> = Trie::new();
+        for (i, key) in ["ns/a", "ns/b/c", "nt"].iter().enumerate() {
+            trie.insert(key, i);
+        }
+        let suffixes = trie.suffixes_with_prefix("ns/");
+        assert_eq!(suffixes, vec![("a".to_string(), &0), ("b/c".to_string(), &1)]);
+        assert_eq!(trie.iter_prefix_relative("ns/b").next(), Some((vec![2, 15, 6, 3], &1)));
+        assert_eq!(trie.iter_prefix_relative("x").count(), 0);
+    }
+
+    #[test]
+    fn scan_prefixes() {
+        let mut trie: Trie<str, usize, 16> = Trie::new();
+        for (i, word) in ["北", "北京", "北京大学", "大学"].iter().enumerate() {
+            trie.insert(word, i);
+        }
+        let text = "在北京大学".as_bytes();
+        let matches: Vec<(usize, usize)> = trie.scan_prefixes(text, 3).into_iter().map(|(len, val)| (len, *val)).collect();
+        assert_eq!(matches, vec![(3, 0), (6, 1), (12, 2)]);
+        assert!(trie.scan_prefixes(text, 0).is_empty());
+        assert!(trie.scan_prefixes(text, 99).is_empty());
+    }
+
+    #[test]
+    fn delete_entry() {
+        let mut trie: Trie<[u8], usize, 16> = Trie::new();
+        trie.insert(b"key", 1);
+        assert_eq!(trie.delete_entry(b"kex"), None);
+        assert_eq!(trie.delete_entry(b"key"), Some((b"key".to_vec(), 1)));
+        assert!(trie.is_empty());
+    }
+
+    #[test]
+    fn into_keys_values() {
+        let mut trie: Trie<str, String, 16> = Trie::new();
+        trie.insert("b", "two".to_string());
+        trie.insert("a", "one".to_string());
+        let mut other: Trie<str, String, 16> = Trie::new();
+        other.insert("c", "three".to_string());
+        assert_eq!(other.into_keys().collect::<Vec<_>>(), vec!["c"]);
+        assert_eq!(trie.into_values().rev().collect::<Vec<_>>(), vec!["two", "one"]);
+    }
+
+    #[test]
+    fn filter() {
+        let mut trie: Trie<str, usize, 16> = Trie::new();
+        trie.insert("before", 1);
+        trie.enable_filter(100, 10);
+        trie.insert("after", 2);
+        assert_eq!(trie.get("before"), Some(&1));
+        assert_eq!(trie.get("after"), Some(&2));
+        assert_eq!(trie.get("missing"), None);
+        assert_eq!(trie.remove("before"), Some(1));
+        trie.rebuild_filter();
+        assert_eq!(trie.get("before"), None);
+        assert_eq!(trie.get_mut("after"), Some(&mut 2));
+        trie.disable_filter();
+        assert!(trie.filter().is_none());
+        let exported = trie.to_filter(10);
+        assert!(exported.contains_path(&<Trie<str, usize, 16> as TriePathBuilder<str>>::build_path("after")));
+        assert_eq!(trie.get("after"), Some(&2));
+    }
+
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn zeroize() {
+        use zeroize::Zeroize;
+
+        let mut trie: Trie<str, Vec<u8>, 16> = Trie::new();
+        assert!(!trie.insert_zeroizing("token", vec![1, 2, 3]));
+        assert!(trie.insert_zeroizing("token", vec![4, 5, 6]));
+        assert!(trie.delete_zeroizing("token"));
+        assert!(!trie.delete_zeroizing("token"));
+
+        trie.insert("a", vec![7]);
+        trie.insert("ab", vec![8]);
+        trie.zeroize();
+        assert!(trie.is_empty());
+        assert_eq!(trie.get("a"), None);
+    }
+
+    // Counts the times a value is wiped.
+    #[cfg(feature = "zeroize")]
+    #[derive(Clone)]
+    struct Probe(std::sync::Arc<std::sync::atomic::AtomicUsize>);
+
+    #[cfg(feature = "zeroize")]
+    impl zeroize::Zeroize for Probe {
+        fn zeroize(&mut self) {
+            self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        }
+    }
+
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn secret_values_wiped_on_drop() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        use zeroize::Zeroizing;
+
+        use crate::trie::SecretTrie;
+
+        let wiped = Arc::new(AtomicUsize::new(0));
+        let secret = || Zeroizing::new(Probe(Arc::clone(&wiped)));
+        let mut trie: SecretTrie<str, Probe, 16> = Trie::new();
+        trie.insert("a", secret());
+        drop(trie.insert("a", secret()));
+        assert_eq!(wiped.load(Ordering::SeqCst), 1);
+        trie.remove("a");
+        trie.insert("b", secret());
+        trie.insert("bc", secret());
+        trie.remove_prefix("bc");
+        trie.insert("c", secret());
+        assert_eq!(wiped.load(Ordering::SeqCst), 3);
+        drop(trie);
+        assert_eq!(wiped.load(Ordering::SeqCst), 5);
     }
 }
\ No newline at end of file
@@ -1,64 +1,137 @@
+use std::cmp::Ordering;
+use std::mem;
+
 use bitmaps::Bitmap;
 
 const BITMAP_SIZE: usize = 64;
+// Most children an adaptive node keeps in its smallest representation.
+const SMALL_CHILDREN: usize = 4;
+
+/// How a node stores its children.
+///
+/// `Dense` keeps a full `N`-slot array (allocated once the node gets its first child) for
+/// constant-time child access. `Packed` keeps only the present children, ordered by index and
+/// located through the child bitmap; it needs `N <= 64` and falls back to `Sorted` otherwise.
+/// `Sorted` keeps `(index, child)` pairs in a sorted vector found by binary search, or in
+/// access-frequency order after `Trie::retune`. `Adaptive` picks one of those per node by its
+/// number of children, as in an adaptive radix tree: up to 4 children are kept sorted, up to
+/// `N / 2` packed, and more in a full array, converting as children are added and removed. Above
+/// `N = 64` there is no packed step and nodes go from sorted to dense past `N / 4` children.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum NodeLayout {
+    Dense,
+    Packed,
+    Sorted,
+    Adaptive,
+}
+
+impl NodeLayout {
+    // From the dictionary test at N = 16, dense nodes are roughly 20% faster overall than packed
+    // ones and twice as fast on lookups, so they stay the default there. Wider nodes are mostly
+    // empty and their arrays cost far more memory than they save time.
+    #[must_use]
+    pub const fn default_for(n: usize) -> NodeLayout {
+        if n <= 16 {
+            NodeLayout::Dense
+        } else if n <= BITMAP_SIZE {
+            NodeLayout::Packed
+        } else {
+            NodeLayout::Sorted
+        }
+    }
+}
+
+// Result of comparing two key sequences, keeping apart a proper prefix from a real difference.
+pub(crate) enum KeysOrdering {
+    Equal,
+    Differ(Ordering),
+    LeftPrefix,
+    RightPrefix,
+}
+
+impl KeysOrdering {
+    pub(crate) fn into_ordering(self) -> Ordering {
+        match self {
+            KeysOrdering::Equal => Ordering::Equal,
+            KeysOrdering::Differ(ordering) => ordering,
+            KeysOrdering::LeftPrefix => Ordering::Less,
+            KeysOrdering::RightPrefix => Ordering::Greater,
+        }
+    }
+}
+
+type ChildSlots<T, const N: usize> = [Option<Box<TrieNode<T, N>>>; N];
+
+enum Children<T, const N: usize> {
+    Dense(Option<Box<ChildSlots<T, N>>>),
+    Packed(Vec<Box<TrieNode<T, N>>>),
+    Sorted(Vec<(usize, Box<TrieNode<T, N>>)>),
+    // The `Sorted` layout after `rank_children`: the same pairs, hottest first, found by a
+    // linear scan that usually stops at the front.
+    Ranked(Vec<(usize, Box<TrieNode<T, N>>)>),
+    // The `Adaptive` layout, stored like `Sorted`, `Packed` and `Dense` respectively and moved
+    // between them by `readapt`.
+    Small(Vec<(usize, Box<TrieNode<T, N>>)>),
+    Indexed(Vec<Box<TrieNode<T, N>>>),
+    Full(Option<Box<ChildSlots<T, N>>>),
+}
 
 pub(crate) struct TrieNode<T, const N: usize> {
     child_bits: Bitmap<BITMAP_SIZE>,
     value: Option<T>,
-    next: [Option<Box<TrieNode<T, N>>>; N],
+    // Values in this subtree; only kept up to date above the trie's count depth.
+    count: usize,
+    next: Children<T, N>,
 }
 
 impl<T, const N: usize> TrieNode<T, N> {
     #[must_use]
     pub(crate) fn new() -> TrieNode<T, N> {
+        TrieNode::with_layout(NodeLayout::default_for(N))
+    }
+
+    #[must_use]
+    pub(crate) fn with_layout(layout: NodeLayout) -> TrieNode<T, N> {
+        let next = match layout {
+            NodeLayout::Dense => Children::Dense(None),
+            NodeLayout::Packed if N <= BITMAP_SIZE => Children::Packed(Vec::new()),
+            NodeLayout::Packed | NodeLayout::Sorted => Children::Sorted(Vec::new()),
+            NodeLayout::Adaptive => Children::Small(Vec::new()),
+        };
         TrieNode {
             child_bits: Bitmap::new(),
             value: const { None },
-            next: [const { None }; N],
+            count: 0,
+            next,
         }
     }
 
-    pub(crate) fn has_child(&self) -> bool {
-        if const { N <= BITMAP_SIZE } {
-            !self.child_bits.is_empty()
-        } else {
-            for i in 0..N {
-                if self.child(i).is_some() {
-                    return true;
-                }
-            }
-            false
+    pub(crate) fn layout(&self) -> NodeLayout {
+        match self.next {
+            Children::Dense(_) => NodeLayout::Dense,
+            Children::Packed(_) => NodeLayout::Packed,
+            Children::Sorted(_) | Children::Ranked(_) => NodeLayout::Sorted,
+            Children::Small(_) | Children::Indexed(_) | Children::Full(_) => NodeLayout::Adaptive,
         }
     }
 
+    pub(crate) fn has_child(&self) -> bool {
+        self.count_children() > 0
+    }
+
     pub(crate) fn has_multiple_children(&self) -> bool {
-        if const { N <= BITMAP_SIZE } {
-            self.child_bits.len() > 1
-        } else {
-            let mut count = 0;
-            for i in 0..N {
-                if self.child(i).is_some() {
-                    if count > 0 {
-                        return true;
-                    }
-                    count += 1;
-                }
-            }
-            false
-        }
+        self.count_children() > 1
     }
 
     pub(crate) fn count_children(&self) -> usize {
         if const { N <= BITMAP_SIZE } {
-            self.child_bits.len()
-        } else {
-            let mut count = 0;
-            for i in 0..N {
-                if self.child(i).is_some() {
-                    count += 1;
-                }
-            }
-            count
+            return self.child_bits.len();
+        }
+        match &self.next {
+            Children::Dense(None) | Children::Full(None) => 0,
+            Children::Dense(Some(next)) | Children::Full(Some(next)) => next.iter().flatten().count(),
+            Children::Packed(next) | Children::Indexed(next) => next.len(),
+            Children::Sorted(next) | Children::Ranked(next) | Children::Small(next) => next.len(),
         }
     }
 
@@ -79,43 +152,524 @@ impl<T, const N: usize> TrieNode<T, N> {
     }
 
     pub(crate) fn child(&self, index: usize) -> Option<&TrieNode<T, N>> {
-        self.next[index].as_deref()
+        match &self.next {
+            Children::Dense(next) | Children::Full(next) => next.as_ref()?[index].as_deref(),
+            Children::Packed(next) | Children::Indexed(next) => self.child_bits.get(index).then(|| &*next[self.rank(index)]),
+            Children::Sorted(next) | Children::Small(next) => {
+                let pos = next.binary_search_by_key(&index, |(i, _)| *i).ok()?;
+                Some(&next[pos].1)
+            }
+            Children::Ranked(next) => next.iter().find(|(i, _)| *i == index).map(|(_, child)| &**child),
+        }
     }
 
-    pub(crate) fn child_mut<'a>(&'a mut self, index: usize) -> Option<&'a mut TrieNode<T, N>> {
-        self.next[index].as_deref_mut()
+    pub(crate) fn child_mut(&mut self, index: usize) -> Option<&mut TrieNode<T, N>> {
+        self.child_mut_with_count(index).0
+    }
+
+    // `child_mut` along with this node's cached count, borrowed apart so a walk down the trie can
+    // hold on to the counts it passed until it knows whether a value was added.
+    pub(crate) fn child_mut_with_count(&mut self, index: usize) -> (Option<&mut TrieNode<T, N>>, &mut usize) {
+        let rank = self.rank(index);
+        let child = match &mut self.next {
+            Children::Dense(next) | Children::Full(next) => next.as_mut().and_then(|next| next[index].as_deref_mut()),
+            Children::Packed(next) | Children::Indexed(next) => self.child_bits.get(index).then(|| &mut *next[rank]),
+            Children::Sorted(next) | Children::Small(next) => {
+                let pos = next.binary_search_by_key(&index, |(i, _)| *i).ok();
+                pos.map(|pos| &mut *next[pos].1)
+            }
+            Children::Ranked(next) => next.iter_mut().find(|(i, _)| *i == index).map(|(_, child)| &mut **child),
+        };
+        (child, &mut self.count)
     }
 
     pub(crate) fn child_take(&mut self, index: usize) -> Option<TrieNode<T, N>> {
+        let present = N <= BITMAP_SIZE && self.child_bits.get(index);
+        let rank = self.rank(index);
         if const { N <= BITMAP_SIZE } {
             self.child_bits.set(index, false);
         }
-        Some(*self.next[index].take()?)
+        let node = match &mut self.next {
+            Children::Dense(next) | Children::Full(next) => next.as_mut()?[index].take()?,
+            Children::Packed(next) | Children::Indexed(next) => {
+                if !present {
+                    return None;
+                }
+                next.remove(rank)
+            }
+            Children::Sorted(next) | Children::Small(next) => {
+                let pos = next.binary_search_by_key(&index, |(i, _)| *i).ok()?;
+                next.remove(pos).1
+            }
+            Children::Ranked(next) => {
+                let pos = next.iter().position(|(i, _)| *i == index)?;
+                next.remove(pos).1
+            }
+        };
+        self.readapt(self.count_children());
+        Some(*node)
     }
 
     pub(crate) fn child_replace(&mut self, index: usize, node: TrieNode<T, N>) -> Option<TrieNode<T, N>> {
+        self.put(index, Box::new(node)).0.map(|node| *node)
+    }
+
+    pub(crate) fn child_set(&mut self, index: usize, node: TrieNode<T, N>) -> &mut TrieNode<T, N> {
+        self.put(index, Box::new(node)).1
+    }
+
+    // Smallest child index strictly greater than `after`, or the first child index for `None`.
+    pub(crate) fn next_index(&self, after: Option<usize>) -> Option<usize> {
         if const { N <= BITMAP_SIZE } {
-            self.child_bits.set(index, true);
+            return match after {
+                None => self.child_bits.first_index(),
+                Some(index) => self.child_bits.next_index(index),
+            };
+        }
+        let start = after.map_or(0, |index| index + 1);
+        match &self.next {
+            Children::Dense(None) | Children::Full(None) => None,
+            Children::Dense(Some(next)) | Children::Full(Some(next)) => (start..N).find(|&i| next[i].is_some()),
+            Children::Packed(_) | Children::Indexed(_) => unreachable!(),
+            Children::Sorted(next) | Children::Small(next) => {
+                let pos = next.partition_point(|(i, _)| *i < start);
+                next.get(pos).map(|(i, _)| *i)
+            }
+            Children::Ranked(next) => next.iter().map(|(i, _)| *i).filter(|&i| i >= start).min(),
         }
-        Some(*(self.next[index].replace(Box::new(node))?))
     }
 
-    pub(crate) fn child_set(&mut self, index: usize, node: TrieNode<T, N>) -> &mut TrieNode<T, N> {
+    // Largest child index strictly less than `before`, or the last child index for `None`.
+    pub(crate) fn prev_index(&self, before: Option<usize>) -> Option<usize> {
         if const { N <= BITMAP_SIZE } {
-            self.child_bits.set(index, true);
+            return match before {
+                None => self.child_bits.last_index(),
+                Some(index) => self.child_bits.prev_index(index),
+            };
+        }
+        let end = before.unwrap_or(N);
+        match &self.next {
+            Children::Dense(None) | Children::Full(None) => None,
+            Children::Dense(Some(next)) | Children::Full(Some(next)) => (0..end).rev().find(|&i| next[i].is_some()),
+            Children::Packed(_) | Children::Indexed(_) => unreachable!(),
+            Children::Sorted(next) | Children::Small(next) => {
+                let pos = next.partition_point(|(i, _)| *i < end);
+                pos.checked_sub(1).map(|pos| next[pos].0)
+            }
+            Children::Ranked(next) => next.iter().map(|(i, _)| *i).filter(|&i| i < end).max(),
+        }
+    }
+
+    pub(crate) fn child_indices(&self) -> impl Iterator<Item = usize> + '_ {
+        std::iter::successors(self.next_index(None), |&index| self.next_index(Some(index)))
+    }
+
+    // Whether both subtrees hold exactly the same relative key paths, ignoring the values.
+    pub(crate) fn keys_eq<U>(&self, other: &TrieNode<U, N>) -> bool {
+        self.value().is_some() == other.value().is_some()
+            && self.child_indices().eq(other.child_indices())
+            && self
+                .child_indices()
+                .all(|index| self.child(index).unwrap().keys_eq(other.child(index).unwrap()))
+    }
+
+    // Compares the sorted key sequences of both subtrees lexicographically, without collecting them.
+    pub(crate) fn keys_cmp<U>(&self, other: &TrieNode<U, N>) -> KeysOrdering {
+        match (self.value().is_some(), other.value().is_some()) {
+            (true, false) if other.has_child() => return KeysOrdering::Differ(Ordering::Less),
+            (true, false) => return KeysOrdering::RightPrefix,
+            (false, true) if self.has_child() => return KeysOrdering::Differ(Ordering::Greater),
+            (false, true) => return KeysOrdering::LeftPrefix,
+            _ => {}
+        }
+        let mut left = self.next_index(None);
+        let mut right = other.next_index(None);
+        loop {
+            let (l, r) = match (left, right) {
+                (None, None) => return KeysOrdering::Equal,
+                (None, Some(_)) => return KeysOrdering::LeftPrefix,
+                (Some(_), None) => return KeysOrdering::RightPrefix,
+                (Some(l), Some(r)) => (l, r),
+            };
+            if l != r {
+                return KeysOrdering::Differ(l.cmp(&r));
+            }
+            left = self.next_index(Some(l));
+            right = other.next_index(Some(r));
+            // When one child runs out first, the comparison resumes against the next child on that side.
+            match self.child(l).unwrap().keys_cmp(other.child(r).unwrap()) {
+                KeysOrdering::Equal => {}
+                KeysOrdering::Differ(ordering) => return KeysOrdering::Differ(ordering),
+                KeysOrdering::LeftPrefix if left.is_some() => return KeysOrdering::Differ(Ordering::Greater),
+                KeysOrdering::LeftPrefix => return KeysOrdering::LeftPrefix,
+                KeysOrdering::RightPrefix if right.is_some() => return KeysOrdering::Differ(Ordering::Less),
+                KeysOrdering::RightPrefix => return KeysOrdering::RightPrefix,
+            }
+        }
+    }
+
+    pub(crate) fn clone_subtree(&self) -> TrieNode<T, N>
+    where
+        T: Clone,
+    {
+        let mut node = TrieNode::with_layout(self.layout());
+        node.value = self.value.clone();
+        node.count = self.count;
+        for index in self.child_indices() {
+            node.child_set(index, self.child(index).unwrap().clone_subtree());
+        }
+        node
+    }
+
+    pub(crate) fn cached_count(&self) -> usize {
+        self.count
+    }
+
+    pub(crate) fn set_cached_count(&mut self, count: usize) {
+        self.count = count;
+    }
+
+    // Recomputes the cached counts of this node and the `levels - 1` levels below it.
+    pub(crate) fn refresh_counts(&mut self, levels: usize) -> usize {
+        if levels == 0 {
+            return self.count_values();
         }
-        self.next[index].insert(Box::new(node))
+        let mut count = usize::from(self.value.is_some());
+        let indices: Vec<usize> = self.child_indices().collect();
+        for index in indices {
+            count += self.child_mut(index).unwrap().refresh_counts(levels - 1);
+        }
+        self.count = count;
+        count
     }
 
-    pub(crate) fn child_iter_from(&self, index: usize) -> TrieNodeChildIterator<T, N> {
+    // Bytes held by this node's own allocation (its box) and its child table, not counting the
+    // children themselves or anything the value owns.
+    pub(crate) fn node_bytes(&self) -> usize {
+        let table = match &self.next {
+            Children::Dense(None) | Children::Full(None) => 0,
+            Children::Dense(Some(_)) | Children::Full(Some(_)) => mem::size_of::<ChildSlots<T, N>>(),
+            Children::Packed(next) | Children::Indexed(next) => next.capacity() * mem::size_of::<Box<TrieNode<T, N>>>(),
+            Children::Sorted(next) | Children::Ranked(next) | Children::Small(next) => {
+                next.capacity() * mem::size_of::<(usize, Box<TrieNode<T, N>>)>()
+            }
+        };
+        mem::size_of::<TrieNode<T, N>>() + table
+    }
+
+    // (values, nodes, estimated bytes) for the whole subtree, using `node_bytes` per node.
+    pub(crate) fn footprint(&self) -> (usize, usize, usize) {
+        let (mut values, mut nodes, mut bytes) = (0, 0, 0);
+        let mut stack = vec![self];
+        while let Some(node) = stack.pop() {
+            values += usize::from(node.value.is_some());
+            nodes += 1;
+            bytes += node.node_bytes();
+            stack.extend(node.child_indices().map(|index| node.child(index).unwrap()));
+        }
+        (values, nodes, bytes)
+    }
+
+    // Counts values while visiting at most `budget` nodes; the flag says whether the walk finished.
+    pub(crate) fn count_values_within(&self, budget: usize) -> (usize, bool) {
+        let mut count = 0;
+        let mut visited = 0;
+        let mut stack = vec![self];
+        while let Some(node) = stack.pop() {
+            if visited == budget {
+                return (count, false);
+            }
+            visited += 1;
+            count += usize::from(node.value.is_some());
+            stack.extend(node.child_indices().map(|index| node.child(index).unwrap()));
+        }
+        (count, true)
+    }
+
+    pub(crate) fn count_values(&self) -> usize {
+        let mut count = 0;
+        let mut stack = vec![self];
+        while let Some(node) = stack.pop() {
+            count += usize::from(node.value.is_some());
+            stack.extend(node.child_indices().map(|index| node.child(index).unwrap()));
+        }
+        count
+    }
+
+    pub(crate) fn visit_values_mut(&mut self, mut f: impl FnMut(&mut T)) {
+        let mut stack = vec![self];
+        while let Some(node) = stack.pop() {
+            if let Some(val) = node.value.as_mut() {
+                f(val);
+            }
+            match &mut node.next {
+                Children::Dense(None) | Children::Full(None) => {}
+                Children::Dense(Some(next)) | Children::Full(Some(next)) => stack.extend(next.iter_mut().flatten().map(Box::as_mut)),
+                Children::Packed(next) | Children::Indexed(next) => stack.extend(next.iter_mut().map(Box::as_mut)),
+                Children::Sorted(next) | Children::Ranked(next) | Children::Small(next) => {
+                    stack.extend(next.iter_mut().map(|(_, child)| child.as_mut()));
+                }
+            }
+        }
+    }
+
+    // Drops the values `keep` rejects, in path order, along with the subtrees left without values,
+    // and returns how many values were dropped. `path` is the path to this node.
+    pub(crate) fn retain_paths(&mut self, path: &mut Vec<usize>, keep: &mut impl FnMut(&[usize], &mut T) -> bool) -> usize {
+        let mut removed = 0;
+        if let Some(val) = self.value.as_mut() {
+            if !keep(path, val) {
+                self.value = None;
+                removed += 1;
+            }
+        }
+        for index in self.child_indices().collect::<Vec<_>>() {
+            let child = self.child_mut(index).unwrap();
+            path.push(index);
+            removed += child.retain_paths(path, keep);
+            path.pop();
+            if child.value().is_none() && !child.has_child() {
+                self.child_take(index);
+            }
+        }
+        removed
+    }
+
+    // Removes the subtrees below this node that hold no values, returning the number of nodes freed.
+    pub(crate) fn prune_hollow(&mut self) -> usize {
+        let mut freed = 0;
+        for index in self.child_indices().collect::<Vec<_>>() {
+            let child = self.child_mut(index).unwrap();
+            freed += child.prune_hollow();
+            if child.value().is_none() && !child.has_child() {
+                self.child_take(index);
+                freed += 1;
+            }
+        }
+        freed
+    }
+
+    // Calls `f` with the path and value of every value-bearing node, in path order.
+    pub(crate) fn visit_paths<'a>(&'a self, mut f: impl FnMut(&[usize], &'a T)) {
+        walk_paths(self, &mut Vec::new(), &mut f);
+    }
+
+    // Consumes the subtree, calling `f` with the path and value of every value-bearing node in path order.
+    pub(crate) fn drain_paths(self, mut f: impl FnMut(&[usize], T)) {
+        fn walk<T, const N: usize>(node: TrieNode<T, N>, path: &mut Vec<usize>, f: &mut impl FnMut(&[usize], T)) {
+            let (value, children) = node.into_parts();
+            if let Some(val) = value {
+                f(path, val);
+            }
+            for (index, child) in children {
+                path.push(index);
+                walk(child, path, f);
+                path.pop();
+            }
+        }
+        walk(self, &mut Vec::new(), &mut f);
+    }
+
+    // Moves `source` into this empty node, allocating every node in preorder and child tables at
+    // their exact size, so a later walk in path order reads memory roughly front to back.
+    pub(crate) fn fill_from(&mut self, source: TrieNode<T, N>) {
+        self.count = source.count;
+        let ranking = match &source.next {
+            Children::Ranked(next) => Some(next.iter().map(|(i, _)| *i).collect::<Vec<_>>()),
+            _ => None,
+        };
+        let (value, children) = source.into_parts();
+        self.value = value;
+        self.readapt(children.len());
+        match &mut self.next {
+            Children::Dense(_) | Children::Full(_) => {}
+            Children::Packed(next) | Children::Indexed(next) => next.reserve_exact(children.len()),
+            Children::Sorted(next) | Children::Ranked(next) | Children::Small(next) => next.reserve_exact(children.len()),
+        }
+        for (index, child) in children {
+            let slot = self.child_set(index, TrieNode::with_layout(child.layout()));
+            slot.fill_from(child);
+        }
+        if let Some(ranking) = ranking {
+            self.rank_children(|index| ranking.len() - ranking.iter().position(|&i| i == index).unwrap());
+        }
+    }
+
+    // Child indices in storage order, which differs from index order only for ranked children.
+    #[cfg(test)]
+    pub(crate) fn stored_order(&self) -> Vec<usize> {
+        match &self.next {
+            Children::Ranked(next) => next.iter().map(|(i, _)| *i).collect(),
+            _ => self.child_indices().collect(),
+        }
+    }
+
+    // For the sorted layout, puts the children in descending order of `heat`, ties by index, or
+    // back in plain index order when every child has zero heat. Other layouts are left alone.
+    pub(crate) fn rank_children(&mut self, heat: impl Fn(usize) -> usize) {
+        let mut next = match mem::replace(&mut self.next, Children::Dense(None)) {
+            Children::Sorted(next) | Children::Ranked(next) => next,
+            other => {
+                self.next = other;
+                return;
+            }
+        };
+        if next.iter().all(|(i, _)| heat(*i) == 0) {
+            next.sort_unstable_by_key(|(i, _)| *i);
+            self.next = Children::Sorted(next);
+        } else {
+            next.sort_unstable_by_key(|(i, _)| (std::cmp::Reverse(heat(*i)), *i));
+            self.next = Children::Ranked(next);
+        }
+    }
+
+    pub(crate) fn into_parts(mut self) -> (Option<T>, Vec<(usize, TrieNode<T, N>)>) {
+        let children = self.take_children().into_iter().map(|(index, child)| (index, *child)).collect();
+        (self.value, children)
+    }
+
+    // Empties the child table, returning the children in index order. The child bitmap is left
+    // as it was.
+    fn take_children(&mut self) -> Vec<(usize, Box<TrieNode<T, N>>)> {
+        match mem::replace(&mut self.next, Children::Dense(None)) {
+            Children::Dense(None) | Children::Full(None) => Vec::new(),
+            Children::Dense(Some(next)) | Children::Full(Some(next)) => next
+                .into_iter()
+                .enumerate()
+                .filter_map(|(index, child)| Some((index, child?)))
+                .collect(),
+            Children::Packed(next) | Children::Indexed(next) => self.child_bits.into_iter().zip(next).collect(),
+            Children::Sorted(next) | Children::Small(next) => next,
+            Children::Ranked(mut next) => {
+                next.sort_unstable_by_key(|(i, _)| *i);
+                next
+            }
+        }
+    }
+
+    // For the adaptive layout, moves the children into the representation suited to `count` of
+    // them once the count leaves the current one's range. Nodes shrink only at half the count
+    // they grew at, so a node hovering around a threshold doesn't convert back and forth.
+    fn readapt(&mut self, count: usize) {
+        let (small_max, packed_max) = if N <= BITMAP_SIZE { (SMALL_CHILDREN, N / 2) } else { (N / 4, N / 4) };
+        match &self.next {
+            Children::Small(_) if count > small_max => {}
+            Children::Indexed(_) if count > packed_max || count <= small_max / 2 => {}
+            Children::Full(_) if count <= packed_max / 2 => {}
+            _ => return,
+        }
+        let children = self.take_children();
+        self.next = if count > packed_max {
+            let mut slots = Box::new([const { None }; N]);
+            for (index, child) in children {
+                slots[index] = Some(child);
+            }
+            Children::Full(Some(slots))
+        } else if count > small_max {
+            Children::Indexed(children.into_iter().map(|(_, child)| child).collect())
+        } else {
+            Children::Small(children)
+        };
+    }
+
+    // Like `into_parts`, but borrowing, so a mutable walk can hold the value and children apart.
+    pub(crate) fn parts_mut(&mut self) -> (Option<&mut T>, Vec<(usize, &mut TrieNode<T, N>)>) {
+        let children = match &mut self.next {
+            Children::Dense(None) | Children::Full(None) => Vec::new(),
+            Children::Dense(Some(next)) | Children::Full(Some(next)) => next
+                .iter_mut()
+                .enumerate()
+                .filter_map(|(index, child)| Some((index, child.as_deref_mut()?)))
+                .collect(),
+            Children::Packed(next) | Children::Indexed(next) => {
+                self.child_bits.into_iter().zip(next).map(|(index, child)| (index, &mut **child)).collect()
+            }
+            Children::Sorted(next) | Children::Small(next) => next.iter_mut().map(|(index, child)| (*index, &mut **child)).collect(),
+            Children::Ranked(next) => {
+                let mut children: Vec<_> = next.iter_mut().map(|(index, child)| (*index, &mut **child)).collect();
+                children.sort_unstable_by_key(|(i, _)| *i);
+                children
+            }
+        };
+        (self.value.as_mut(), children)
+    }
+
+    pub(crate) fn child_iter_from(&self, index: usize) -> TrieNodeChildIterator<'_, T, N> {
         TrieNodeChildIterator {
             moved: false,
             index,
-            node: &self,
+            node: self,
+        }
+    }
+
+    // Position of `index` among the present children; only meaningful for the packed layout.
+    fn rank(&self, index: usize) -> usize {
+        if const { N <= BITMAP_SIZE } {
+            let below = (1u64 << index) - 1;
+            (*self.child_bits.as_value() & below).count_ones() as usize
+        } else {
+            0
+        }
+    }
+
+    fn put(&mut self, index: usize, node: Box<TrieNode<T, N>>) -> (Option<Box<TrieNode<T, N>>>, &mut TrieNode<T, N>) {
+        if self.layout() == NodeLayout::Adaptive && self.child(index).is_none() {
+            self.readapt(self.count_children() + 1);
+        }
+        let present = N <= BITMAP_SIZE && self.child_bits.get(index);
+        let rank = self.rank(index);
+        if const { N <= BITMAP_SIZE } {
+            self.child_bits.set(index, true);
+        }
+        match &mut self.next {
+            Children::Dense(next) | Children::Full(next) => {
+                let next = next.get_or_insert_with(|| Box::new([const { None }; N]));
+                let old = next[index].take();
+                (old, next[index].insert(node))
+            }
+            Children::Packed(next) | Children::Indexed(next) => {
+                let old = if present {
+                    Some(std::mem::replace(&mut next[rank], node))
+                } else {
+                    next.insert(rank, node);
+                    None
+                };
+                (old, &mut next[rank])
+            }
+            Children::Sorted(next) | Children::Small(next) => match next.binary_search_by_key(&index, |(i, _)| *i) {
+                Ok(pos) => (Some(std::mem::replace(&mut next[pos].1, node)), &mut next[pos].1),
+                Err(pos) => {
+                    next.insert(pos, (index, node));
+                    (None, &mut next[pos].1)
+                }
+            },
+            // New children start cold, at the back.
+            Children::Ranked(next) => match next.iter().position(|(i, _)| *i == index) {
+                Some(pos) => (Some(std::mem::replace(&mut next[pos].1, node)), &mut next[pos].1),
+                None => {
+                    next.push((index, node));
+                    let last = next.len() - 1;
+                    (None, &mut next[last].1)
+                }
+            },
         }
     }
 }
 
+// Calls `f` with the path (extending `path`) and value of every value-bearing node in path order.
+fn walk_paths<'a, T, const N: usize>(node: &'a TrieNode<T, N>, path: &mut Vec<usize>, f: &mut impl FnMut(&[usize], &'a T)) {
+    if let Some(val) = node.value() {
+        f(path, val);
+    }
+    let mut next = node.next_index(None);
+    while let Some(index) = next {
+        path.push(index);
+        walk_paths(node.child(index).unwrap(), path, f);
+        path.pop();
+        next = node.next_index(Some(index));
+    }
+}
+
 pub(crate) struct TrieNodeChildIterator<'a, T, const N: usize> {
     moved: bool,
     index: usize,
@@ -126,71 +680,33 @@ impl<'a, T, const N: usize> Iterator for TrieNodeChildIterator<'a, T, N> {
     type Item = &'a TrieNode<T, N>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if const { N <= BITMAP_SIZE } {
-            let my_index_opt = if !self.moved && self.index == 0 {
-                self.node.child_bits.first_index()
-            } else {
-                self.node.child_bits.next_index(self.index)
-            };
-            if let Some(index) = my_index_opt {
-                self.moved = true;
-                self.index = index;
-                self.node.child(self.index)
-            } else {
-                None
-            }
+        let my_index_opt = if !self.moved && self.index == 0 {
+            self.node.next_index(None)
         } else {
-            let mut i = self.index;
-            while i < N {
-                let child_opt = self.node.child(i);
-                if let Some(child) = child_opt {
-                    self.moved = true;
-                    self.index = i;
-                    return Some(child);
-                }
-                i += 1;
-            }
-            None
-        }
+            self.node.next_index(Some(self.index))
+        };
+        let index = my_index_opt?;
+        self.moved = true;
+        self.index = index;
+        self.node.child(self.index)
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        (0, Some(self.node.child_bits.len()))
+        (0, Some(self.node.count_children()))
     }
 }
 
-impl<'a, T, const N: usize> DoubleEndedIterator for TrieNodeChildIterator<'a, T, N> {
+impl<T, const N: usize> DoubleEndedIterator for TrieNodeChildIterator<'_, T, N> {
     fn next_back(&mut self) -> Option<Self::Item> {
-        if const { N <= BITMAP_SIZE } {
-            let my_index_opt = if !self.moved && self.index == 0 {
-                self.node.child_bits.last_index()
-            } else {
-                self.node.child_bits.prev_index(self.index)
-            };
-            if let Some(index) = my_index_opt {
-                self.moved = true;
-                self.index = index;
-                self.node.child(self.index)
-            } else {
-                None
-            }
+        let my_index_opt = if !self.moved && self.index == 0 {
+            self.node.prev_index(None)
         } else {
-            let mut i = if !self.moved && self.index == 0 {
-                N - 1
-            } else {
-                self.index - 1
-            };
-            while i < N {
-                let child_opt = self.node.child(i);
-                if let Some(child) = child_opt {
-                    self.moved = true;
-                    self.index = i;
-                    return Some(child);
-                }
-                i -= 1;
-            }
-            None
-        }
+            self.node.prev_index(Some(self.index))
+        };
+        let index = my_index_opt?;
+        self.moved = true;
+        self.index = index;
+        self.node.child(self.index)
     }
 }
 
@@ -209,7 +725,7 @@ impl<'a, T, const N: usize> IntoIterator for &'a TrieNode<T, N> {
 
 #[cfg(test)]
 mod test {
-    use crate::trie_node::TrieNode;
+    use crate::trie_node::{Children, NodeLayout, TrieNode};
 
     #[test]
     fn test_iterator_forward() {
@@ -344,4 +860,82 @@ mod test {
         assert_eq!(iter.next_back().unwrap().value(), Some(&4));
         assert!(iter.next_back().is_none());
     }
-}
\ No newline at end of file
+
+    fn check_layout<const N: usize>(layout: NodeLayout) {
+        let mut root = TrieNode::<usize, N>::with_layout(layout);
+        for index in [N - 1, 0, 7, 3] {
+            let mut child = TrieNode::<usize, N>::with_layout(layout);
+            child.value_replace(index);
+            root.child_set(index, child);
+        }
+        assert_eq!(root.count_children(), 4);
+        let values: Vec<usize> = root.into_iter().map(|node| *node.value().unwrap()).collect();
+        assert_eq!(values, vec![0, 3, 7, N - 1]);
+        let values: Vec<usize> = root.into_iter().rev().map(|node| *node.value().unwrap()).collect();
+        assert_eq!(values, vec![N - 1, 7, 3, 0]);
+
+        assert_eq!(root.child_take(3).unwrap().value(), Some(&3));
+        assert!(root.child_take(3).is_none());
+        assert!(root.child(3).is_none());
+        let mut child = TrieNode::<usize, N>::with_layout(layout);
+        child.value_replace(70);
+        assert_eq!(root.child_replace(7, child).unwrap().value(), Some(&7));
+        *root.child_mut(7).unwrap().value_mut().unwrap() += 1;
+        let values: Vec<usize> = root.child_iter_from(0).map(|node| *node.value().unwrap()).collect();
+        assert_eq!(values, vec![0, 71, N - 1]);
+        assert!(root.has_multiple_children());
+    }
+
+    #[test]
+    fn test_layouts() {
+        for layout in [NodeLayout::Dense, NodeLayout::Packed, NodeLayout::Sorted, NodeLayout::Adaptive] {
+            check_layout::<16>(layout);
+            check_layout::<64>(layout);
+            check_layout::<100>(layout);
+        }
+    }
+
+    #[test]
+    fn adaptive_growth() {
+        let mut root = TrieNode::<usize, 16>::with_layout(NodeLayout::Adaptive);
+        let mut stages = Vec::new();
+        for index in (0..16).rev() {
+            let mut child = TrieNode::with_layout(NodeLayout::Adaptive);
+            child.value_replace(index);
+            root.child_set(index, child);
+            stages.push(match root.next {
+                Children::Small(_) => 's',
+                Children::Indexed(_) => 'i',
+                Children::Full(_) => 'f',
+                _ => unreachable!(),
+            });
+        }
+        assert_eq!(stages.into_iter().collect::<String>(), "ssssiiiiffffffff");
+        assert_eq!(root.layout(), NodeLayout::Adaptive);
+        assert_eq!(root.child(9).unwrap().value(), Some(&9));
+
+        for index in 0..12 {
+            assert_eq!(root.child_take(index).unwrap().value(), Some(&index));
+        }
+        assert!(matches!(root.next, Children::Small(_)));
+        for index in 0..3 {
+            root.child_set(index, TrieNode::new());
+        }
+        assert!(matches!(root.next, Children::Indexed(_)));
+        assert_eq!(root.child_indices().collect::<Vec<_>>(), vec![0, 1, 2, 12, 13, 14, 15]);
+        assert_eq!(root.child_take(0).map(|node| node.value().is_none()), Some(true));
+        assert!(matches!(root.next, Children::Indexed(_)));
+
+        let mut wide = TrieNode::<usize, 256>::with_layout(NodeLayout::Adaptive);
+        for index in 0..65 {
+            wide.child_set(index * 3, TrieNode::new());
+        }
+        assert!(matches!(wide.next, Children::Full(_)));
+        assert_eq!(wide.next_index(Some(3)), Some(6));
+        for index in 0..33 {
+            wide.child_take(index * 3);
+        }
+        assert!(matches!(wide.next, Children::Small(_)));
+        assert_eq!(wide.count_children(), 32);
+    }
+}
@@ -0,0 +1,265 @@
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+
+use crate::codec::ValueCodec;
+use crate::trie::{Trie, TriePathBuilder, TriePathDecoder};
+use crate::trie_node::TrieNode;
+
+// How many keys `self_test` round-trips through decode and build, spread evenly over the trie.
+const SELF_TEST_SAMPLE: usize = 1024;
+
+/// A key-value store a trie can be checked against with `Trie::verify_against`.
+pub trait Lookup<K: ?Sized, T> {
+    fn lookup(&self, key: &K) -> Option<&T>;
+    fn for_each_entry(&self, f: &mut dyn FnMut(&K, &T));
+}
+
+impl<K: ?Sized + Ord, O: Borrow<K> + Ord, T> Lookup<K, T> for BTreeMap<O, T> {
+    fn lookup(&self, key: &K) -> Option<&T> {
+        self.get(key)
+    }
+
+    fn for_each_entry(&self, f: &mut dyn FnMut(&K, &T)) {
+        for (key, val) in self {
+            f(key.borrow(), val);
+        }
+    }
+}
+
+impl<K: ?Sized + Hash + Eq, O: Borrow<K> + Hash + Eq, T> Lookup<K, T> for HashMap<O, T> {
+    fn lookup(&self, key: &K) -> Option<&T> {
+        self.get(key)
+    }
+
+    fn for_each_entry(&self, f: &mut dyn FnMut(&K, &T)) {
+        for (key, val) in self {
+            f(key.borrow(), val);
+        }
+    }
+}
+
+/// One disagreement found by `Trie::verify_against`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Mismatch<O> {
+    MissingFromOracle(O),
+    MissingFromTrie(O),
+    ValueDiffers(O),
+}
+
+/// The outcome of `Trie::verify_against`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Verification<O> {
+    pub trie_entries: usize,
+    pub oracle_entries: usize,
+    // Every mismatch found, including those past the reporting limit.
+    pub mismatch_count: usize,
+    // The first mismatches, up to the limit given to `verify_against`.
+    pub mismatches: Vec<Mismatch<O>>,
+}
+
+impl<O> Verification<O> {
+    #[must_use]
+    pub fn is_consistent(&self) -> bool {
+        self.mismatch_count == 0
+    }
+}
+
+/// The outcome of `Trie::self_test`, for health checks of long-lived tries. Every field after
+/// `entries_iterated` counts problems and is zero for a healthy trie.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SelfTestReport {
+    pub len: usize,
+    pub values_counted: usize,
+    pub entries_iterated: usize,
+    // Consecutive entries the iterator did not yield in strictly increasing path order.
+    pub out_of_order: usize,
+    // Nodes above the prefix count depth whose cached count is wrong.
+    pub stale_prefix_counts: usize,
+    // Valueless leaves on a trie that `take` has not touched since the last `prune`.
+    pub dangling_nodes: usize,
+    pub keys_sampled: usize,
+    // Sampled keys that failed to decode, encode back to the same path or be found again.
+    pub round_trip_failures: usize,
+    // Whether a save and load gave back the same entries; only run by `self_test_snapshot`.
+    pub snapshot_round_trip: Option<bool>,
+}
+
+impl SelfTestReport {
+    #[must_use]
+    pub fn is_healthy(&self) -> bool {
+        self.values_counted == self.len
+            && self.entries_iterated == self.len
+            && self.out_of_order == 0
+            && self.stale_prefix_counts == 0
+            && self.dangling_nodes == 0
+            && self.round_trip_failures == 0
+            && self.snapshot_round_trip != Some(false)
+    }
+}
+
+impl<K: ?Sized + ToOwned, T, const N: usize> Trie<K, T, N>
+where
+    Trie<K, T, N>: TriePathBuilder<K> + TriePathDecoder<K>,
+{
+    // Runs the internal consistency checks: `len` against the stored values and the iterator,
+    // iteration order, cached prefix counts, leftover nodes, and a sample of keys decoded, encoded
+    // back and looked up again. Walks the whole trie a few times.
+    #[must_use]
+    pub fn self_test(&self) -> SelfTestReport {
+        let mut report = SelfTestReport {
+            len: self.len,
+            values_counted: self.root.count_values(),
+            ..SelfTestReport::default()
+        };
+        check_nodes(&self.root, 0, self.count_depth, &mut report);
+        if self.hollow {
+            report.dangling_nodes = 0;
+        }
+
+        let stride = (self.len / SELF_TEST_SAMPLE).max(1);
+        let mut previous: Option<Vec<usize>> = None;
+        for (i, (path, _)) in self.iter_paths().enumerate() {
+            report.entries_iterated += 1;
+            if previous.as_ref().is_some_and(|previous| *previous >= path) {
+                report.out_of_order += 1;
+            }
+            if i % stride == 0 {
+                report.keys_sampled += 1;
+                let round_trips = <Trie<K, T, N> as TriePathDecoder<K>>::decode_path(&path).is_ok_and(|key| {
+                    <Trie<K, T, N> as TriePathBuilder<K>>::build_path(key.borrow()) == path && self.get(key.borrow()).is_some()
+                });
+                if !round_trips {
+                    report.round_trip_failures += 1;
+                }
+            }
+            previous = Some(path);
+        }
+        report
+    }
+
+    // `self_test`, plus saving a snapshot with `codec` and checking that loading it gives back
+    // equal entries.
+    #[must_use]
+    pub fn self_test_snapshot<C: ValueCodec<T>>(&self, codec: &C) -> SelfTestReport
+    where
+        T: PartialEq,
+    {
+        let mut report = self.self_test();
+        let mut bytes = Vec::new();
+        let restored = self.save(&mut bytes, codec).and_then(|_| Trie::<K, T, N>::load(bytes.as_slice(), codec));
+        report.snapshot_round_trip = Some(restored.is_ok_and(|restored| restored.iter_paths().eq(self.iter_paths())));
+        report
+    }
+}
+
+// Counts stale cached counts above `count_depth` and valueless leaves below the root.
+fn check_nodes<T, const N: usize>(node: &TrieNode<T, N>, depth: usize, count_depth: usize, report: &mut SelfTestReport) {
+    if depth < count_depth && node.cached_count() != node.count_values() {
+        report.stale_prefix_counts += 1;
+    }
+    if depth > 0 && node.value().is_none() && !node.has_child() {
+        report.dangling_nodes += 1;
+    }
+    for index in node.child_indices() {
+        check_nodes(node.child(index).unwrap(), depth + 1, count_depth, report);
+    }
+}
+
+impl<K: ?Sized + ToOwned, T: PartialEq, const N: usize> Trie<K, T, N>
+where
+    Trie<K, T, N>: TriePathBuilder<K> + TriePathDecoder<K>,
+{
+    // Checks both directions: every trie entry must be in `oracle` with an equal value, and every
+    // oracle key must be in the trie. Entries are streamed from each side and only the first
+    // `max_report` mismatches are kept, so memory stays bounded however far the two drift apart.
+    pub fn verify_against<C: Lookup<K, T> + ?Sized>(&self, oracle: &C, max_report: usize) -> Verification<K::Owned> {
+        let mut verification = Verification {
+            trie_entries: 0,
+            oracle_entries: 0,
+            mismatch_count: 0,
+            mismatches: Vec::new(),
+        };
+        let mut record = |verification: &mut Verification<K::Owned>, mismatch: Mismatch<K::Owned>| {
+            verification.mismatch_count += 1;
+            if verification.mismatches.len() < max_report {
+                verification.mismatches.push(mismatch);
+            }
+        };
+        self.root.visit_paths(|path, val| {
+            verification.trie_entries += 1;
+            let key = <Trie<K, T, N> as TriePathDecoder<K>>::decode_path(path).expect("trie holds a path that is not a valid key");
+            match oracle.lookup(key.borrow()) {
+                None => record(&mut verification, Mismatch::MissingFromOracle(key)),
+                Some(expected) if expected != val => record(&mut verification, Mismatch::ValueDiffers(key)),
+                Some(_) => {}
+            }
+        });
+        oracle.for_each_entry(&mut |key, _| {
+            verification.oracle_entries += 1;
+            if self.get(key).is_none() {
+                record(&mut verification, Mismatch::MissingFromTrie(key.to_owned()));
+            }
+        });
+        verification
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::codec::BytesCodec;
+    use crate::trie::Trie;
+    use crate::verify::Mismatch;
+
+    #[test]
+    fn both_ways() {
+        let mut trie: Trie<str, usize, 16> = Trie::new();
+        let mut oracle: BTreeMap<String, usize> = BTreeMap::new();
+        for (i, key) in ["a", "b", "c", "d"].iter().enumerate() {
+            trie.insert(key, i);
+            oracle.insert(key.to_string(), i);
+        }
+        assert!(trie.verify_against(&oracle, 10).is_consistent());
+
+        trie.insert("b", 10);
+        trie.insert("e", 4);
+        oracle.insert("f".to_string(), 5);
+        let verification = trie.verify_against(&oracle, 2);
+        assert_eq!((verification.trie_entries, verification.oracle_entries), (5, 5));
+        assert_eq!(verification.mismatch_count, 3);
+        assert_eq!(
+            verification.mismatches,
+            vec![Mismatch::ValueDiffers("b".to_string()), Mismatch::MissingFromOracle("e".to_string())]
+        );
+    }
+
+    #[test]
+    fn self_test() {
+        let mut trie: Trie<str, Vec<u8>, 16> = Trie::new();
+        trie.enable_prefix_counts(2);
+        for key in ["", "a", "ab", "b", "zz"] {
+            trie.insert(key, key.as_bytes().to_vec());
+        }
+        let report = trie.self_test_snapshot(&BytesCodec);
+        assert!(report.is_healthy(), "{report:?}");
+        assert_eq!((report.entries_iterated, report.keys_sampled, report.snapshot_round_trip), (5, 5, Some(true)));
+
+        let _ = trie.take("zz");
+        assert!(trie.self_test().is_healthy());
+        trie.hollow = false;
+        assert_eq!(trie.self_test().dangling_nodes, 1);
+        trie.prune();
+        trie.len += 1;
+        let report = trie.self_test();
+        assert!(!report.is_healthy());
+        assert_eq!((report.values_counted, report.len), (4, 5));
+
+        let child = trie.root.child_mut(6).unwrap();
+        child.set_cached_count(child.cached_count() + 3);
+        assert_eq!(trie.self_test().stale_prefix_counts, 1);
+        assert_eq!(trie.recount(), 4);
+        assert!(trie.self_test().is_healthy());
+    }
+}
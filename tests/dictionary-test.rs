@@ -1,10 +1,19 @@
 #![cfg(test)]
 
-use hardly_trie::trie::Trie;
-use std::collections::HashMap;
+use hardly_trie::compressed::CompressedTrie;
+use hardly_trie::trie::{NodeLayout, Trie, TriePathBuilder};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+use fst::automaton::{Automaton, Str};
+use fst::{IntoStreamer, Map as FstMap, Streamer};
+use patricia_tree::StringPatriciaMap;
+use qp_trie::Trie as QpTrie;
 use radix_trie::{Trie as RxTrie, TrieCommon};
 
 const CONTENTS: &str = include_str!("./data/wordlist/wordlist-20210729.txt");
+const PREFIX_QUERIES: usize = 50;
 
 use std::time::Instant;
 
@@ -15,9 +24,14 @@ trait Collection {
     fn size(&self) -> usize;
 
     fn remove(&mut self, key: &str) -> Option<String>;
+
+    fn count_prefix(&self, prefix: &str) -> usize;
 }
 
-impl Collection for Trie<str, String, 16> {
+impl<const N: usize> Collection for Trie<str, String, N>
+where
+    Trie<str, String, N>: TriePathBuilder<str>,
+{
     fn add(&mut self, key: &str) -> Option<String> {
         self.insert(key, key.into())
     }
@@ -31,26 +45,118 @@ impl Collection for Trie<str, String, 16> {
     }
 
     fn remove(&mut self, key: &str) -> Option<String> {
-        self.delete(key)
+        Trie::remove(self, key)
+    }
+
+    fn count_prefix(&self, prefix: &str) -> usize {
+        Trie::count_prefix(self, prefix)
     }
 }
 
 #[test]
 fn insert_all_trie() {
-    let mut trie = Trie::new();
-    let now = Instant::now();
+    bench("hardly_trie::Trie", &mut Trie::<str, String, 16>::new());
+}
+
+#[test]
+fn insert_all_trie_layouts() {
+    for layout in [NodeLayout::Dense, NodeLayout::Packed, NodeLayout::Sorted, NodeLayout::Adaptive] {
+        bench(&format!("hardly_trie::Trie ({layout:?})"), &mut Trie::<str, String, 16>::with_layout(layout));
+    }
+}
+
+#[test]
+fn insert_all_byte_trie() {
+    for layout in [NodeLayout::Sorted, NodeLayout::Adaptive, NodeLayout::Dense] {
+        bench(&format!("hardly_trie::Trie<_, _, 256> ({layout:?})"), &mut Trie::<str, String, 256>::with_layout(layout));
+    }
+}
+
+impl Collection for CompressedTrie<str, String, 16> {
+    fn add(&mut self, key: &str) -> Option<String> {
+        self.insert(key, key.into())
+    }
+
+    fn find(&self, key: &str) -> Option<&String> {
+        self.get(key)
+    }
+
+    fn size(&self) -> usize {
+        self.len()
+    }
+
+    fn remove(&mut self, key: &str) -> Option<String> {
+        self.remove(key)
+    }
+
+    fn count_prefix(&self, prefix: &str) -> usize {
+        CompressedTrie::count_prefix(self, prefix)
+    }
+}
+
+#[test]
+fn insert_all_compressed_trie() {
+    bench("hardly_trie::CompressedTrie", &mut CompressedTrie::new());
+}
+
+// Full iteration after deleting and reinserting half the words, before and after optimize_layout.
+#[test]
+fn iterate_after_churn_trie() {
+    let mut trie: Trie<str, String, 16> = Trie::new();
     insert_all(&mut trie);
-    let elapsed_insert = now.elapsed();
+    for word in words().step_by(2) {
+        assert!(trie.remove(word).is_some());
+    }
+    for word in words().step_by(2) {
+        trie.insert(word, word.into());
+    }
+    let now = Instant::now();
+    let before = trie.iter_paths().count();
+    let elapsed_churned = now.elapsed();
     let now = Instant::now();
+    trie.optimize_layout();
+    let elapsed_optimize = now.elapsed();
+    let now = Instant::now();
+    let after = trie.iter_paths().count();
+    let elapsed_optimized = now.elapsed();
+    assert_eq!((before, after), (trie.len(), trie.len()));
     find_all(&trie);
-    let elapsed_find = now.elapsed();
+    report(
+        "hardly_trie::Trie (churned)",
+        trie.len(),
+        &[("iter_churned", elapsed_churned), ("optimize_layout", elapsed_optimize), ("iter_optimized", elapsed_optimized)],
+    );
+}
+
+// Edit-distance search over the wordlist, checked against a brute-force scan of every word.
+#[test]
+fn search_within_distance_trie() {
+    let mut trie: Trie<str, String, 16> = Trie::new();
+    insert_all(&mut trie);
+    let queries: Vec<String> = words().step_by(1000).map(|word| format!("{word}x")).collect();
     let now = Instant::now();
-    remove_all(&mut trie);
-    let elapsed_remove = now.elapsed();
-    println!("hardly_trie::Trie insert: {:?} - size: {}", elapsed_insert, trie.len());
-    println!("hardly_trie::Trie find: {elapsed_find:?}");
-    println!("hardly_trie::Trie remove_all: {elapsed_remove:?}");
-    println!("hardly_trie::Trie total: {:?}\n", elapsed_insert + elapsed_find + elapsed_remove);
+    let found: Vec<Vec<String>> = queries
+        .iter()
+        .map(|query| trie.search_within_distance(query, 2).into_iter().map(|(key, _, _)| key).collect())
+        .collect();
+    let elapsed = now.elapsed();
+    for (query, found) in queries.iter().zip(&found) {
+        let expected: BTreeSet<String> = words().filter(|word| levenshtein(query.as_bytes(), word.as_bytes()) <= 2).map(String::from).collect();
+        assert_eq!(found.iter().cloned().collect::<BTreeSet<_>>(), expected, "{query}");
+    }
+    report("hardly_trie::Trie (fuzzy)", trie.len(), &[("search_within_distance", elapsed)]);
+}
+
+fn levenshtein(a: &[u8], b: &[u8]) -> usize {
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for &x in a {
+        let mut next = vec![row[0] + 1];
+        for (j, &y) in b.iter().enumerate() {
+            next.push((row[j] + usize::from(x != y)).min(row[j + 1] + 1).min(next[j] + 1));
+        }
+        row = next;
+    }
+    row[b.len()]
 }
 
 impl Collection for HashMap<String, String> {
@@ -69,24 +175,15 @@ impl Collection for HashMap<String, String> {
     fn remove(&mut self, key: &str) -> Option<String> {
         self.remove(key)
     }
+
+    fn count_prefix(&self, prefix: &str) -> usize {
+        self.keys().filter(|key| key.starts_with(prefix)).count()
+    }
 }
 
 #[test]
 fn insert_all_hashmap() {
-    let mut hashmap = HashMap::new();
-    let now = Instant::now();
-    insert_all(&mut hashmap);
-    let elapsed_insert = now.elapsed();
-    let now = Instant::now();
-    find_all(&hashmap);
-    let elapsed_find = now.elapsed();
-    let now = Instant::now();
-    remove_all(&mut hashmap);
-    let elapsed_remove = now.elapsed();
-    println!("std::HashMap insert: {:?} - size: {}", elapsed_insert, hashmap.len());
-    println!("std::HashMap find: {elapsed_find:?}");
-    println!("std::HashMap remove_all: {elapsed_remove:?}");
-    println!("std::HashMap total: {:?}\n", elapsed_insert + elapsed_find + elapsed_remove);
+    bench("std::HashMap", &mut HashMap::new());
 }
 
 impl Collection for RxTrie<String, String> {
@@ -105,30 +202,158 @@ impl Collection for RxTrie<String, String> {
     fn remove(&mut self, key: &str) -> Option<String> {
         self.remove(key)
     }
+
+    // The raw descendant can hold keys that only share part of the prefix, so filter them out.
+    fn count_prefix(&self, prefix: &str) -> usize {
+        self.get_raw_descendant(prefix)
+            .map_or(0, |sub| sub.keys().filter(|key| key.starts_with(prefix)).count())
+    }
 }
 
 #[test]
 fn insert_all_radixtrie() {
-    let mut trie = RxTrie::new();
+    bench("radix_trie::Trie", &mut RxTrie::new());
+}
+
+impl Collection for StringPatriciaMap<String> {
+    fn add(&mut self, key: &str) -> Option<String> {
+        self.insert(key, key.into())
+    }
+
+    fn find(&self, key: &str) -> Option<&String> {
+        self.get(key)
+    }
+
+    fn size(&self) -> usize {
+        self.len()
+    }
+
+    fn remove(&mut self, key: &str) -> Option<String> {
+        self.remove(key)
+    }
+
+    fn count_prefix(&self, prefix: &str) -> usize {
+        self.iter_prefix(prefix).count()
+    }
+}
+
+#[test]
+fn insert_all_patricia_tree() {
+    bench("patricia_tree::StringPatriciaMap", &mut StringPatriciaMap::new());
+}
+
+// Keyed by the UTF-8 bytes, which qp-trie branches on a nibble at a time.
+impl Collection for QpTrie<Vec<u8>, String> {
+    fn add(&mut self, key: &str) -> Option<String> {
+        self.insert(key.as_bytes().to_vec(), key.into())
+    }
+
+    fn find(&self, key: &str) -> Option<&String> {
+        self.get(key.as_bytes())
+    }
+
+    fn size(&self) -> usize {
+        self.count()
+    }
+
+    fn remove(&mut self, key: &str) -> Option<String> {
+        self.remove(key.as_bytes())
+    }
+
+    fn count_prefix(&self, prefix: &str) -> usize {
+        self.iter_prefix(prefix.as_bytes()).count()
+    }
+}
+
+#[test]
+fn insert_all_qp_trie() {
+    bench("qp_trie::Trie", &mut QpTrie::new());
+}
+
+// An fst is built once from sorted keys and cannot be modified, so it has no `Collection` impl
+// and reports the build as its insert time and no remove time.
+#[test]
+fn insert_all_fst() {
     let now = Instant::now();
-    insert_all(&mut trie);
+    let keys: BTreeSet<&str> = words().collect();
+    let map = FstMap::from_iter(keys.iter().enumerate().map(|(i, key)| (key, i as u64))).unwrap();
     let elapsed_insert = now.elapsed();
     let now = Instant::now();
-    find_all(&trie);
+    assert!(words().all(|word| map.get(word).is_some()));
     let elapsed_find = now.elapsed();
     let now = Instant::now();
-    remove_all(&mut trie);
+    for prefix in prefixes() {
+        let mut stream = map.search(Str::new(prefix).starts_with()).into_stream();
+        let mut count = 0;
+        while stream.next().is_some() {
+            count += 1;
+        }
+        assert!(count > 0);
+    }
+    let elapsed_prefix = now.elapsed();
+    report(
+        "fst::Map",
+        map.len(),
+        &[("insert", elapsed_insert), ("find", elapsed_find), ("prefix", elapsed_prefix)],
+    );
+}
+
+fn bench<C: Collection>(name: &str, c: &mut C) {
+    let now = Instant::now();
+    insert_all(c);
+    let elapsed_insert = now.elapsed();
+    let size = c.size();
+    let now = Instant::now();
+    find_all(c);
+    let elapsed_find = now.elapsed();
+    let now = Instant::now();
+    prefix_all(c);
+    let elapsed_prefix = now.elapsed();
+    let now = Instant::now();
+    remove_all(c);
     let elapsed_remove = now.elapsed();
-    println!("radix_trie::Trie insert: {:?} - size: {}", elapsed_remove, trie.len());
-    println!("radix_trie::Trie find: {elapsed_remove:?}");
-    println!("radix_trie::Trie remove_all: {elapsed_remove:?}");
-    println!("radix_trie::Trie total: {:?}\n", elapsed_insert + elapsed_find + elapsed_remove);
+    report(
+        name,
+        size,
+        &[("insert", elapsed_insert), ("find", elapsed_find), ("prefix", elapsed_prefix), ("remove_all", elapsed_remove)],
+    );
 }
 
-fn insert_all<C: Collection>(c: &mut C) {
-    for line in CONTENTS.lines() {
+// Prints the timings and writes them as JSON to the test target directory, one file per collection.
+fn report(name: &str, size: usize, timings: &[(&str, Duration)]) {
+    let mut total = Duration::ZERO;
+    let mut ops = Vec::new();
+    for &(op, elapsed) in timings {
+        println!("{name} {op}: {elapsed:?}");
+        ops.push(format!("\"{op}\":{}", elapsed.as_nanos()));
+        total += elapsed;
+    }
+    println!("{name} total: {total:?} - size: {size}\n");
+
+    let json = format!("{{\"collection\":\"{name}\",\"size\":{size},\"nanos\":{{{}}}}}\n", ops.join(","));
+    let file_name: String = name.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect();
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("dictionary-{file_name}.json"));
+    fs::write(path, json).unwrap();
+}
+
+fn words() -> impl Iterator<Item = &'static str> {
+    CONTENTS.lines().map(|line| {
         let line = line.strip_prefix('"').unwrap_or(line);
-        let line = line.strip_suffix('"').unwrap_or(line);
+        line.strip_suffix('"').unwrap_or(line)
+    })
+}
+
+// An even sample of the distinct two-character word starts, each matching at least one word.
+fn prefixes() -> Vec<&'static str> {
+    let starts: BTreeSet<&str> = words()
+        .map(|word| word.char_indices().nth(2).map_or(word, |(end, _)| &word[..end]))
+        .collect();
+    let step = (starts.len() / PREFIX_QUERIES).max(1);
+    starts.into_iter().step_by(step).collect()
+}
+
+fn insert_all<C: Collection>(c: &mut C) {
+    for line in words() {
         if let Some(val) = c.add(line) {
             panic!("What? {line} <> {val}");
         }
@@ -138,9 +363,7 @@ fn insert_all<C: Collection>(c: &mut C) {
 fn find_all<C: Collection>(c: &C) {
     let mut found: usize = 0;
     let mut not_found: usize = 0;
-    for line in CONTENTS.lines() {
-        let line = line.strip_prefix('"').unwrap_or(line);
-        let line = line.strip_suffix('"').unwrap_or(line);
+    for line in words() {
         if c.find(line).is_some() {
             found += 1;
         } else {
@@ -151,13 +374,17 @@ fn find_all<C: Collection>(c: &C) {
     assert_eq!(found, c.size());
 }
 
+fn prefix_all<C: Collection>(c: &C) {
+    for prefix in prefixes() {
+        assert!(c.count_prefix(prefix) > 0);
+    }
+}
+
 fn remove_all<C: Collection>(c: &mut C) {
     let orig_size = c.size();
     let mut found: usize = 0;
     let mut not_found: usize = 0;
-    for line in CONTENTS.lines() {
-        let line = line.strip_prefix('"').unwrap_or(line);
-        let line = line.strip_suffix('"').unwrap_or(line);
+    for line in words() {
         if c.remove(line).is_some() {
             found += 1;
         } else {
@@ -167,5 +394,3 @@ fn remove_all<C: Collection>(c: &mut C) {
     assert_eq!(not_found, 0);
     assert_eq!(found, orig_size);
 }
-
-